@@ -1,11 +1,26 @@
+use mm_spd_did::{CanonicalizeOptions, SnapId, SnapSource};
 use serde_derive::{Deserialize, Serialize};
 
 use crate::error::AttTrError;
 
-#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+/// Kind of a peer or subject identifier, and how its key is encoded:
+///
+/// ```text
+/// PkhEth:    did:pkh:eth:0x<address>             - address
+/// Snap:      snap://0x<hex>                      - bytes
+/// PkhEip155: did:pkh:eip155:<chain id>:0x<address> - u64 chain id, address
+/// Key:       did:key:z<base58btc>                - multicodec-prefixed public key
+/// NpmSnap:   npm:[@<scope>/]<package>[@<version>][#<shasum>] - UTF-8 package spec
+/// LocalSnap: local:<http(s) url>                 - UTF-8 url
+/// ```
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Schema {
 	PkhEth,
 	Snap,
+	PkhEip155,
+	Key,
+	NpmSnap,
+	LocalSnap,
 }
 
 impl From<Schema> for u8 {
@@ -13,11 +28,15 @@ impl From<Schema> for u8 {
 		match value {
 			Schema::PkhEth => 0,
 			Schema::Snap => 1,
+			Schema::PkhEip155 => 2,
+			Schema::Key => 3,
+			Schema::NpmSnap => 4,
+			Schema::LocalSnap => 5,
 		}
 	}
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct Did {
 	pub(crate) schema: Schema,
 	pub(crate) key: Vec<u8>,
@@ -28,6 +47,32 @@ impl Did {
 		Self { schema, key }
 	}
 
+	/// Parse any supported peer or subject identifier.
+	pub fn parse(value: String) -> Result<Self, AttTrError> {
+		Self::parse_peer(value.clone()).or_else(|_| Self::parse_snap_id(value))
+	}
+
+	/// Parse the DID of a peer: `did:pkh:eth`, `did:pkh:eip155` or `did:key`.
+	pub fn parse_peer(value: String) -> Result<Self, AttTrError> {
+		match value.splitn(3, ':').collect::<Vec<_>>()[..] {
+			["did", "pkh", msid] if msid.starts_with("eth:") => Self::parse_pkh_eth(value),
+			["did", "pkh", _] => Self::parse_pkh_eip155(value),
+			["did", "key", _] => Self::parse_key(value),
+			_ => Err(AttTrError::ParseError),
+		}
+	}
+
+	/// Parse the id of a snap: `snap://0x<hex>`, `npm:<package>` or `local:<url>`.
+	pub fn parse_snap_id(value: String) -> Result<Self, AttTrError> {
+		if value.starts_with("npm:") {
+			Self::parse_npm(value)
+		} else if value.starts_with("local:") {
+			Self::parse_local(value)
+		} else {
+			Self::parse_snap(value)
+		}
+	}
+
 	pub fn parse_pkh_eth(value: String) -> Result<Self, AttTrError> {
 		let parts = value.split(':');
 		let part_slices: Vec<&str> = parts.into_iter().collect();
@@ -50,6 +95,37 @@ impl Did {
 		Ok(Self { schema, key })
 	}
 
+	pub fn parse_pkh_eip155(value: String) -> Result<Self, AttTrError> {
+		let part_slices: Vec<&str> = value.split(':').collect();
+		// 5 parts: did, pkh, eip155, [chain id], [address]
+		let (chain_id, addr) = match part_slices[..] {
+			["did", "pkh", "eip155", chain_id, addr] => (chain_id, addr),
+			_ => return Err(AttTrError::ParseError),
+		};
+		let chain_id: u64 = parse_decimal(chain_id)?;
+		let addr = addr.strip_prefix("0x").ok_or(AttTrError::ParseError)?;
+		let address = hex::decode(addr).map_err(|_| AttTrError::ParseError)?;
+		if address.len() != 20 {
+			return Err(AttTrError::ParseError);
+		}
+
+		let mut key = chain_id.to_be_bytes().to_vec();
+		key.extend_from_slice(&address);
+		Ok(Self { schema: Schema::PkhEip155, key })
+	}
+
+	pub fn parse_key(value: String) -> Result<Self, AttTrError> {
+		let multibase = value.strip_prefix("did:key:").ok_or(AttTrError::ParseError)?;
+		// Only base58btc, the multibase did:key requires.
+		let encoded = multibase.strip_prefix('z').ok_or(AttTrError::ParseError)?;
+		let key = bs58::decode(encoded).into_vec().map_err(|_| AttTrError::ParseError)?;
+		if key.is_empty() {
+			return Err(AttTrError::ParseError);
+		}
+
+		Ok(Self { schema: Schema::Key, key })
+	}
+
 	pub fn parse_snap(value: String) -> Result<Self, AttTrError> {
 		let parts = value.split("://");
 		let part_slices: Vec<&str> = parts.into_iter().collect();
@@ -68,18 +144,82 @@ impl Did {
 
 		Ok(Self { schema: Schema::Snap, key })
 	}
+
+	/// Parse an npm snap id, e.g. `npm:@consensys/foo` or `npm:@consensys/foo@1.2.0#<shasum>`.
+	/// Package names are case-insensitive on the registry, so they are lowercased.
+	/// The version and shasum are kept, as they are what the issuer signed.
+	pub fn parse_npm(value: String) -> Result<Self, AttTrError> {
+		let snap_id = Self::parse_snap_id_with_version(&value)?;
+		if !matches!(snap_id.source(), SnapSource::Npm(_)) {
+			return Err(AttTrError::ParseError);
+		}
+		let id = snap_id.to_string();
+		let key = id.strip_prefix("npm:").ok_or(AttTrError::ParseError)?;
+		Ok(Self { schema: Schema::NpmSnap, key: key.as_bytes().to_vec() })
+	}
+
+	/// Parse a local snap id, e.g. `local:http://localhost:8080`, without any trailing slash.
+	pub fn parse_local(value: String) -> Result<Self, AttTrError> {
+		let snap_id = Self::parse_snap_id_with_version(&value)?;
+		match snap_id.source() {
+			SnapSource::Local(url) => {
+				Ok(Self { schema: Schema::LocalSnap, key: url.as_bytes().to_vec() })
+			},
+			_ => Err(AttTrError::ParseError),
+		}
+	}
+
+	fn parse_snap_id_with_version(value: &str) -> Result<SnapId, AttTrError> {
+		let options = CanonicalizeOptions { keep_snap_version: true, ..Default::default() };
+		SnapId::parse(value, &options).map_err(|_| AttTrError::ParseError)
+	}
+
+	/// Encoding of the DID in signed messages: the schema, then the key.
+	///
+	/// The keys of `PkhEth` and `Snap` follow as they are, as they always have;
+	/// those of the later schemas are prefixed with their u32 big-endian length.
+	pub fn to_bytes(&self) -> Result<Vec<u8>, AttTrError> {
+		let mut bytes = vec![self.schema.into()];
+		if !matches!(self.schema, Schema::PkhEth | Schema::Snap) {
+			let len = u32::try_from(self.key.len()).map_err(|_| AttTrError::SerialisationError)?;
+			bytes.extend_from_slice(&len.to_be_bytes());
+		}
+		bytes.extend_from_slice(&self.key);
+		Ok(bytes)
+	}
 }
 
+/// Canonical form of the DID, e.g. with lowercase hex.
 impl From<Did> for String {
 	fn from(value: Did) -> Self {
-		let key = hex::encode(value.key);
 		match value.schema {
-			Schema::PkhEth => format!("did:pkh:eth:0x{}", key),
-			Schema::Snap => format!("snap://0x{}", key),
+			Schema::PkhEth => format!("did:pkh:eth:0x{}", hex::encode(value.key)),
+			Schema::Snap => format!("snap://0x{}", hex::encode(value.key)),
+			Schema::PkhEip155 => {
+				let (chain_id, address) = value.key.split_at(8.min(value.key.len()));
+				let mut chain_id_bytes = [0; 8];
+				chain_id_bytes[8 - chain_id.len()..].copy_from_slice(chain_id);
+				let chain_id = u64::from_be_bytes(chain_id_bytes);
+				format!("did:pkh:eip155:{}:0x{}", chain_id, hex::encode(address))
+			},
+			Schema::Key => format!("did:key:z{}", bs58::encode(value.key).into_string()),
+			Schema::NpmSnap => format!("npm:{}", String::from_utf8_lossy(&value.key)),
+			Schema::LocalSnap => format!("local:{}", String::from_utf8_lossy(&value.key)),
 		}
 	}
 }
 
+/// Decimal integer without sign or leading zeros.
+fn parse_decimal(value: &str) -> Result<u64, AttTrError> {
+	let is_canonical = !value.is_empty()
+		&& value.chars().all(|c| c.is_ascii_digit())
+		&& (value == "0" || !value.starts_with('0'));
+	if !is_canonical {
+		return Err(AttTrError::ParseError);
+	}
+	value.parse().map_err(|_| AttTrError::ParseError)
+}
+
 #[cfg(test)]
 mod test {
 	use crate::did::Schema;
@@ -100,4 +240,107 @@ mod test {
 
 		assert_eq!(did_string, did_new_string);
 	}
+
+	#[test]
+	fn should_parse_and_canonicalize_all_schemas() {
+		let cases = [
+			(
+				"did:pkh:eth:0x90F8bf6A479f320ead074411a4B0e7944Ea8c9C2",
+				"did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+				Schema::PkhEth,
+			),
+			(
+				"snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+				"snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+				Schema::Snap,
+			),
+			(
+				"did:pkh:eip155:137:0x90F8bf6A479f320ead074411a4B0e7944Ea8c9C2",
+				"did:pkh:eip155:137:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+				Schema::PkhEip155,
+			),
+			(
+				"did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+				"did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+				Schema::Key,
+			),
+			("npm:@Consensys/Foo", "npm:@consensys/foo", Schema::NpmSnap),
+			("npm:foo-snap", "npm:foo-snap", Schema::NpmSnap),
+			(
+				"npm:Foo-Snap@1.2.0#rNyfINgNh161cBmUop+F7xlE+xEJQuIlkbnL1PZ0WcI=",
+				"npm:foo-snap@1.2.0#rNyfINgNh161cBmUop+F7xlE+xEJQuIlkbnL1PZ0WcI=",
+				Schema::NpmSnap,
+			),
+			(
+				"local:HTTP://localhost:8080/",
+				"local:http://localhost:8080",
+				Schema::LocalSnap,
+			),
+			(
+				"local:https://LocalHost:8080/Snap",
+				"local:https://LocalHost:8080/Snap",
+				Schema::LocalSnap,
+			),
+		];
+		for (value, canonical, schema) in cases {
+			let did = Did::parse(value.to_string()).unwrap();
+			assert_eq!(did.schema, schema, "{}", value);
+			let did_string: String = did.clone().into();
+			assert_eq!(did_string, canonical);
+			assert_eq!(Did::parse(did_string).unwrap(), did);
+		}
+
+		let did =
+			Did::parse_peer("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into());
+		// Ed25519 public key multicodec.
+		assert_eq!(did.unwrap().key[..2], [0xed, 0x01]);
+	}
+
+	#[test]
+	fn should_reject_invalid_ids() {
+		let invalid = [
+			"did:pkh:eip155:01:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+			"did:pkh:eip155:1:0x90f8bf6a479f320ead074411a4b0e7944ea8c9",
+			"did:pkh:eip155:1:90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+			"did:pkh:cosmos:cosmoshub-3:cosmos1t2uflqwqe0fsj0shcfkrvpukewcw40yjj6hdc0",
+			"did:key:6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+			"did:key:z0OIl",
+			"did:web:example.com",
+			"npm:",
+			"npm:@consensys",
+			"npm:.foo",
+			"npm:foo bar",
+			"npm:foo@1.2",
+			"local:ftp://localhost",
+			"local:http://",
+			"snap://0xzz",
+		];
+		for value in invalid {
+			assert!(Did::parse(value.to_string()).is_err(), "{}", value);
+		}
+		assert!(Did::parse_peer("npm:foo".to_string()).is_err());
+		assert!(Did::parse_snap_id(
+			"did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".into()
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn should_encode_legacy_schemas_unchanged() {
+		let address = hex::decode("90f8bf6a479f320ead074411a4b0e7944ea8c9c2").unwrap();
+		let did = Did::new(Schema::PkhEth, address.clone());
+		assert_eq!(did.to_bytes().unwrap(), [&[0], address.as_slice()].concat());
+		let did = Did::new(Schema::Snap, address.clone());
+		assert_eq!(did.to_bytes().unwrap(), [&[1], address.as_slice()].concat());
+
+		let did = Did::parse("npm:foo".to_string()).unwrap();
+		assert_eq!(did.to_bytes().unwrap(), [4, 0, 0, 0, 3, b'f', b'o', b'o']);
+		let did = Did::parse(format!("did:pkh:eip155:1:0x{}", hex::encode(&address))).unwrap();
+		let chain_id = 1u64.to_be_bytes();
+		let key = [&chain_id[..], &address].concat();
+		assert_eq!(
+			did.to_bytes().unwrap(),
+			[&[2, 0, 0, 0, 28], key.as_slice()].concat()
+		);
+	}
 }
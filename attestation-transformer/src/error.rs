@@ -1,4 +1,7 @@
 use hex::FromHexError;
+use mm_spd_did::CanonicalizeDidError;
+use mm_spd_vc::error::VcError;
+use proto_buf::transformer::RejectionKind;
 use rocksdb::Error as RocksDbError;
 use secp256k1::Error as SecpError;
 use serde_json::Error;
@@ -29,10 +32,68 @@ pub enum AttTrError {
 
 	#[error("ParseError")]
 	ParseError,
+
+	#[error("DidError: {0}")]
+	DidError(CanonicalizeDidError),
+
+	#[error("UnknownSchema: {0}")]
+	UnknownSchema(u32),
+
+	#[error("UnknownSchemaKind: {0}")]
+	UnknownSchemaKind(String),
+
+	#[error("DuplicateSchema: {0}")]
+	DuplicateSchema(u32),
+
+	#[error("PolicyError: {0}")]
+	PolicyError(String),
+
+	#[error("UnsupportedMessageVersion: {0}")]
+	UnsupportedMessageVersion(u32),
+
+	#[error("NotYetValid: until {0}")]
+	NotYetValid(u64),
+
+	#[error("Expired: at {0}")]
+	Expired(u64),
+
+	#[error("DateError: {0}")]
+	DateError(String),
+
+	#[error("ProofError: {0}")]
+	ProofError(VcError),
+
+	#[error("ConfigError: {0}")]
+	ConfigError(VcError),
 }
 
 impl From<AttTrError> for tonic::Status {
 	fn from(value: AttTrError) -> Self {
-		Self::internal(format!("Internal error: {}", value))
+		match value {
+			AttTrError::UnknownSchema(_) => Self::invalid_argument(value.to_string()),
+			_ => Self::internal(format!("Internal error: {}", value)),
+		}
+	}
+}
+
+impl From<&AttTrError> for RejectionKind {
+	fn from(value: &AttTrError) -> Self {
+		match value {
+			AttTrError::SerdeError(_) => Self::Serde,
+			AttTrError::HexError(_) => Self::Hex,
+			AttTrError::SigVerificationError(_) => Self::Signature,
+			AttTrError::VerificationError => Self::IssuerMismatch,
+			AttTrError::ParseError | AttTrError::DidError(_) => Self::DidParse,
+			AttTrError::UnknownSchema(_) => Self::UnknownSchema,
+			AttTrError::PolicyError(_) => Self::Policy,
+			AttTrError::UnsupportedMessageVersion(_) => Self::MessageVersion,
+			AttTrError::NotYetValid(_) | AttTrError::Expired(_) | AttTrError::DateError(_) => {
+				Self::Validity
+			},
+			AttTrError::ProofError(VcError::SerdeError(_)) => Self::Serde,
+			AttTrError::ProofError(VcError::HexError(_)) => Self::Hex,
+			AttTrError::ProofError(_) => Self::Signature,
+			_ => Self::Other,
+		}
 	}
 }
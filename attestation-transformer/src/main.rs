@@ -1,34 +1,42 @@
+use std::env;
 use std::error::Error;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use futures::stream::iter;
+use mm_spd_did::CanonicalizeOptions;
 use rocksdb::{Options, DB};
-use serde_json::from_str;
 use tonic::transport::Channel;
 use tonic::{transport::Server, Request, Response, Status};
 
+use mm_spd_vc::schema::SchemaConfig;
 use proto_buf::combiner::linear_combiner_client::LinearCombinerClient;
 use proto_buf::indexer::indexer_client::IndexerClient;
-use proto_buf::indexer::{IndexerEvent, Query};
+use proto_buf::indexer::Query;
 use proto_buf::transformer::transformer_server::{Transformer, TransformerServer};
-use proto_buf::transformer::{EventBatch, EventResult, TermBatch, TermResult};
+use proto_buf::transformer::{
+	DeadLetterBatch, DeadLetterQuery, EventBatch, EventResult, RetryResult, TermBatch, TermResult,
+};
 
 use crate::error::AttTrError;
 use crate::managers::checkpoint::CheckpointManager;
+use crate::managers::dead_letter::DeadLetterManager;
+use crate::managers::latest::LatestBatch;
 use crate::managers::term::TermManager;
-use crate::schemas::security::SecurityReportSchema;
-use crate::schemas::status::StatusSchema;
-use crate::schemas::trust::TrustSchema;
-use crate::schemas::{IntoTerm, SchemaType};
-use crate::term::Term;
+use crate::policy::WeightPolicy;
+use crate::schemas::registry::SchemaRegistry;
 
 pub mod did;
 pub mod error;
 pub mod managers;
+pub mod policy;
 pub mod schemas;
 pub mod term;
 pub mod utils;
 
 const MAX_TERM_BATCH_SIZE: u32 = 1000;
+const MAX_DEAD_LETTER_BATCH_SIZE: u32 = 1000;
+const COLUMN_FAMILIES: [&str; 5] = ["checkpoint", "term", "latest", "dead_letter", "expiry"];
 const ATTESTATION_SOURCE_ADDRESS: &str = "0x1";
 const AUDIT_APPROVE_SCHEMA_ID: &str = "0x2";
 const AUDIT_DISAPPROVE_SCHEMA_ID: &str = "0x3";
@@ -39,44 +47,34 @@ struct TransformerService {
 	indexer_channel: Channel,
 	lt_channel: Channel,
 	db_url: String,
+	registry: Arc<SchemaRegistry>,
 }
 
 impl TransformerService {
 	fn new(
-		indexer_channel: Channel, lt_channel: Channel, db_url: &str,
+		indexer_channel: Channel, lt_channel: Channel, db_url: &str, registry: SchemaRegistry,
+		options: &CanonicalizeOptions,
 	) -> Result<Self, AttTrError> {
 		let mut opts = Options::default();
 		opts.create_missing_column_families(true);
 		opts.create_if_missing(true);
-		let db =
-			DB::open_cf(&opts, db_url, vec!["checkpoint", "term"]).map_err(AttTrError::DbError)?;
+		let db = DB::open_cf(&opts, db_url, COLUMN_FAMILIES).map_err(AttTrError::DbError)?;
 		CheckpointManager::init(&db)?;
+		let migrated = TermManager::migrate_terms(&db)?;
+		if migrated > 0 {
+			println!("Migrated {} legacy terms", migrated);
+		}
+		let migrated = migrate_latest_to_canonical_dids(&db, options)?;
+		if migrated > 0 {
+			println!("Migrated {} latest entries to canonical DIDs", migrated);
+		}
 
-		Ok(Self { indexer_channel, lt_channel, db_url: db_url.to_string() })
-	}
-
-	fn parse_event(event: IndexerEvent) -> Result<Vec<Term>, AttTrError> {
-		let schema_id = event.schema_id;
-		let schema_type = SchemaType::from(schema_id);
-		let terms = match schema_type {
-			SchemaType::SecurityCredential => {
-				let parsed_att: SecurityReportSchema =
-					from_str(&event.schema_value).map_err(AttTrError::SerdeError)?;
-				parsed_att.into_term(event.timestamp)?
-			},
-			SchemaType::StatusCredential => {
-				let parsed_att: StatusSchema =
-					from_str(&event.schema_value).map_err(AttTrError::SerdeError)?;
-				parsed_att.into_term(event.timestamp)?
-			},
-			SchemaType::TrustCredential => {
-				let parsed_att: TrustSchema =
-					from_str(&event.schema_value).map_err(AttTrError::SerdeError)?;
-				parsed_att.into_term(event.timestamp)?
-			},
-		};
-
-		Ok(terms)
+		Ok(Self {
+			indexer_channel,
+			lt_channel,
+			db_url: db_url.to_string(),
+			registry: Arc::new(registry),
+		})
 	}
 }
 
@@ -90,12 +88,8 @@ impl Transformer for TransformerService {
 			return Err(Status::invalid_argument("Invalid `size`."));
 		}
 
-		let db = DB::open_cf(
-			&Options::default(),
-			&self.db_url,
-			vec!["term", "checkpoint"],
-		)
-		.map_err(|e| Status::internal(format!("Internal error: {}", e)))?;
+		let db = DB::open_cf(&Options::default(), &self.db_url, COLUMN_FAMILIES)
+			.map_err(|e| Status::internal(format!("Internal error: {}", e)))?;
 
 		let (ch_offset, ct_offset) = CheckpointManager::read_checkpoint(&db)?;
 
@@ -113,26 +107,50 @@ impl Transformer for TransformerService {
 		let mut client = IndexerClient::new(self.indexer_channel.clone());
 		let mut response = client.subscribe(indexer_query).await?.into_inner();
 
+		// All the changes of the batch of events are written at once, along with the checkpoint.
+		let mut latest = LatestBatch::new(&db);
 		let mut terms = Vec::new();
+		let mut dead_letters = Vec::new();
+		let mut num_events = 0;
 		// ResponseStream
 		while let Ok(Some(res)) = response.message().await {
-			let parsed_terms = Self::parse_event(res)?;
-			terms.push(parsed_terms);
+			num_events += 1;
+			// A rejected event is dead-lettered, so that it doesn't hold back the checkpoint.
+			match self.registry.parse_event(&res) {
+				Ok(parsed_terms) => {
+					let kind = self.registry.kind(res.schema_id).unwrap_or_default();
+					terms.push(latest.supersede(kind, parsed_terms)?);
+				},
+				Err(e) => {
+					println!("Dead-lettering event {}: {}", res.id, e);
+					dead_letters.push((res, e));
+				},
+			}
+		}
+		println!("Received num events: {}", num_events);
+
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+		let expired = latest.expire(now)?;
+		if !expired.is_empty() {
+			println!("Expired num terms: {}", expired.len());
+			terms.push(expired);
 		}
-		println!("Received num events: {}", terms.len());
 		println!("Received terms: {:#?}", terms);
 
-		let num_new_term_groups =
-			u32::try_from(terms.len()).map_err(|_| AttTrError::SerialisationError)?;
-		let new_checkpoint = ch_offset + num_new_term_groups;
+		let new_checkpoint = ch_offset + num_events;
 
 		let (new_count, indexed_terms) = TermManager::get_indexed_terms(ct_offset, terms)
 			.map_err(|_| AttTrError::SerialisationError)?;
 
 		println!("Received num terms: {}", new_count);
 
-		TermManager::write_terms(&db, indexed_terms)?;
-		CheckpointManager::write_checkpoint(&db, new_checkpoint, new_count)?;
+		let mut batch = latest.into_batch()?;
+		for (event, e) in dead_letters {
+			DeadLetterManager::put_dead_letter(&db, &mut batch, event, &e)?;
+		}
+		TermManager::put_terms(&db, &mut batch, indexed_terms)?;
+		CheckpointManager::put_checkpoint(&db, &mut batch, new_checkpoint, new_count)?;
+		db.write(batch).map_err(AttTrError::DbError)?;
 
 		let event_result = EventResult { num_terms: new_count - ct_offset, total_count: new_count };
 		Ok(Response::new(event_result))
@@ -149,12 +167,8 @@ impl Transformer for TransformerService {
 			)));
 		}
 
-		let db = DB::open_cf(
-			&Options::default(),
-			&self.db_url,
-			vec!["term", "checkpoint"],
-		)
-		.map_err(|e| Status::internal(format!("Internal error: {}", e)))?;
+		let db = DB::open_cf(&Options::default(), &self.db_url, COLUMN_FAMILIES)
+			.map_err(|e| Status::internal(format!("Internal error: {}", e)))?;
 
 		let terms = TermManager::read_terms(&db, inner)?;
 		let num_terms = terms.len();
@@ -167,6 +181,115 @@ impl Transformer for TransformerService {
 
 		Ok(Response::new(res))
 	}
+
+	async fn list_dead_letters(
+		&self, request: Request<DeadLetterQuery>,
+	) -> Result<Response<DeadLetterBatch>, Status> {
+		let query = request.into_inner();
+		if query.size > MAX_DEAD_LETTER_BATCH_SIZE {
+			return Err(Status::invalid_argument(format!(
+				"Batch size too big. Max size: {}",
+				MAX_DEAD_LETTER_BATCH_SIZE
+			)));
+		}
+
+		let db = DB::open_cf(&Options::default(), &self.db_url, COLUMN_FAMILIES)
+			.map_err(|e| Status::internal(format!("Internal error: {}", e)))?;
+
+		let dead_letters = DeadLetterManager::read_dead_letters(&db, query)?;
+		Ok(Response::new(DeadLetterBatch { dead_letters }))
+	}
+
+	async fn retry_dead_letters(
+		&self, request: Request<DeadLetterQuery>,
+	) -> Result<Response<RetryResult>, Status> {
+		let query = request.into_inner();
+		if query.size > MAX_DEAD_LETTER_BATCH_SIZE {
+			return Err(Status::invalid_argument(format!(
+				"Batch size too big. Max size: {}",
+				MAX_DEAD_LETTER_BATCH_SIZE
+			)));
+		}
+
+		let db = DB::open_cf(&Options::default(), &self.db_url, COLUMN_FAMILIES)
+			.map_err(|e| Status::internal(format!("Internal error: {}", e)))?;
+
+		let (ch_offset, ct_offset) = CheckpointManager::read_checkpoint(&db)?;
+		let (num_retried, num_failed, new_count) =
+			retry_dead_letters(&db, &self.registry, query, ch_offset, ct_offset)?;
+
+		let res = RetryResult { num_retried, num_failed, num_terms: new_count - ct_offset };
+		Ok(Response::new(res))
+	}
+}
+
+/// Parse the dead-lettered events again and store the terms of the ones that pass,
+/// after the term with the id `ct_offset`.
+/// The ones still rejected are kept, with the new error.
+///
+/// Like new events, retried ones are superseded by the newer credentials stored meanwhile,
+/// and all the changes are written at once, along with the checkpoint.
+/// The event checkpoint `ch_offset` is already past the dead-lettered events.
+///
+/// Returns the number of the retried and the failed events, and the new term count.
+fn retry_dead_letters(
+	db: &DB, registry: &SchemaRegistry, query: DeadLetterQuery, ch_offset: u32, ct_offset: u32,
+) -> Result<(u32, u32, u32), AttTrError> {
+	let dead_letters = DeadLetterManager::read_dead_letters(db, query)?;
+
+	let mut latest = LatestBatch::new(db);
+	let mut terms = Vec::new();
+	let mut retried = Vec::new();
+	let mut failed = Vec::new();
+	for dead_letter in dead_letters {
+		let event = dead_letter.event.ok_or(AttTrError::SerialisationError)?;
+		match registry.parse_event(&event) {
+			Ok(parsed_terms) => {
+				let kind = registry.kind(event.schema_id).unwrap_or_default();
+				terms.push(latest.supersede(kind, parsed_terms)?);
+				retried.push(event.id);
+			},
+			Err(e) => failed.push((event, e)),
+		}
+	}
+
+	let (new_count, indexed_terms) = TermManager::get_indexed_terms(ct_offset, terms)
+		.map_err(|_| AttTrError::SerialisationError)?;
+	let mut batch = latest.into_batch()?;
+	TermManager::put_terms(db, &mut batch, indexed_terms)?;
+	// The dead letters are dropped along with storing their terms.
+	for id in &retried {
+		DeadLetterManager::delete_dead_letter(db, &mut batch, *id)?;
+	}
+	let num_failed = u32::try_from(failed.len()).map_err(|_| AttTrError::SerialisationError)?;
+	for (event, e) in failed {
+		DeadLetterManager::put_dead_letter(db, &mut batch, event, &e)?;
+	}
+	CheckpointManager::put_checkpoint(db, &mut batch, ch_offset, new_count)?;
+	db.write(batch).map_err(AttTrError::DbError)?;
+
+	let num_retried = u32::try_from(retried.len()).map_err(|_| AttTrError::SerialisationError)?;
+	Ok((num_retried, num_failed, new_count))
+}
+
+/// Re-key the latest entries by canonical DIDs, see [`LatestBatch::migrate_to_canonical_dids`].
+/// The revocations of the merged entries' terms are stored in the same batch.
+///
+/// Returns the number of re-keyed entries.
+fn migrate_latest_to_canonical_dids(
+	db: &DB, options: &CanonicalizeOptions,
+) -> Result<u32, AttTrError> {
+	let mut latest = LatestBatch::new(db);
+	let (revocations, num_rekeyed) = latest.migrate_to_canonical_dids(options)?;
+	let (ch_offset, ct_offset) = CheckpointManager::read_checkpoint(db)?;
+	let (new_count, indexed_terms) = TermManager::get_indexed_terms(ct_offset, vec![revocations])
+		.map_err(|_| AttTrError::SerialisationError)?;
+
+	let mut batch = latest.into_batch()?;
+	TermManager::put_terms(db, &mut batch, indexed_terms)?;
+	CheckpointManager::put_checkpoint(db, &mut batch, ch_offset, new_count)?;
+	db.write(batch).map_err(AttTrError::DbError)?;
+	Ok(num_rekeyed)
 }
 
 #[tokio::main]
@@ -174,7 +297,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
 	let indexer_channel = Channel::from_static("http://localhost:50050").connect().await?;
 	let lc_channel = Channel::from_static("http://localhost:50052").connect().await?;
 	let db_url = "att-tr-storage";
-	let tr_service = TransformerService::new(indexer_channel, lc_channel, db_url)?;
+	let schema_config = match env::var("SCHEMA_CONFIG_PATH") {
+		Ok(path) => SchemaConfig::from_file(path).map_err(AttTrError::ConfigError)?,
+		Err(_) => SchemaConfig::default(),
+	};
+	let policy = match env::var("WEIGHT_POLICY_PATH") {
+		Ok(path) => WeightPolicy::from_file(path)?,
+		Err(_) => WeightPolicy::default(),
+	};
+	println!("Weight policy version: {}", policy.version);
+	let registry = SchemaRegistry::from_config(&schema_config, &policy)?;
+	println!("Registered schemas: {:?}", registry);
+	let options = policy.snap_versions.canonicalize_options();
+	let tr_service =
+		TransformerService::new(indexer_channel, lc_channel, db_url, registry, &options)?;
 
 	let addr = "[::1]:50051".parse()?;
 	Server::builder().add_service(TransformerServer::new(tr_service)).serve(addr).await?;
@@ -189,6 +325,7 @@ mod test {
 	use sha3::{Digest, Keccak256};
 
 	use proto_buf::indexer::IndexerEvent;
+	use proto_buf::transformer::RejectionKind;
 
 	use crate::did::{Did, Schema};
 	use crate::schemas::status::{CredentialSubject, CurrentStatus, StatusSchema};
@@ -201,6 +338,10 @@ mod test {
 
 	use super::*;
 
+	fn registry() -> SchemaRegistry {
+		SchemaRegistry::from_config(&SchemaConfig::default(), &WeightPolicy::default()).unwrap()
+	}
+
 	impl StatusSchema {
 		pub fn generate(id: String, current_status: CurrentStatus) -> Self {
 			let did = Did::parse_snap(id.clone()).unwrap();
@@ -374,6 +515,57 @@ mod test {
 		}
 	}
 
+	#[test]
+	fn should_retry_dead_letters() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(&opts, "att-retry-test-storage", COLUMN_FAMILIES).unwrap();
+
+		let recipient = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
+		let status_schema = StatusSchema::generate(recipient, CurrentStatus::Endorsed);
+		let valid = IndexerEvent {
+			id: 4,
+			schema_id: 1,
+			schema_value: to_string(&status_schema).unwrap(),
+			timestamp: 10,
+		};
+		let malformed = IndexerEvent { id: 5, schema_value: "{}".to_string(), ..valid.clone() };
+
+		// Status credentials aren't registered yet.
+		let mut config = SchemaConfig::default();
+		config.schemas.retain(|s| s.id != 1);
+		let old_registry = SchemaRegistry::from_config(&config, &WeightPolicy::default()).unwrap();
+		for event in [&valid, &malformed] {
+			let e = old_registry.parse_event(event).unwrap_err();
+			DeadLetterManager::write_dead_letter(&db, event.clone(), &e).unwrap();
+		}
+
+		let query = DeadLetterQuery { start: 0, size: 10 };
+		let (num_retried, num_failed, new_count) =
+			retry_dead_letters(&db, &registry(), query.clone(), 6, 3).unwrap();
+		assert_eq!((num_retried, num_failed, new_count), (1, 1, 4));
+		assert_eq!(CheckpointManager::read_checkpoint(&db).unwrap(), (6, 4));
+
+		let term = TermManager::read_terms(&db, TermBatch { start: 3, size: 1 }).unwrap();
+		assert_eq!(
+			term[0].to,
+			"snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2"
+		);
+		let dead_letters = DeadLetterManager::read_dead_letters(&db, query).unwrap();
+		assert_eq!(dead_letters.len(), 1);
+		assert_eq!(dead_letters[0].event, Some(malformed));
+		assert_eq!(dead_letters[0].kind(), RejectionKind::Serde);
+
+		// A late copy of the event is superseded by the retried one.
+		let late = IndexerEvent { id: 6, timestamp: 5, ..valid };
+		DeadLetterManager::write_dead_letter(&db, late, &AttTrError::ParseError).unwrap();
+		let query = DeadLetterQuery { start: 6, size: 10 };
+		let retried = retry_dead_letters(&db, &registry(), query.clone(), 7, 4).unwrap();
+		assert_eq!(retried, (1, 0, 4));
+		assert!(DeadLetterManager::read_dead_letters(&db, query).unwrap().is_empty());
+	}
+
 	#[test]
 	fn should_parse_event() {
 		let recipient = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
@@ -385,7 +577,7 @@ mod test {
 			schema_value: to_string(&status_schema).unwrap(),
 			timestamp,
 		};
-		let terms = TransformerService::parse_event(indexed_event).unwrap();
+		let terms = registry().parse_event(&indexed_event).unwrap();
 		assert_eq!(
 			terms,
 			vec![Term::new(
@@ -395,8 +587,11 @@ mod test {
 				Domain::SoftwareSecurity.into(),
 				true,
 				timestamp,
-			)]
-		)
+			)
+			.into_canonical(&Default::default())
+			.unwrap()]
+		);
+		assert!(terms[0].from().starts_with("did:pkh:eip155:1:0x"));
 	}
 
 	#[test]
@@ -525,7 +720,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -548,7 +743,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -732,7 +927,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -755,7 +950,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -905,7 +1100,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -928,7 +1123,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -1006,7 +1201,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -1029,7 +1224,7 @@ mod test {
 				schema_value: to_string(&schema_value).unwrap(),
 				timestamp,
 			};
-			let _ = TransformerService::parse_event(indexed_event).unwrap();
+			let _ = registry().parse_event(&indexed_event).unwrap();
 
 			let string = [
 				id.to_string(),
@@ -1,4 +1,4 @@
-use rocksdb::DB;
+use rocksdb::{WriteBatch, DB};
 
 use crate::error::AttTrError;
 
@@ -40,9 +40,18 @@ impl CheckpointManager {
 	}
 
 	pub fn write_checkpoint(db: &DB, checkpoint: u32, count: u32) -> Result<(), AttTrError> {
+		let mut batch = WriteBatch::default();
+		Self::put_checkpoint(db, &mut batch, checkpoint, count)?;
+		db.write(batch).map_err(AttTrError::DbError)
+	}
+
+	/// Add the checkpoint to the batch, to be written along with the terms up to it.
+	pub fn put_checkpoint(
+		db: &DB, batch: &mut WriteBatch, checkpoint: u32, count: u32,
+	) -> Result<(), AttTrError> {
 		let cf = db.cf_handle("checkpoint").ok_or_else(|| AttTrError::NotFoundError)?;
-		db.put_cf(&cf, b"event_count", checkpoint.to_be_bytes()).map_err(AttTrError::DbError)?;
-		db.put_cf(&cf, b"term_count", count.to_be_bytes()).map_err(AttTrError::DbError)?;
+		batch.put_cf(&cf, b"event_count", checkpoint.to_be_bytes());
+		batch.put_cf(&cf, b"term_count", count.to_be_bytes());
 		Ok(())
 	}
 }
@@ -0,0 +1,109 @@
+use prost::Message;
+use proto_buf::indexer::IndexerEvent;
+use proto_buf::transformer::{DeadLetter, DeadLetterQuery, RejectionKind};
+use rocksdb::{Direction, IteratorMode, WriteBatch, DB};
+
+use crate::error::AttTrError;
+
+/// Stores the events that failed to parse, keyed by event id, so that they can be
+/// inspected and retried without holding back the rest of the pipeline.
+#[derive(Debug)]
+pub struct DeadLetterManager;
+
+impl DeadLetterManager {
+	pub fn write_dead_letter(
+		db: &DB, event: IndexerEvent, error: &AttTrError,
+	) -> Result<(), AttTrError> {
+		let mut batch = WriteBatch::default();
+		Self::put_dead_letter(db, &mut batch, event, error)?;
+		db.write(batch).map_err(AttTrError::DbError)
+	}
+
+	/// Add the dead letter to the batch, to be written along with the checkpoint past its event.
+	pub fn put_dead_letter(
+		db: &DB, batch: &mut WriteBatch, event: IndexerEvent, error: &AttTrError,
+	) -> Result<(), AttTrError> {
+		let cf = db.cf_handle("dead_letter").ok_or_else(|| AttTrError::NotFoundError)?;
+		let id = event.id.to_be_bytes();
+		let dead_letter = DeadLetter {
+			event: Some(event),
+			kind: RejectionKind::from(error).into(),
+			error: error.to_string(),
+		};
+		batch.put_cf(&cf, id, dead_letter.encode_to_vec());
+		Ok(())
+	}
+
+	/// Dead letters with event ids from `query.start` on, at most `query.size` of them.
+	pub fn read_dead_letters(
+		db: &DB, query: DeadLetterQuery,
+	) -> Result<Vec<DeadLetter>, AttTrError> {
+		let cf = db.cf_handle("dead_letter").ok_or_else(|| AttTrError::NotFoundError)?;
+		let start = query.start.to_be_bytes();
+		let mode = IteratorMode::From(&start, Direction::Forward);
+
+		let mut dead_letters = Vec::new();
+		for item in db.iterator_cf(&cf, mode).take(query.size as usize) {
+			let (_, value) = item.map_err(AttTrError::DbError)?;
+			let dead_letter =
+				DeadLetter::decode(value.as_ref()).map_err(|_| AttTrError::SerialisationError)?;
+			dead_letters.push(dead_letter);
+		}
+		Ok(dead_letters)
+	}
+
+	/// Add the deletion of the dead letter to the batch,
+	/// to be written along with the terms of its event.
+	pub fn delete_dead_letter(db: &DB, batch: &mut WriteBatch, id: u32) -> Result<(), AttTrError> {
+		let cf = db.cf_handle("dead_letter").ok_or_else(|| AttTrError::NotFoundError)?;
+		batch.delete_cf(&cf, id.to_be_bytes());
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use rocksdb::{Options, DB};
+
+	use super::*;
+
+	fn event(id: u32) -> IndexerEvent {
+		IndexerEvent { id, schema_id: 1, schema_value: "{}".to_string(), timestamp: 0 }
+	}
+
+	#[test]
+	fn should_write_read_dead_letters() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(&opts, "att-dl-test-storage", vec!["dead_letter"]).unwrap();
+
+		DeadLetterManager::write_dead_letter(&db, event(3), &AttTrError::VerificationError)
+			.unwrap();
+		DeadLetterManager::write_dead_letter(&db, event(7), &AttTrError::ParseError).unwrap();
+		DeadLetterManager::write_dead_letter(&db, event(9), &AttTrError::UnknownSchema(5)).unwrap();
+
+		let query = DeadLetterQuery { start: 4, size: 10 };
+		let dead_letters = DeadLetterManager::read_dead_letters(&db, query).unwrap();
+		let kinds: Vec<_> = dead_letters.iter().map(|d| d.kind()).collect();
+		assert_eq!(
+			kinds,
+			vec![RejectionKind::DidParse, RejectionKind::UnknownSchema]
+		);
+		assert_eq!(dead_letters[0].event, Some(event(7)));
+		assert_eq!(dead_letters[1].error, "UnknownSchema: 5");
+
+		let mut batch = WriteBatch::default();
+		DeadLetterManager::delete_dead_letter(&db, &mut batch, 7).unwrap();
+		db.write(batch).unwrap();
+		let query = DeadLetterQuery { start: 0, size: 1 };
+		let dead_letters = DeadLetterManager::read_dead_letters(&db, query).unwrap();
+		assert_eq!(dead_letters.len(), 1);
+		assert_eq!(dead_letters[0].kind(), RejectionKind::IssuerMismatch);
+		let query = DeadLetterQuery { start: 4, size: 10 };
+		assert_eq!(
+			DeadLetterManager::read_dead_letters(&db, query).unwrap().len(),
+			1
+		);
+	}
+}
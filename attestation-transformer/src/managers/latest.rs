@@ -0,0 +1,535 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mm_spd_did::{canonicalize_did_with, CanonicalizeOptions};
+use rocksdb::{IteratorMode, WriteBatch, DB};
+
+use crate::error::AttTrError;
+use crate::term::{Term, TermKind};
+
+/// Leading byte of the stored latest entries that carry their timestamp.
+/// Entries stored before have none, and start with the length of their first term instead.
+const LATEST_VERSION: u8 = 1;
+
+/// The latest asserted terms of one (issuer, subject, schema kind, domain),
+/// and the timestamp of the credential they come from.
+///
+/// The timestamp is kept after the terms are revoked or expire,
+/// so that older credentials, e.g. replayed ones, stay superseded.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Latest {
+	timestamp: u64,
+	terms: Vec<Term>,
+}
+
+/// Keeps the latest asserted terms of each (issuer, subject, schema kind, domain),
+/// so that newer credentials supersede the earlier ones instead of adding up.
+///
+/// Changes are collected into a batch, so that they are written along with the terms
+/// they yield and the checkpoint, see [`LatestBatch::into_batch`].
+/// Reads see the changes made so far.
+pub struct LatestBatch<'a> {
+	db: &'a DB,
+	batch: WriteBatch,
+	pending: BTreeMap<Vec<u8>, Latest>,
+}
+
+impl<'a> LatestBatch<'a> {
+	pub fn new(db: &'a DB) -> Self {
+		Self { db, batch: WriteBatch::default(), pending: BTreeMap::new() }
+	}
+
+	/// Resolve the terms parsed from one credential of the given schema kind
+	/// against the latest ones, and record them as the latest.
+	///
+	/// Terms older than the latest ones are ignored, and so are revocations of them.
+	///
+	/// Returns the terms to store: revocations of the superseded (or explicitly revoked) terms,
+	/// followed by the new assertions.
+	pub fn supersede(&mut self, kind: &str, terms: Vec<Term>) -> Result<Vec<Term>, AttTrError> {
+		let expiry_cf = self.db.cf_handle("expiry").ok_or_else(|| AttTrError::NotFoundError)?;
+
+		let mut revocations = Vec::new();
+		let mut groups: Vec<(Vec<u8>, Vec<Term>)> = Vec::new();
+		for term in terms {
+			match term.kind() {
+				TermKind::Assertion => {
+					let key = latest_key(term.from(), term.to(), kind, term.domain());
+					match groups.iter_mut().find(|(k, _)| *k == key) {
+						Some((_, group)) => group.push(term),
+						None => groups.push((key, vec![term])),
+					}
+				},
+				TermKind::Revocation => {
+					let timestamp = term.timestamp();
+					for (key, latest) in self.scan(&subject_prefix(term.from(), term.to()))? {
+						if latest.timestamp > timestamp {
+							continue;
+						}
+						revocations.extend(latest.terms.iter().map(|t| t.revocation(timestamp)));
+						self.pending.insert(key, Latest { timestamp, terms: Vec::new() });
+					}
+				},
+			}
+		}
+
+		let mut assertions = Vec::new();
+		for (key, group) in groups {
+			let timestamp = group[0].timestamp();
+			if let Some(latest) = self.get(&key)? {
+				if latest.timestamp > timestamp {
+					continue;
+				}
+				revocations.extend(latest.terms.iter().map(|t| t.revocation(timestamp)));
+			}
+			if let Some(until) = group[0].expires_at() {
+				self.batch.put_cf(&expiry_cf, expiry_key(until, &key), []);
+			}
+			// Kept to be revoked, which the findings aren't needed for.
+			let terms = group.iter().map(|term| term.clone().with_findings(Vec::new())).collect();
+			self.pending.insert(key, Latest { timestamp, terms });
+			assertions.extend(group);
+		}
+
+		revocations.extend(assertions);
+		Ok(revocations)
+	}
+
+	/// Revoke the latest terms that expired by `now` (in milliseconds), as of their expiry.
+	///
+	/// Expiries of terms superseded in the meantime are dropped without revoking anything.
+	pub fn expire(&mut self, now: u64) -> Result<Vec<Term>, AttTrError> {
+		let expiry_cf = self.db.cf_handle("expiry").ok_or_else(|| AttTrError::NotFoundError)?;
+
+		let mut revocations = Vec::new();
+		for item in self.db.iterator_cf(&expiry_cf, IteratorMode::Start) {
+			let (expiry, _) = item.map_err(AttTrError::DbError)?;
+			if expiry.len() < 8 {
+				return Err(AttTrError::SerialisationError);
+			}
+			let (until, key) = expiry.split_at(8);
+			let until = u64::from_be_bytes(until.try_into().unwrap());
+			if until > now {
+				break;
+			}
+
+			if let Some(latest) = self.get(key)? {
+				let (expired, live): (Vec<Term>, Vec<Term>) =
+					latest.terms.into_iter().partition(|term| term.expires_at() == Some(until));
+				if !expired.is_empty() {
+					revocations.extend(expired.iter().map(|term| term.revocation(until)));
+					let latest = Latest { timestamp: latest.timestamp, terms: live };
+					self.pending.insert(key.to_vec(), latest);
+				}
+			}
+			self.batch.delete_cf(&expiry_cf, &expiry);
+		}
+
+		Ok(revocations)
+	}
+
+	/// Re-key the latest entries, and their expiries, by the canonical DIDs of their issuers
+	/// and subjects, as the terms have been since they are canonicalized.
+	///
+	/// Entries that turn out to share a key are merged into the newest of them.
+	/// Returns the revocations of the terms of the others, and the number of re-keyed entries.
+	pub fn migrate_to_canonical_dids(
+		&mut self, options: &CanonicalizeOptions,
+	) -> Result<(Vec<Term>, u32), AttTrError> {
+		let cf = self.db.cf_handle("latest").ok_or_else(|| AttTrError::NotFoundError)?;
+		let expiry_cf = self.db.cf_handle("expiry").ok_or_else(|| AttTrError::NotFoundError)?;
+
+		let mut rekeyed = HashMap::new();
+		let mut revocations = Vec::new();
+		for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+			let (key, value) = item.map_err(AttTrError::DbError)?;
+			let (from, to, kind, domain) = split_latest_key(&key)?;
+			let canonical = (
+				canonicalize_did_with(&from, options),
+				canonicalize_did_with(&to, options),
+			);
+			let new_key = match canonical {
+				(Ok(from), Ok(to)) => latest_key(&from, &to, &kind, domain),
+				_ => continue,
+			};
+			if *new_key == *key {
+				continue;
+			}
+
+			let latest = decode_latest(&value)?;
+			let terms = latest.terms.into_iter().map(|term| term.into_canonical(options));
+			let mut latest =
+				Latest { timestamp: latest.timestamp, terms: terms.collect::<Result<_, _>>()? };
+			if let Some(other) = self.get(&new_key)? {
+				let (newer, older) = if other.timestamp >= latest.timestamp {
+					(other, latest)
+				} else {
+					(latest, other)
+				};
+				revocations.extend(older.terms.iter().map(|term| term.revocation(newer.timestamp)));
+				latest = newer;
+			}
+			self.batch.delete_cf(&cf, &key);
+			self.pending.insert(new_key.clone(), latest);
+			rekeyed.insert(key.to_vec(), new_key);
+		}
+
+		for item in self.db.iterator_cf(&expiry_cf, IteratorMode::Start) {
+			let (expiry, _) = item.map_err(AttTrError::DbError)?;
+			if expiry.len() < 8 {
+				return Err(AttTrError::SerialisationError);
+			}
+			let (until, key) = expiry.split_at(8);
+			if let Some(new_key) = rekeyed.get(key) {
+				let until = u64::from_be_bytes(until.try_into().unwrap());
+				self.batch.delete_cf(&expiry_cf, &expiry);
+				self.batch.put_cf(&expiry_cf, expiry_key(until, new_key), []);
+			}
+		}
+
+		let num_rekeyed =
+			u32::try_from(rekeyed.len()).map_err(|_| AttTrError::SerialisationError)?;
+		Ok((revocations, num_rekeyed))
+	}
+
+	/// The batch of all the changes, to add the other writes of the same events to.
+	pub fn into_batch(mut self) -> Result<WriteBatch, AttTrError> {
+		let cf = self.db.cf_handle("latest").ok_or_else(|| AttTrError::NotFoundError)?;
+		for (key, latest) in &self.pending {
+			self.batch.put_cf(&cf, key, encode_latest(latest)?);
+		}
+		Ok(self.batch)
+	}
+
+	fn get(&self, key: &[u8]) -> Result<Option<Latest>, AttTrError> {
+		if let Some(latest) = self.pending.get(key) {
+			return Ok(Some(latest.clone()));
+		}
+		let cf = self.db.cf_handle("latest").ok_or_else(|| AttTrError::NotFoundError)?;
+		let value = self.db.get_cf(&cf, key).map_err(AttTrError::DbError)?;
+		value.map(|value| decode_latest(&value)).transpose()
+	}
+
+	/// The latest entries under the key prefix, in key order.
+	fn scan(&self, prefix: &[u8]) -> Result<BTreeMap<Vec<u8>, Latest>, AttTrError> {
+		let cf = self.db.cf_handle("latest").ok_or_else(|| AttTrError::NotFoundError)?;
+		let mut entries = BTreeMap::new();
+		for item in self.db.prefix_iterator_cf(&cf, prefix) {
+			let (key, value) = item.map_err(AttTrError::DbError)?;
+			if !key.starts_with(prefix) {
+				break;
+			}
+			entries.insert(key.to_vec(), decode_latest(&value)?);
+		}
+		let pending = self.pending.range(prefix.to_vec()..);
+		for (key, latest) in pending.take_while(|(key, _)| key.starts_with(prefix)) {
+			entries.insert(key.clone(), latest.clone());
+		}
+		Ok(entries)
+	}
+}
+
+fn push_part(bytes: &mut Vec<u8>, part: &[u8]) {
+	bytes.extend_from_slice(&(part.len() as u32).to_be_bytes());
+	bytes.extend_from_slice(part);
+}
+
+fn subject_prefix(from: &str, to: &str) -> Vec<u8> {
+	let mut key = Vec::new();
+	push_part(&mut key, from.as_bytes());
+	push_part(&mut key, to.as_bytes());
+	key
+}
+
+fn latest_key(from: &str, to: &str, kind: &str, domain: u32) -> Vec<u8> {
+	let mut key = subject_prefix(from, to);
+	push_part(&mut key, kind.as_bytes());
+	key.extend_from_slice(&domain.to_be_bytes());
+	key
+}
+
+/// Issuer, subject, schema kind and domain of the latest key.
+fn split_latest_key(mut key: &[u8]) -> Result<(String, String, String, u32), AttTrError> {
+	let key = &mut key;
+	let mut read_part = || -> Result<String, AttTrError> {
+		let len = key.get(..4).ok_or(AttTrError::SerialisationError)?;
+		let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+		let part = key.get(4..4 + len).ok_or(AttTrError::SerialisationError)?;
+		let part = String::from_utf8(part.to_vec()).map_err(|_| AttTrError::SerialisationError)?;
+		*key = &key[4 + len..];
+		Ok(part)
+	};
+	let (from, to, kind) = (read_part()?, read_part()?, read_part()?);
+	let domain: [u8; 4] = (*key).try_into().map_err(|_| AttTrError::SerialisationError)?;
+	Ok((from, to, kind, u32::from_be_bytes(domain)))
+}
+
+/// Big-endian expiry first, so that the expiries iterate in time order.
+fn expiry_key(until: u64, latest_key: &[u8]) -> Vec<u8> {
+	let mut key = until.to_be_bytes().to_vec();
+	key.extend_from_slice(latest_key);
+	key
+}
+
+fn encode_terms(terms: &[Term]) -> Result<Vec<u8>, AttTrError> {
+	let mut bytes = Vec::new();
+	for term in terms {
+		push_part(&mut bytes, &term.clone().into_bytes()?);
+	}
+	Ok(bytes)
+}
+
+fn encode_latest(latest: &Latest) -> Result<Vec<u8>, AttTrError> {
+	let mut bytes = vec![LATEST_VERSION];
+	bytes.extend_from_slice(&latest.timestamp.to_be_bytes());
+	bytes.extend(encode_terms(&latest.terms)?);
+	Ok(bytes)
+}
+
+/// Entries stored without a timestamp are as old as their newest term.
+fn decode_latest(bytes: &[u8]) -> Result<Latest, AttTrError> {
+	match bytes.split_first() {
+		Some((&LATEST_VERSION, rest)) => {
+			if rest.len() < 8 {
+				return Err(AttTrError::SerialisationError);
+			}
+			let (timestamp, terms) = rest.split_at(8);
+			let timestamp = u64::from_be_bytes(timestamp.try_into().unwrap());
+			Ok(Latest { timestamp, terms: decode_terms(terms)? })
+		},
+		_ => {
+			let terms = decode_terms(bytes)?;
+			let timestamp = terms.iter().map(Term::timestamp).max().unwrap_or_default();
+			Ok(Latest { timestamp, terms })
+		},
+	}
+}
+
+fn decode_terms(mut bytes: &[u8]) -> Result<Vec<Term>, AttTrError> {
+	let mut terms = Vec::new();
+	while !bytes.is_empty() {
+		if bytes.len() < 4 {
+			return Err(AttTrError::SerialisationError);
+		}
+		let (len, rest) = bytes.split_at(4);
+		let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+		if rest.len() < len {
+			return Err(AttTrError::SerialisationError);
+		}
+		let (term, rest) = rest.split_at(len);
+		terms.push(Term::from_bytes(term.to_vec())?);
+		bytes = rest;
+	}
+	Ok(terms)
+}
+
+#[cfg(test)]
+mod test {
+	use rocksdb::{Options, DB};
+
+	use super::*;
+
+	const ISSUER: &str = "did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2";
+	const SNAP: &str = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c3";
+
+	fn open(path: &str) -> DB {
+		let _ = DB::destroy(&Options::default(), path);
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		DB::open_cf(&opts, path, vec!["latest", "expiry"]).unwrap()
+	}
+
+	fn status(is_trust: bool, timestamp: u64) -> Term {
+		Term::new(
+			ISSUER.to_string(),
+			SNAP.to_string(),
+			50.,
+			2,
+			is_trust,
+			timestamp,
+		)
+	}
+
+	fn trust(level: f32, timestamp: u64) -> Term {
+		Term::new(
+			ISSUER.to_string(),
+			SNAP.to_string(),
+			level,
+			2,
+			true,
+			timestamp,
+		)
+	}
+
+	/// Supersede in a batch of its own.
+	fn supersede(db: &DB, kind: &str, terms: Vec<Term>) -> Vec<Term> {
+		let mut latest = LatestBatch::new(db);
+		let terms = latest.supersede(kind, terms).unwrap();
+		db.write(latest.into_batch().unwrap()).unwrap();
+		terms
+	}
+
+	fn expire(db: &DB, now: u64) -> Vec<Term> {
+		let mut latest = LatestBatch::new(db);
+		let terms = latest.expire(now).unwrap();
+		db.write(latest.into_batch().unwrap()).unwrap();
+		terms
+	}
+
+	#[test]
+	fn should_supersede_and_revoke_latest_terms() {
+		let db = open("att-latest-test-storage");
+
+		let endorse = status(true, 1);
+		let terms = supersede(&db, "StatusCredential", vec![endorse.clone()]);
+		assert_eq!(terms, vec![endorse.clone()]);
+
+		// Same issuer, subject and domain, but another schema: no supersession.
+		let trust = trust(10., 2);
+		let terms = supersede(&db, "TrustCredential", vec![trust.clone()]);
+		assert_eq!(terms, vec![trust.clone()]);
+
+		let dispute = status(false, 3);
+		let terms = supersede(&db, "StatusCredential", vec![dispute.clone()]);
+		assert_eq!(terms, vec![endorse.revocation(3), dispute.clone()]);
+
+		let revoke = Term::revoke_all(ISSUER.to_string(), SNAP.to_string(), 4);
+		let terms = supersede(&db, "RevocationCredential", vec![revoke.clone()]);
+		// In key order, i.e. by length-prefixed schema kind.
+		assert_eq!(terms, vec![trust.revocation(4), dispute.revocation(4)]);
+
+		let terms = supersede(&db, "RevocationCredential", vec![revoke]);
+		assert_eq!(terms, vec![]);
+	}
+
+	#[test]
+	fn should_ignore_older_terms() {
+		let db = open("att-latest-older-test-storage");
+
+		let dispute = status(false, 3);
+		supersede(&db, "StatusCredential", vec![dispute.clone()]);
+		// E.g. a replayed or late credential.
+		assert_eq!(
+			supersede(&db, "StatusCredential", vec![status(true, 2)]),
+			vec![]
+		);
+		let revoke = |timestamp| Term::revoke_all(ISSUER.to_string(), SNAP.to_string(), timestamp);
+		assert_eq!(
+			supersede(&db, "RevocationCredential", vec![revoke(2)]),
+			vec![]
+		);
+
+		// Still superseded once revoked.
+		assert_eq!(
+			supersede(&db, "RevocationCredential", vec![revoke(5)]),
+			vec![dispute.revocation(5)]
+		);
+		assert_eq!(
+			supersede(&db, "StatusCredential", vec![status(true, 4)]),
+			vec![]
+		);
+		let endorse = status(true, 5);
+		assert_eq!(
+			supersede(&db, "StatusCredential", vec![endorse.clone()]),
+			vec![endorse]
+		);
+	}
+
+	#[test]
+	fn should_see_changes_within_batch() {
+		let db = open("att-latest-batch-test-storage");
+
+		let (endorse, dispute) = (status(true, 1), status(false, 2));
+		let mut latest = LatestBatch::new(&db);
+		latest.supersede("StatusCredential", vec![endorse.clone()]).unwrap();
+		let terms = latest.supersede("StatusCredential", vec![dispute.clone()]).unwrap();
+		assert_eq!(terms, vec![endorse.revocation(2), dispute.clone()]);
+		let revoke = Term::revoke_all(ISSUER.to_string(), SNAP.to_string(), 3);
+		let terms = latest.supersede("RevocationCredential", vec![revoke]).unwrap();
+		assert_eq!(terms, vec![dispute.revocation(3)]);
+
+		// Nothing is written until the batch is.
+		let cf = db.cf_handle("latest").unwrap();
+		assert_eq!(db.iterator_cf(&cf, IteratorMode::Start).count(), 0);
+		db.write(latest.into_batch().unwrap()).unwrap();
+		assert_eq!(
+			supersede(&db, "StatusCredential", vec![status(true, 2)]),
+			vec![]
+		);
+	}
+
+	#[test]
+	fn should_read_entries_without_timestamp() {
+		let db = open("att-latest-legacy-test-storage");
+		let cf = db.cf_handle("latest").unwrap();
+
+		let endorse = status(true, 3);
+		let key = latest_key(ISSUER, SNAP, "StatusCredential", 2);
+		db.put_cf(&cf, key, encode_terms(&[endorse.clone()]).unwrap()).unwrap();
+
+		assert_eq!(
+			supersede(&db, "StatusCredential", vec![status(false, 2)]),
+			vec![]
+		);
+		let dispute = status(false, 4);
+		assert_eq!(
+			supersede(&db, "StatusCredential", vec![dispute.clone()]),
+			vec![endorse.revocation(4), dispute]
+		);
+	}
+
+	#[test]
+	fn should_expire_latest_terms() {
+		let db = open("att-expiry-test-storage");
+
+		let endorse = status(true, 1).with_expiry(Some(10));
+		supersede(&db, "StatusCredential", vec![endorse.clone()]);
+		supersede(
+			&db,
+			"TrustCredential",
+			vec![trust(10., 2).with_expiry(Some(20))],
+		);
+
+		assert_eq!(expire(&db, 9), vec![]);
+		assert_eq!(expire(&db, 10), vec![endorse.revocation(10)]);
+		assert_eq!(expire(&db, 10), vec![]);
+
+		// Superseded before expiring: the newer term doesn't expire.
+		supersede(&db, "TrustCredential", vec![trust(20., 3)]);
+		assert_eq!(expire(&db, 30), vec![]);
+		// Expired terms stay superseded.
+		assert_eq!(
+			supersede(&db, "StatusCredential", vec![status(false, 0)]),
+			vec![]
+		);
+	}
+
+	#[test]
+	fn should_migrate_to_canonical_dids() {
+		let db = open("att-latest-migrate-test-storage");
+		let options = CanonicalizeOptions::default();
+
+		let dispute = status(false, 1).into_canonical(&options).unwrap();
+		supersede(&db, "StatusCredential", vec![dispute.clone()]);
+		let issuer = "did:pkh:eth:0x90F8BF6A479F320EAD074411A4B0E7944EA8C9C2";
+		let endorse = Term::new(issuer.to_string(), SNAP.to_string(), 50., 2, true, 3);
+		let endorse = endorse.with_expiry(Some(10));
+		supersede(&db, "StatusCredential", vec![endorse.clone()]);
+
+		// Merged into the newer entry, the other's terms revoked.
+		let mut latest = LatestBatch::new(&db);
+		let (revocations, num_rekeyed) = latest.migrate_to_canonical_dids(&options).unwrap();
+		db.write(latest.into_batch().unwrap()).unwrap();
+		assert_eq!((revocations, num_rekeyed), (vec![dispute.revocation(3)], 1));
+
+		let mut latest = LatestBatch::new(&db);
+		assert_eq!(
+			latest.migrate_to_canonical_dids(&options).unwrap(),
+			(vec![], 0)
+		);
+
+		// The expiry moved along with the entry.
+		let endorse = endorse.into_canonical(&options).unwrap();
+		assert_eq!(expire(&db, 10), vec![endorse.revocation(10)]);
+	}
+}
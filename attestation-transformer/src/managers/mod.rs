@@ -1,2 +1,4 @@
 pub mod checkpoint;
+pub mod dead_letter;
+pub mod latest;
 pub mod term;
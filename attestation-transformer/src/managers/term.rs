@@ -1,10 +1,10 @@
 use itertools::Itertools;
-use rocksdb::{WriteBatch, DB};
+use rocksdb::{IteratorMode, WriteBatch, DB};
 
 use proto_buf::transformer::{TermBatch, TermObject};
 
 use crate::error::AttTrError;
-use crate::term::Term;
+use crate::term::{is_legacy, Term};
 
 #[derive(Debug)]
 pub struct TermManager;
@@ -27,15 +27,43 @@ impl TermManager {
 	}
 
 	pub fn write_terms(db: &DB, terms: Vec<(u32, Term)>) -> Result<(), AttTrError> {
+		let mut batch = WriteBatch::default();
+		Self::put_terms(db, &mut batch, terms)?;
+		db.write(batch).map_err(AttTrError::DbError)
+	}
+
+	/// Add the terms to the batch, to be written along with the other changes of the same events.
+	pub fn put_terms(
+		db: &DB, batch: &mut WriteBatch, terms: Vec<(u32, Term)>,
+	) -> Result<(), AttTrError> {
 		let cf = db.cf_handle("term").ok_or_else(|| AttTrError::NotFoundError)?;
 
-		let mut batch = WriteBatch::default();
 		for (id, term) in terms {
 			let term_bytes = term.into_bytes()?;
 			let id = id.to_be_bytes();
 			batch.put_cf(&cf, id, term_bytes);
 		}
-		db.write(batch).map_err(AttTrError::DbError)
+		Ok(())
+	}
+
+	/// Rewrite the terms stored in the legacy, fixed-length format in the current format.
+	/// Returns the number of migrated terms.
+	pub fn migrate_terms(db: &DB) -> Result<u32, AttTrError> {
+		let cf = db.cf_handle("term").ok_or_else(|| AttTrError::NotFoundError)?;
+
+		let mut batch = WriteBatch::default();
+		let mut count = 0;
+		for item in db.iterator_cf(&cf, IteratorMode::Start) {
+			let (id, value) = item.map_err(AttTrError::DbError)?;
+			if !is_legacy(&value) {
+				continue;
+			}
+			let term = Term::from_bytes(value.to_vec())?;
+			batch.put_cf(&cf, id, term.into_bytes()?);
+			count += 1;
+		}
+		db.write(batch).map_err(AttTrError::DbError)?;
+		Ok(count)
 	}
 
 	pub fn get_indexed_terms(
@@ -97,4 +125,35 @@ mod test {
 		let term_objs: Vec<TermObject> = org_terms.into_iter().map(|x| x.into()).collect_vec();
 		assert_eq!(terms, term_objs);
 	}
+
+	#[test]
+	fn should_migrate_legacy_terms() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(&opts, "att-migrate-test-storage", vec!["term"]).unwrap();
+		let cf = db.cf_handle("term").unwrap();
+
+		let from = "did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2";
+		let to = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c3";
+		let mut legacy = Vec::new();
+		legacy.extend_from_slice(from.as_bytes());
+		legacy.extend_from_slice(to.as_bytes());
+		legacy.extend_from_slice(&50f32.to_be_bytes());
+		legacy.extend_from_slice(&2u32.to_be_bytes());
+		legacy.push(1);
+		legacy.extend_from_slice(&7u64.to_be_bytes());
+		db.put_cf(&cf, 0u32.to_be_bytes(), legacy).unwrap();
+		let term = Term::new(from.to_string(), to.to_string(), 50., 2, false, 7);
+		TermManager::write_terms(&db, vec![(1, term.clone())]).unwrap();
+
+		assert_eq!(TermManager::migrate_terms(&db).unwrap(), 1);
+		assert_eq!(TermManager::migrate_terms(&db).unwrap(), 0);
+
+		let stored = db.get_cf(&cf, 0u32.to_be_bytes()).unwrap().unwrap();
+		assert_eq!(stored, term.clone().into_bytes().unwrap());
+		let terms = TermManager::read_terms(&db, TermBatch { start: 0, size: 2 }).unwrap();
+		let term_obj: TermObject = term.into();
+		assert_eq!(terms, vec![term_obj.clone(), term_obj]);
+	}
 }
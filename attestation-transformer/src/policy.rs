@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fs;
+use std::iter;
+use std::path::Path;
+
+use mm_spd_did::CanonicalizeOptions;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::AttTrError;
+use crate::schemas::Domain;
+
+/// End of the transition to the current message versions: 2027-01-01T00:00:00Z, in milliseconds.
+pub const DEFAULT_LEGACY_MESSAGES_UNTIL: u64 = 1_798_761_600_000;
+
+fn default_legacy_messages_until() -> u64 {
+	DEFAULT_LEGACY_MESSAGES_UNTIL
+}
+
+/// Term weighting policy: the weights of the terms emitted by each schema kind, per domain.
+///
+/// Loaded from a JSON config, e.g.:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "schemas": {
+///     "StatusCredential": { "SoftwareSecurity": { "weight": 50.0 } },
+///     "TrustCredential": {
+///       "Honesty": { "weight": 1.0, "fanOut": ["SoftwareDevelopment", "SoftwareSecurity"] },
+///       "SoftwareSecurity": { "weight": 10.0 }
+///     }
+///   },
+///   "legacyMessagesUntil": 1798761600000,
+///   "validity": { "useIssuanceTime": true, "clockSkew": 300000 },
+///   "findingsAggregation": { "kind": "diminishingReturns", "factor": 0.5 },
+///   "snapVersions": "separate"
+/// }
+/// ```
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct WeightPolicy {
+	/// Recorded in every term emitted under this policy.
+	pub version: u32,
+	pub schemas: HashMap<String, HashMap<Domain, DomainWeight>>,
+	/// Event timestamp, in milliseconds since the Unix epoch, until which credentials signed
+	/// in the legacy message version (e.g. trust credentials without their levels) are still
+	/// accepted. [`DEFAULT_LEGACY_MESSAGES_UNTIL`] if not given.
+	#[serde(rename = "legacyMessagesUntil", default = "default_legacy_messages_until")]
+	pub legacy_messages_until: u64,
+	#[serde(default)]
+	pub validity: ValidityPolicy,
+	/// How the findings of a security report add up to the weight of its term.
+	#[serde(rename = "findingsAggregation", default)]
+	pub findings_aggregation: Aggregation,
+	#[serde(rename = "snapVersions", default)]
+	pub snap_versions: SnapVersions,
+}
+
+/// Whether the terms about versioned snap ids (e.g. `npm:foo@1.2.0`) are about the snap
+/// as a whole, or about the version alone.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapVersions {
+	/// Strip the version and shasum, so that the endorsements of all versions add up.
+	#[default]
+	Group,
+	/// Keep the version and shasum, so that each version is endorsed on its own;
+	/// the snap score computer rolls them up into the score of the snap as a whole.
+	Separate,
+}
+
+impl SnapVersions {
+	/// Options the issuers and subjects of terms are canonicalized with.
+	pub fn canonicalize_options(&self) -> CanonicalizeOptions {
+		CanonicalizeOptions { keep_snap_version: *self == Self::Separate, ..Default::default() }
+	}
+}
+
+/// How the validity periods of credentials are enforced.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidityPolicy {
+	/// Stamp terms with the start of the validity period (e.g. the issuance date)
+	/// rather than with the event timestamp, for the proofs that sign the period.
+	#[serde(default)]
+	pub use_issuance_time: bool,
+	/// Milliseconds a credential may be valid from after the event timestamp,
+	/// to allow for issuer clocks running ahead.
+	#[serde(default)]
+	pub clock_skew: u64,
+}
+
+/// Aggregation of the scores of the findings of a security report, so that many minor findings
+/// don't outweigh a critical one.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Aggregation {
+	/// The highest score.
+	#[default]
+	Max,
+	/// The sum of the scores, up to the cap.
+	CappedSum { cap: f32 },
+	/// The scores from the highest down, each weighted `factor` times the previous one.
+	DiminishingReturns { factor: f32 },
+}
+
+impl Aggregation {
+	/// Check that the aggregate is non-negative and no more than a sum of the scores.
+	pub fn validate(&self) -> Result<(), AttTrError> {
+		match self {
+			Self::CappedSum { cap } if cap.is_nan() || *cap < 0. => {
+				Err(AttTrError::PolicyError(format!("negative cap {}", cap)))
+			},
+			Self::DiminishingReturns { factor } if !(0. ..=1.).contains(factor) => Err(
+				AttTrError::PolicyError(format!("factor {} not in [0, 1]", factor)),
+			),
+			_ => Ok(()),
+		}
+	}
+
+	pub fn aggregate(&self, scores: impl IntoIterator<Item = f32>) -> f32 {
+		let mut scores: Vec<f32> = scores.into_iter().collect();
+		scores.sort_by(|a, b| b.total_cmp(a));
+		match self {
+			Self::Max => scores.first().copied().unwrap_or(0.),
+			Self::CappedSum { cap } => scores.iter().sum::<f32>().min(*cap),
+			Self::DiminishingReturns { factor } => {
+				let factors = iter::successors(Some(1.), |f| Some(f * factor));
+				scores.iter().zip(factors).map(|(score, f)| score * f).sum()
+			},
+		}
+	}
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainWeight {
+	/// Multiplier of the schema's own term value (e.g. trust level or finding criticality).
+	pub weight: f32,
+	/// Domains the terms are emitted in, the domain itself if not given.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub fan_out: Option<Vec<Domain>>,
+}
+
+/// Weights of one schema kind under a policy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weights {
+	pub version: u32,
+	pub domains: HashMap<Domain, DomainWeight>,
+	pub legacy_messages_until: u64,
+	pub validity: ValidityPolicy,
+	pub findings_aggregation: Aggregation,
+	pub snap_versions: SnapVersions,
+}
+
+impl WeightPolicy {
+	/// Load the policy from a JSON file.
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AttTrError> {
+		let bytes = fs::read(path).map_err(|e| AttTrError::PolicyError(e.to_string()))?;
+		let policy: Self = serde_json::from_slice(&bytes).map_err(AttTrError::SerdeError)?;
+		policy.findings_aggregation.validate()?;
+		Ok(policy)
+	}
+
+	/// Weights of the schema kind.
+	pub fn weights(&self, kind: &str) -> Result<Weights, AttTrError> {
+		let domains = self
+			.schemas
+			.get(kind)
+			.ok_or_else(|| AttTrError::PolicyError(format!("no weights for {}", kind)))?;
+		Ok(Weights {
+			version: self.version,
+			domains: domains.clone(),
+			legacy_messages_until: self.legacy_messages_until,
+			validity: self.validity.clone(),
+			findings_aggregation: self.findings_aggregation.clone(),
+			snap_versions: self.snap_versions,
+		})
+	}
+}
+
+impl Default for WeightPolicy {
+	/// The built-in policy (version 0).
+	fn default() -> Self {
+		let weight = |weight| DomainWeight { weight, fan_out: None };
+		let security = HashMap::from([(Domain::SoftwareSecurity, weight(50.))]);
+		let trust = HashMap::from([
+			(
+				Domain::Honesty,
+				DomainWeight {
+					weight: 1.,
+					fan_out: Some(vec![Domain::SoftwareDevelopment, Domain::SoftwareSecurity]),
+				},
+			),
+			(Domain::SoftwareDevelopment, weight(10.)),
+			(Domain::SoftwareSecurity, weight(10.)),
+		]);
+		Self {
+			version: 0,
+			schemas: HashMap::from([
+				("SecurityCredential".to_string(), security.clone()),
+				("StatusCredential".to_string(), security),
+				("TrustCredential".to_string(), trust),
+				// Revocations carry no weight of their own.
+				("RevocationCredential".to_string(), HashMap::new()),
+			]),
+			legacy_messages_until: DEFAULT_LEGACY_MESSAGES_UNTIL,
+			validity: ValidityPolicy::default(),
+			findings_aggregation: Aggregation::default(),
+			snap_versions: SnapVersions::default(),
+		}
+	}
+}
+
+impl Weights {
+	/// Whether a credential signed in the legacy message version is accepted at the timestamp.
+	pub fn accepts_legacy_message(&self, timestamp: u64) -> bool {
+		timestamp <= self.legacy_messages_until
+	}
+
+	/// Domains and weight of the terms about the given domain.
+	pub fn terms(&self, domain: &Domain) -> Result<(Vec<Domain>, f32), AttTrError> {
+		let rule = self
+			.domains
+			.get(domain)
+			.ok_or_else(|| AttTrError::PolicyError(format!("no weight for domain {:?}", domain)))?;
+		let domains = rule.fan_out.clone().unwrap_or_else(|| vec![domain.clone()]);
+		Ok((domains, rule.weight))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::term::Term;
+
+	#[test]
+	fn should_fan_out_by_policy() {
+		let json = r#"{"version": 3, "schemas": {"TrustCredential": {
+			"Honesty": {"weight": 2.0, "fanOut": ["Software security"]},
+			"SoftwareSecurity": {"weight": 5.0}
+		}}}"#;
+		let policy: WeightPolicy = serde_json::from_str(json).unwrap();
+		let weights = policy.weights("TrustCredential").unwrap();
+
+		assert_eq!(weights.version, 3);
+		assert_eq!(weights.legacy_messages_until, DEFAULT_LEGACY_MESSAGES_UNTIL);
+		assert_eq!(
+			weights.terms(&Domain::Honesty).unwrap(),
+			(vec![Domain::SoftwareSecurity], 2.)
+		);
+		assert_eq!(
+			weights.terms(&Domain::SoftwareSecurity).unwrap(),
+			(vec![Domain::SoftwareSecurity], 5.)
+		);
+		assert!(weights.terms(&Domain::SoftwareDevelopment).is_err());
+		assert!(policy.weights("StatusCredential").is_err());
+	}
+
+	#[test]
+	fn should_aggregate_findings() {
+		let scores = [0.1, 1., 0.1, 0.4];
+		let json = r#"{"kind": "cappedSum", "cap": 1.2}"#;
+		let capped_sum: Aggregation = serde_json::from_str(json).unwrap();
+		let json = r#"{"kind": "diminishingReturns", "factor": 0.5}"#;
+		let diminishing: Aggregation = serde_json::from_str(json).unwrap();
+
+		assert_eq!(Aggregation::Max.aggregate(scores), 1.);
+		assert_eq!(capped_sum.aggregate(scores), 1.2);
+		assert_eq!(diminishing.aggregate(scores), 1. + 0.2 + 0.025 + 0.0125);
+		assert_eq!(Aggregation::Max.aggregate([]), 0.);
+
+		// Twenty trivial findings don't outweigh a critical one.
+		assert!(diminishing.aggregate([0.1; 20]) < diminishing.aggregate([1.]));
+
+		assert!(capped_sum.validate().is_ok() && diminishing.validate().is_ok());
+		let invalid =
+			[Aggregation::CappedSum { cap: -1. }, Aggregation::DiminishingReturns { factor: 1.5 }];
+		assert!(invalid.iter().all(|aggregation| aggregation.validate().is_err()));
+	}
+
+	#[test]
+	fn should_group_or_separate_snap_versions() {
+		let json = r#"{"version": 1, "schemas": {}, "snapVersions": "separate"}"#;
+		let policy: WeightPolicy = serde_json::from_str(json).unwrap();
+		assert_eq!(policy.snap_versions, SnapVersions::Separate);
+		assert_eq!(WeightPolicy::default().snap_versions, SnapVersions::Group);
+
+		let term = Term::new(
+			"did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_string(),
+			"npm:Foo@1.2.0".to_string(),
+			1.,
+			Domain::SoftwareSecurity.into(),
+			true,
+			0,
+		);
+		let grouped = term.clone().into_canonical(&SnapVersions::Group.canonicalize_options());
+		assert_eq!(grouped.unwrap().to(), "npm:foo");
+		let separate = term.into_canonical(&SnapVersions::Separate.canonicalize_options());
+		assert_eq!(separate.unwrap().to(), "npm:foo@1.2.0");
+	}
+
+	#[test]
+	fn should_ship_built_in_policy() {
+		let path = concat!(env!("CARGO_MANIFEST_DIR"), "/weights.json");
+		// Shipped with snap versions apart, for the snap score computer to score each version.
+		let policy = WeightPolicy { snap_versions: SnapVersions::Separate, ..Default::default() };
+		assert_eq!(WeightPolicy::from_file(path).unwrap(), policy);
+	}
+}
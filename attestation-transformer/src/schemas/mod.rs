@@ -1,22 +1,109 @@
+use std::marker::PhantomData;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use mm_spd_did::canonicalize_did;
+use mm_spd_vc::error::VcError;
+use mm_spd_vc::integrity;
 use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
-use secp256k1::{Message, PublicKey, Secp256k1};
+use secp256k1::{Error as SecpError, Message, PublicKey, Secp256k1};
+use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 
+use crate::did::{Did, Schema};
+use crate::policy::Weights;
+use crate::schemas::revocation::RevocationSchema;
+use crate::schemas::security::SecurityReportSchema;
+use crate::schemas::status::StatusSchema;
+use crate::schemas::trust::TrustSchema;
+use crate::schemas::typed_data::TypedData;
+use crate::schemas::validity::ValidityPeriod;
+use crate::utils::address_from_ecdsa_key;
 use crate::{error::AttTrError, term::Term};
 
+pub mod registry;
+pub mod revocation;
 pub mod security;
 pub mod status;
 pub mod trust;
+pub mod typed_data;
+pub mod validity;
+
+/// How the signature of a proof was produced, selected by its `type`.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProofType {
+	/// Keccak256 over the schema's byte layout, see [`Validation::get_message`].
+	#[default]
+	Legacy,
+	/// EIP-191 `personal_sign` of the schema's byte layout.
+	Eip191,
+	/// EIP-712 `eth_signTypedData_v4` of the schema's typed data,
+	/// see [`Validation::get_typed_data`].
+	Eip712,
+	/// W3C Data Integrity proof over the whole credential, verified by
+	/// [`integrity::verify_credential`] rather than by [`Validation::validate`].
+	#[serde(
+		rename = "EcdsaSecp256k1Signature2019",
+		alias = "EthereumEip712Signature2021",
+		alias = "EcdsaSecp256k1RecoverySignature2020"
+	)]
+	DataIntegrity,
+}
+
+impl ProofType {
+	fn is_legacy(&self) -> bool {
+		*self == Self::Legacy
+	}
+}
+
+/// Version of the signed message of proofs that don't specify one.
+pub const DEFAULT_MESSAGE_VERSION: u32 = 1;
+
+fn default_message_version() -> u32 {
+	DEFAULT_MESSAGE_VERSION
+}
+
+fn is_default_message_version(version: &u32) -> bool {
+	*version == DEFAULT_MESSAGE_VERSION
+}
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Proof {
+	#[serde(rename = "type", default, skip_serializing_if = "ProofType::is_legacy")]
+	kind: ProofType,
+	/// Version of the schema's signed message, i.e. of what the signature covers.
+	#[serde(
+		rename = "messageVersion",
+		default = "default_message_version",
+		skip_serializing_if = "is_default_message_version"
+	)]
+	message_version: u32,
+	/// Absent in Data Integrity proofs, which carry a `jws` or `proofValue` instead.
+	#[serde(default)]
 	signature: String,
 }
 
 impl Proof {
 	pub fn new(signature: String) -> Self {
-		Self { signature }
+		Self::with_type(ProofType::Legacy, signature)
+	}
+
+	pub fn with_type(kind: ProofType, signature: String) -> Self {
+		Self { kind, message_version: DEFAULT_MESSAGE_VERSION, signature }
+	}
+
+	pub fn with_message_version(mut self, message_version: u32) -> Self {
+		self.message_version = message_version;
+		self
+	}
+
+	pub fn get_type(&self) -> ProofType {
+		self.kind
+	}
+
+	pub fn get_message_version(&self) -> u32 {
+		self.message_version
 	}
 
 	pub fn get_signature(&self) -> String {
@@ -25,10 +112,29 @@ impl Proof {
 }
 
 pub trait Validation {
-	fn get_trimmed_signature(&self) -> String;
+	fn get_proof(&self) -> &Proof;
+
+	fn get_trimmed_signature(&self) -> String {
+		self.get_proof().get_signature().trim_start_matches("0x").to_owned()
+	}
+
+	/// Versions of the signed message the schema supports.
+	fn get_message_versions(&self) -> RangeInclusive<u32> {
+		DEFAULT_MESSAGE_VERSION..=DEFAULT_MESSAGE_VERSION
+	}
 
 	fn validate(&self) -> Result<PublicKey, AttTrError> {
+		let message_version = self.get_proof().get_message_version();
+		if !self.get_message_versions().contains(&message_version) {
+			return Err(AttTrError::UnsupportedMessageVersion(message_version));
+		}
+
 		let sig_bytes = hex::decode(self.get_trimmed_signature()).map_err(AttTrError::HexError)?;
+		if sig_bytes.len() != 65 {
+			return Err(AttTrError::SigVerificationError(
+				SecpError::InvalidSignature,
+			));
+		}
 		let mut rs_bytes = [0; 64];
 		rs_bytes.copy_from_slice(&sig_bytes[..64]);
 		let rec_id: i32 = match i32::from(sig_bytes[64]) {
@@ -36,7 +142,11 @@ pub trait Validation {
 			1 => 1,
 			27 => 0,
 			28 => 1,
-			_ => return Err(AttTrError::ParseError),
+			_ => {
+				return Err(AttTrError::SigVerificationError(
+					SecpError::InvalidRecoveryId,
+				))
+			},
 		};
 
 		let rec_id_p = RecoveryId::from_i32(rec_id).map_err(AttTrError::SigVerificationError)?;
@@ -44,11 +154,17 @@ pub trait Validation {
 		let signature = RecoverableSignature::from_compact(&rs_bytes, rec_id_p)
 			.map_err(AttTrError::SigVerificationError)?;
 
-		let mut keccak = Keccak256::default();
-		keccak.update(&self.get_message()?);
-		let digest = keccak.finalize();
-		let message = Message::from_digest_slice(digest.as_ref())
-			.map_err(AttTrError::SigVerificationError)?;
+		let digest = match self.get_proof().get_type() {
+			ProofType::Legacy => Keccak256::digest(self.get_message()?).into(),
+			ProofType::Eip191 => personal_message_digest(&self.get_message()?),
+			ProofType::Eip712 => self.get_typed_data()?.digest().map_err(AttTrError::ProofError)?,
+			ProofType::DataIntegrity => {
+				let suite = "Data Integrity proof without the credential".to_string();
+				return Err(AttTrError::ProofError(VcError::UnsupportedProof(suite)));
+			},
+		};
+		let message =
+			Message::from_digest_slice(&digest).map_err(AttTrError::SigVerificationError)?;
 		let pk = signature.recover(&message).map_err(AttTrError::SigVerificationError)?;
 
 		let secp = Secp256k1::verification_only();
@@ -58,31 +174,130 @@ pub trait Validation {
 		Ok(pk)
 	}
 
+	/// Signed bytes of legacy and EIP-191 proofs, in the proof's message version.
 	fn get_message(&self) -> Result<Vec<u8>, AttTrError>;
+
+	/// Signed struct of EIP-712 proofs, in the proof's message version.
+	fn get_typed_data(&self) -> Result<TypedData, AttTrError>;
 }
 
-pub trait IntoTerm: Validation {
-	fn into_term(self, timestamp: u64) -> Result<Vec<Term>, AttTrError>;
+/// Digest of an EIP-191 (version 0x45) signed message, as signed by `personal_sign`.
+pub fn personal_message_digest(message: &[u8]) -> [u8; 32] {
+	let mut keccak = Keccak256::default();
+	keccak.update(format!("\x19Ethereum Signed Message:\n{}", message.len()));
+	keccak.update(message);
+	keccak.finalize().into()
 }
 
+pub trait IntoTerm: Validation + Sized {
+	fn get_issuer_did(&self) -> &str;
+
+	fn get_validity_period(&self) -> &ValidityPeriod;
+
+	/// Terms of the credential, whose signer has been verified to be its issuer `from_did`.
+	fn into_verified_term(
+		self, from_did: String, timestamp: u64, weights: &Weights,
+	) -> Result<Vec<Term>, AttTrError>;
+
+	fn into_term(self, timestamp: u64, weights: &Weights) -> Result<Vec<Term>, AttTrError> {
+		let pk = self.validate()?;
+
+		let from_address = address_from_ecdsa_key(&pk);
+		let from_did: String = Did::new(Schema::PkhEth, from_address).into();
+		// The issuer may be written in any form of the DID, e.g. `did:pkh:eip155:1:`.
+		let issuer_did = canonicalize_did(self.get_issuer_did()).ok();
+		if issuer_did.as_deref() != canonicalize_did(&from_did).ok().as_deref() {
+			return Err(AttTrError::VerificationError);
+		}
+
+		self.into_verified_term(from_did, timestamp, weights)
+	}
+}
+
+/// Built-in schema parser implementations, named by the `kind` of the schema config.
 pub enum SchemaType {
 	SecurityCredential,
 	StatusCredential,
 	TrustCredential,
+	RevocationCredential,
 }
 
-impl From<u32> for SchemaType {
-	fn from(value: u32) -> Self {
-		match value {
-			0 => Self::SecurityCredential,
-			1 => Self::StatusCredential,
-			2 => Self::TrustCredential,
-			_ => panic!("Invalid Schema type"),
+impl SchemaType {
+	pub fn parser(&self) -> Box<dyn SchemaParser> {
+		match self {
+			Self::SecurityCredential => Box::new(JsonSchema::<SecurityReportSchema>::new()),
+			Self::StatusCredential => Box::new(JsonSchema::<StatusSchema>::new()),
+			Self::TrustCredential => Box::new(JsonSchema::<TrustSchema>::new()),
+			Self::RevocationCredential => Box::new(JsonSchema::<RevocationSchema>::new()),
 		}
 	}
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+impl FromStr for SchemaType {
+	type Err = AttTrError;
+
+	fn from_str(kind: &str) -> Result<Self, Self::Err> {
+		match kind {
+			"SecurityCredential" => Ok(Self::SecurityCredential),
+			"StatusCredential" => Ok(Self::StatusCredential),
+			"TrustCredential" => Ok(Self::TrustCredential),
+			"RevocationCredential" => Ok(Self::RevocationCredential),
+			_ => Err(AttTrError::UnknownSchemaKind(kind.to_string())),
+		}
+	}
+}
+
+/// Parses the schema value of an indexed event and emits its terms.
+pub trait SchemaParser: Send + Sync {
+	fn parse(
+		&self, schema_value: &str, timestamp: u64, weights: &Weights,
+	) -> Result<Vec<Term>, AttTrError>;
+}
+
+/// Parser of schemas with a JSON representation.
+pub struct JsonSchema<T>(PhantomData<fn() -> T>);
+
+impl<T> JsonSchema<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T> Default for JsonSchema<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: DeserializeOwned + IntoTerm> SchemaParser for JsonSchema<T> {
+	fn parse(
+		&self, schema_value: &str, timestamp: u64, weights: &Weights,
+	) -> Result<Vec<Term>, AttTrError> {
+		let value: serde_json::Value =
+			serde_json::from_str(schema_value).map_err(AttTrError::SerdeError)?;
+		let parsed_att = T::deserialize(&value).map_err(AttTrError::SerdeError)?;
+
+		// The stated validity period is checked whether signed or not: moving it can only get
+		// the credential rejected. Only Data Integrity proofs cover it, though; the others sign
+		// the schema's message alone, so their period could be moved to replay or expire
+		// the terms, and it neither dates nor expires them.
+		let period = parsed_att.get_validity_period();
+		let term_timestamp = period.term_timestamp(timestamp, &weights.validity)?;
+		let terms = if parsed_att.get_proof().get_type() == ProofType::DataIntegrity {
+			let timestamp = term_timestamp;
+			let expires_at = period.until()?;
+			let from_did = integrity::verify_credential(&value).map_err(AttTrError::ProofError)?;
+			let terms = parsed_att.into_verified_term(from_did, timestamp, weights)?;
+			terms.into_iter().map(|term| term.with_expiry(expires_at)).collect()
+		} else {
+			parsed_att.into_term(timestamp, weights)?
+		};
+		let options = weights.snap_versions.canonicalize_options();
+		terms.into_iter().map(|term| term.into_canonical(&options)).collect()
+	}
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Domain {
 	Honesty,
 	#[serde(alias = "Software development")]
@@ -91,6 +306,17 @@ pub enum Domain {
 	SoftwareSecurity,
 }
 
+impl Domain {
+	/// Name of the domain, as signed in typed data.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Domain::Honesty => "Honesty",
+			Domain::SoftwareDevelopment => "SoftwareDevelopment",
+			Domain::SoftwareSecurity => "SoftwareSecurity",
+		}
+	}
+}
+
 impl From<Domain> for u8 {
 	fn from(value: Domain) -> Self {
 		match value {
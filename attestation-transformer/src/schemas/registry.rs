@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use mm_spd_vc::schema::SchemaConfig;
+use proto_buf::indexer::IndexerEvent;
+
+use crate::error::AttTrError;
+use crate::policy::{WeightPolicy, Weights};
+use crate::schemas::{SchemaParser, SchemaType};
+use crate::term::Term;
+
+struct Registration {
+	kind: String,
+	parser: Box<dyn SchemaParser>,
+	weights: Weights,
+}
+
+/// Schema parsers by schema ID.
+///
+/// Credential types are mapped to schema IDs by the indexer, see `SchemaConfig::schema_id`.
+#[derive(Default)]
+pub struct SchemaRegistry {
+	schemas: HashMap<u32, Registration>,
+}
+
+impl SchemaRegistry {
+	/// Registry of the schemas in the config, with the built-in parsers named by their kinds,
+	/// weighing terms by the policy.
+	pub fn from_config(config: &SchemaConfig, policy: &WeightPolicy) -> Result<Self, AttTrError> {
+		let mut registry = Self::default();
+		for entry in &config.schemas {
+			let parser = entry.kind.parse::<SchemaType>()?.parser();
+			let weights = policy.weights(&entry.kind)?;
+			registry.register(entry.id, &entry.kind, parser, weights)?;
+		}
+		Ok(registry)
+	}
+
+	/// Register a parser for the schema ID.
+	pub fn register(
+		&mut self, id: u32, kind: &str, parser: Box<dyn SchemaParser>, weights: Weights,
+	) -> Result<(), AttTrError> {
+		if self.schemas.contains_key(&id) {
+			return Err(AttTrError::DuplicateSchema(id));
+		}
+		self.schemas.insert(id, Registration { kind: kind.to_string(), parser, weights });
+		Ok(())
+	}
+
+	/// Kind of the schema, i.e. the name of its parser.
+	pub fn kind(&self, id: u32) -> Option<&str> {
+		self.schemas.get(&id).map(|r| r.kind.as_str())
+	}
+
+	pub fn parse_event(&self, event: &IndexerEvent) -> Result<Vec<Term>, AttTrError> {
+		let registration =
+			self.schemas.get(&event.schema_id).ok_or(AttTrError::UnknownSchema(event.schema_id))?;
+		registration.parser.parse(&event.schema_value, event.timestamp, &registration.weights)
+	}
+}
+
+impl fmt::Debug for SchemaRegistry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut schemas: Vec<_> = self.schemas.iter().map(|(id, r)| (id, &r.kind)).collect();
+		schemas.sort();
+		f.debug_struct("SchemaRegistry").field("schemas", &schemas).finish()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use mm_spd_vc::schema::SchemaEntry;
+
+	use super::*;
+
+	fn event(schema_id: u32) -> IndexerEvent {
+		IndexerEvent { id: 0, schema_id, schema_value: "{}".to_string(), timestamp: 0 }
+	}
+
+	#[test]
+	fn should_register_schemas_from_config() {
+		let mut config = SchemaConfig::default();
+		config.schemas.push(SchemaEntry {
+			id: 7,
+			kind: "TrustCredential".to_string(),
+			types: vec!["PeerTrustCredential".to_string()],
+		});
+		let registry = SchemaRegistry::from_config(&config, &WeightPolicy::default()).unwrap();
+
+		assert_eq!(registry.kind(1), Some("StatusCredential"));
+		assert_eq!(registry.kind(7), Some("TrustCredential"));
+		assert_eq!(registry.kind(3), Some("RevocationCredential"));
+		// Known schema, invalid value.
+		assert!(matches!(
+			registry.parse_event(&event(7)),
+			Err(AttTrError::SerdeError(_))
+		));
+		assert!(matches!(
+			registry.parse_event(&event(4)),
+			Err(AttTrError::UnknownSchema(4))
+		));
+	}
+
+	#[test]
+	fn should_reject_invalid_config() {
+		let mut config = SchemaConfig::default();
+		config.schemas[0].kind = "AuditCredential".to_string();
+		assert!(matches!(
+			SchemaRegistry::from_config(&config, &WeightPolicy::default()),
+			Err(AttTrError::UnknownSchemaKind(_))
+		));
+
+		let mut config = SchemaConfig::default();
+		config.schemas[1].id = 0;
+		assert!(matches!(
+			SchemaRegistry::from_config(&config, &WeightPolicy::default()),
+			Err(AttTrError::DuplicateSchema(0))
+		));
+
+		let mut policy = WeightPolicy::default();
+		policy.schemas.remove("TrustCredential");
+		assert!(matches!(
+			SchemaRegistry::from_config(&SchemaConfig::default(), &policy),
+			Err(AttTrError::PolicyError(_))
+		));
+	}
+}
@@ -0,0 +1,238 @@
+use std::ops::RangeInclusive;
+
+use mm_spd_vc::OneOrMore;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::did::Did;
+use crate::error::AttTrError;
+use crate::policy::Weights;
+use crate::schemas::typed_data::{TypedData, Value};
+use crate::schemas::validity::ValidityPeriod;
+use crate::schemas::{IntoTerm, Proof, ProofType, Validation, DEFAULT_MESSAGE_VERSION};
+use crate::term::Term;
+
+/// Prefix of the signed message, so that no other credential's signature can pass for a revocation.
+const MESSAGE_TAG: &[u8] = b"revoke";
+/// Version of the signed message covering the issuance time of the revocation.
+pub const MESSAGE_VERSION_V2: u32 = 2;
+/// Prefix of the version 2 message, so that it can't be taken for a version 1 one.
+const MESSAGE_V2_TAG: &[u8] = b"revoke-v2";
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct CredentialSubject {
+	id: String,
+}
+
+impl CredentialSubject {
+	pub fn new(id: String) -> Self {
+		Self { id }
+	}
+}
+
+/// Revokes all the issuer's earlier opinions about the subject, of any schema and domain.
+///
+/// Revocations are as of their signed issuance time, so a replayed revocation
+/// doesn't revoke the opinions asserted after it.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationSchema {
+	#[serde(alias = "type")]
+	kind: OneOrMore<String>,
+	issuer: String,
+	credential_subject: CredentialSubject,
+	proof: Proof,
+	#[serde(flatten)]
+	validity: ValidityPeriod,
+}
+
+impl RevocationSchema {
+	/// The subject as its signed DID, which its terms are about.
+	fn subject(&self) -> Result<Did, AttTrError> {
+		Did::parse(self.credential_subject.id.clone())
+	}
+
+	pub fn new(
+		kind: String, issuer: String, credential_subject: CredentialSubject, proof: Proof,
+	) -> Self {
+		Self {
+			kind: OneOrMore::One(kind),
+			issuer,
+			credential_subject,
+			proof,
+			validity: ValidityPeriod::default(),
+		}
+	}
+}
+
+impl Validation for RevocationSchema {
+	fn get_proof(&self) -> &Proof {
+		&self.proof
+	}
+
+	fn get_message_versions(&self) -> RangeInclusive<u32> {
+		DEFAULT_MESSAGE_VERSION..=MESSAGE_VERSION_V2
+	}
+
+	/// Version 2 also covers the issuance time, in milliseconds:
+	///
+	/// ```text
+	/// version 1: "revoke", schema, key
+	/// version 2: "revoke-v2", schema, key, issuance time (u64)
+	/// ```
+	fn get_message(&self) -> Result<Vec<u8>, AttTrError> {
+		let did = self.subject()?;
+
+		let mut bytes = match self.proof.get_message_version() {
+			MESSAGE_VERSION_V2 => MESSAGE_V2_TAG.to_vec(),
+			_ => MESSAGE_TAG.to_vec(),
+		};
+		bytes.extend_from_slice(&did.to_bytes()?);
+		if self.proof.get_message_version() == MESSAGE_VERSION_V2 {
+			bytes.extend_from_slice(&self.issued_at()?.to_be_bytes());
+		}
+
+		Ok(bytes)
+	}
+
+	/// Version 2 adds the issuance time, in milliseconds.
+	fn get_typed_data(&self) -> Result<TypedData, AttTrError> {
+		let typed_data = TypedData::new("RevocationCredential")
+			.member("subject", Value::String(self.credential_subject.id.clone()));
+		if self.proof.get_message_version() != MESSAGE_VERSION_V2 {
+			return Ok(typed_data);
+		}
+		Ok(typed_data.member("issuedAt", Value::Uint256(self.issued_at()?)))
+	}
+}
+
+impl RevocationSchema {
+	/// Issuance time, in milliseconds, required by the version 2 message.
+	fn issued_at(&self) -> Result<u64, AttTrError> {
+		self.validity.from()?.ok_or_else(|| AttTrError::DateError("no issuance date".to_string()))
+	}
+}
+
+impl IntoTerm for RevocationSchema {
+	fn get_issuer_did(&self) -> &str {
+		&self.issuer
+	}
+
+	fn get_validity_period(&self) -> &ValidityPeriod {
+		&self.validity
+	}
+
+	/// Version 1 revocations sign no time, so they are only accepted
+	/// while legacy messages are, and are as of the event timestamp.
+	fn into_verified_term(
+		self, from_did: String, timestamp: u64, weights: &Weights,
+	) -> Result<Vec<Term>, AttTrError> {
+		// Data Integrity proofs cover the whole credential, issuance date included.
+		let timestamp = match (self.proof.get_type(), self.proof.get_message_version()) {
+			(ProofType::DataIntegrity, _) => self.validity.from()?.unwrap_or(timestamp),
+			(_, MESSAGE_VERSION_V2) => self.issued_at()?,
+			(_, message_version) => {
+				if !weights.accepts_legacy_message(timestamp) {
+					return Err(AttTrError::UnsupportedMessageVersion(message_version));
+				}
+				timestamp
+			},
+		};
+		Ok(vec![Term::revoke_all(
+			from_did,
+			self.subject()?.into(),
+			timestamp,
+		)])
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use secp256k1::rand::thread_rng;
+	use secp256k1::{generate_keypair, Message, Secp256k1};
+	use sha3::{Digest, Keccak256};
+
+	use crate::policy::WeightPolicy;
+	use crate::term::TermKind;
+	use crate::utils::address_from_ecdsa_key;
+
+	use super::*;
+
+	fn sign(message: &[u8]) -> (String, Proof) {
+		let digest = Keccak256::digest(message);
+		let message = Message::from_digest_slice(digest.as_ref()).unwrap();
+		let (sk, pk) = generate_keypair(&mut thread_rng());
+		let (rec_id, sig_bytes) =
+			Secp256k1::new().sign_ecdsa_recoverable(&message, &sk).serialize_compact();
+		let mut bytes = sig_bytes.to_vec();
+		bytes.push(rec_id.to_i32() as u8);
+
+		let issuer = format!("did:pkh:eth:0x{}", hex::encode(address_from_ecdsa_key(&pk)));
+		(issuer, Proof::new(hex::encode(bytes)))
+	}
+
+	#[test]
+	fn should_revoke_as_of_signed_issuance_time() {
+		let did_string = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
+		let did = Did::parse_snap(did_string.clone()).unwrap();
+		// 2024-01-01, in milliseconds.
+		let issued_at = 1704067200000u64;
+
+		let mut message = MESSAGE_V2_TAG.to_vec();
+		message.extend_from_slice(&did.to_bytes().unwrap());
+		message.extend_from_slice(&issued_at.to_be_bytes());
+		let (issuer, proof) = sign(&message);
+		let mut schema = RevocationSchema::new(
+			"RevocationCredential".to_string(),
+			issuer.clone(),
+			CredentialSubject::new(did_string.clone()),
+			proof.with_message_version(MESSAGE_VERSION_V2),
+		);
+		schema.validity.issuance_date = Some("2024-01-01T00:00:00Z".to_string());
+		let mut weights = WeightPolicy::default().weights("RevocationCredential").unwrap();
+		weights.legacy_messages_until = 0;
+
+		let terms = schema.clone().into_term(issued_at + 5, &weights).unwrap();
+		assert_eq!(terms, vec![Term::revoke_all(issuer, did_string, issued_at)]);
+
+		// The issuance time is signed.
+		schema.validity.issuance_date = Some("2024-01-02T00:00:00Z".to_string());
+		assert!(matches!(
+			schema.clone().into_term(issued_at + 5, &weights),
+			Err(AttTrError::VerificationError)
+		));
+		schema.validity.issuance_date = None;
+		assert!(matches!(
+			schema.into_term(issued_at + 5, &weights),
+			Err(AttTrError::DateError(_))
+		));
+	}
+
+	#[test]
+	fn should_revoke_opinions_about_subject() {
+		let did_string = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
+		let did = Did::parse_snap(did_string.clone()).unwrap();
+
+		let mut message = MESSAGE_TAG.to_vec();
+		message.extend_from_slice(&did.to_bytes().unwrap());
+		let (issuer, proof) = sign(&message);
+		let schema = RevocationSchema::new(
+			"RevocationCredential".to_string(),
+			issuer.clone(),
+			CredentialSubject::new(did_string.clone()),
+			proof,
+		);
+		let weights = WeightPolicy::default().weights("RevocationCredential").unwrap();
+
+		let terms = schema.clone().into_term(5, &weights).unwrap();
+
+		assert_eq!(terms, vec![Term::revoke_all(issuer, did_string, 5)]);
+		assert_eq!(terms[0].kind(), TermKind::Revocation);
+
+		// Version 1 signs no time, so it is replayable, and only accepted until the cutoff.
+		let weights = Weights { legacy_messages_until: 4, ..weights };
+		assert!(matches!(
+			schema.into_term(5, &weights),
+			Err(AttTrError::UnsupportedMessageVersion(1))
+		));
+	}
+}
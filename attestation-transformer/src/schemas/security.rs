@@ -1,10 +1,13 @@
+use mm_spd_vc::OneOrMore;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::did::{Did, Schema};
+use crate::did::Did;
 use crate::error::AttTrError;
+use crate::policy::Weights;
+use crate::schemas::typed_data::{TypedData, Value};
+use crate::schemas::validity::ValidityPeriod;
 use crate::schemas::{Domain, IntoTerm, Proof, Validation};
 use crate::term::Term;
-use crate::utils::address_from_ecdsa_key;
 
 #[derive(Deserialize, Serialize, Clone)]
 pub enum SecurityStatus {
@@ -21,7 +24,43 @@ impl From<SecurityStatus> for u8 {
 	}
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+/// Qualitative severity of a finding, in the bands of CVSS v3 scaled down to criticalities
+/// between 0 and 1.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+	None,
+	Low,
+	Medium,
+	High,
+	Critical,
+}
+
+impl Severity {
+	pub fn from_criticality(criticality: f32) -> Self {
+		match criticality {
+			c if c >= 0.9 => Self::Critical,
+			c if c >= 0.7 => Self::High,
+			c if c >= 0.4 => Self::Medium,
+			c if c > 0. => Self::Low,
+			_ => Self::None,
+		}
+	}
+
+	/// Score the findings of the severity count for, the lower bound of its band.
+	pub fn score(&self) -> f32 {
+		match self {
+			Self::None => 0.,
+			Self::Low => 0.1,
+			Self::Medium => 0.4,
+			Self::High => 0.7,
+			Self::Critical => 1.,
+		}
+	}
+}
+
+/// Only the criticality is signed, so the severity is derived from it, and only it goes into the
+/// weight of the terms. The other details are unsigned: they are kept just to explain the terms.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct SecurityFinding {
 	criticality: f32,
 	#[serde(alias = "type")]
@@ -36,6 +75,29 @@ impl SecurityFinding {
 	) -> Self {
 		Self { criticality, kind, description, lang }
 	}
+
+	pub fn criticality(&self) -> f32 {
+		self.criticality
+	}
+
+	pub fn severity(&self) -> Severity {
+		Severity::from_criticality(self.criticality)
+	}
+
+	/// Unsigned, e.g. `XSS`.
+	pub fn kind(&self) -> Option<&str> {
+		self.kind.as_deref()
+	}
+
+	/// Unsigned.
+	pub fn description(&self) -> Option<&str> {
+		self.description.as_deref()
+	}
+
+	/// Unsigned language of the description.
+	pub fn lang(&self) -> Option<&str> {
+		self.lang.as_deref()
+	}
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -58,30 +120,42 @@ impl CredentialSubject {
 #[serde(rename_all = "camelCase")]
 pub struct SecurityReportSchema {
 	#[serde(alias = "type")]
-	kind: String,
+	kind: OneOrMore<String>,
 	issuer: String,
 	credential_subject: CredentialSubject,
 	proof: Proof,
+	#[serde(flatten)]
+	validity: ValidityPeriod,
 }
 
 impl SecurityReportSchema {
 	pub fn new(
 		kind: String, issuer: String, credential_subject: CredentialSubject, proof: Proof,
 	) -> Self {
-		Self { kind, issuer, credential_subject, proof }
+		Self {
+			kind: OneOrMore::One(kind),
+			issuer,
+			credential_subject,
+			proof,
+			validity: ValidityPeriod::default(),
+		}
+	}
+
+	/// The subject as its signed snap id, which its terms are about.
+	fn subject(&self) -> Result<Did, AttTrError> {
+		Did::parse_snap_id(self.credential_subject.id.clone())
 	}
 }
 
 impl Validation for SecurityReportSchema {
-	fn get_trimmed_signature(&self) -> String {
-		self.proof.get_signature().trim_start_matches("0x").to_owned()
+	fn get_proof(&self) -> &Proof {
+		&self.proof
 	}
 
 	fn get_message(&self) -> Result<Vec<u8>, AttTrError> {
-		let did = Did::parse_snap(self.credential_subject.id.clone())?;
+		let did = self.subject()?;
 		let mut bytes = Vec::new();
-		bytes.push(did.schema.into());
-		bytes.extend_from_slice(&did.key);
+		bytes.extend_from_slice(&did.to_bytes()?);
 		bytes.push(self.credential_subject.security_status.clone().into());
 		for finding in &self.credential_subject.security_findings {
 			bytes.extend(finding.criticality.to_be_bytes());
@@ -89,45 +163,66 @@ impl Validation for SecurityReportSchema {
 
 		Ok(bytes)
 	}
+
+	/// The criticalities are signed as fixed-point decimals, e.g. `0.500000`.
+	fn get_typed_data(&self) -> Result<TypedData, AttTrError> {
+		let status = match self.credential_subject.security_status {
+			SecurityStatus::Unsecure => "Unsecure",
+			SecurityStatus::Secure => "Secure",
+		};
+		let findings = self.credential_subject.security_findings.iter();
+		let criticalities = findings.map(|f| f.criticality);
+		Ok(TypedData::new("SecurityReportCredential")
+			.member("subject", Value::String(self.credential_subject.id.clone()))
+			.member("securityStatus", Value::String(status.to_string()))
+			.member("criticalities", Value::decimals(criticalities)))
+	}
 }
 
 impl IntoTerm for SecurityReportSchema {
-	fn into_term(self, timestamp: u64) -> Result<Vec<Term>, AttTrError> {
-		let pk = self.validate()?;
+	fn get_issuer_did(&self) -> &str {
+		&self.issuer
+	}
 
-		let from_address = address_from_ecdsa_key(&pk);
-		let from_did: String = Did::new(Schema::PkhEth, from_address).into();
-		if from_did != self.issuer {
-			return Err(AttTrError::VerificationError);
-		}
+	fn get_validity_period(&self) -> &ValidityPeriod {
+		&self.validity
+	}
 
+	fn into_verified_term(
+		self, from_did: String, timestamp: u64, weights: &Weights,
+	) -> Result<Vec<Term>, AttTrError> {
 		let form = match self.credential_subject.security_status {
 			SecurityStatus::Unsecure => false,
 			SecurityStatus::Secure => true,
 		};
 
-		let weight = 50.;
-		let mut terms = Vec::new();
-		if form {
-			let term = Term::new(
-				from_did,
-				self.credential_subject.id,
-				weight,
-				Domain::SoftwareSecurity.into(),
-				form,
-				timestamp,
-			);
-			terms.push(term);
+		let subject: String = self.subject()?.into();
+		let (domains, weight) = weights.terms(&Domain::SoftwareSecurity)?;
+		// One distrust term for all the findings of an unsecure report, none if there are none.
+		let findings = self.credential_subject.security_findings;
+		let value = if form {
+			Some(weight)
+		} else if findings.is_empty() {
+			None
 		} else {
-			for finding in &self.credential_subject.security_findings {
+			let scores = findings.iter().map(|finding| finding.severity().score());
+			Some(weights.findings_aggregation.aggregate(scores) * weight)
+		};
+		let mut terms = Vec::new();
+		// The findings explain the weight of all the terms, so they are kept on the first only.
+		let mut findings = Some(findings);
+		for domain in domains {
+			if let Some(value) = value {
 				let term = Term::new(
 					from_did.clone(),
-					self.credential_subject.id.clone(),
-					finding.criticality * weight,
-					Domain::SoftwareSecurity.into(),
+					subject.clone(),
+					value,
+					domain.into(),
 					form,
 					timestamp,
-				);
+				)
+				.with_policy_version(weights.version)
+				.with_findings(findings.take().unwrap_or_default());
 				terms.push(term);
 			}
 		}
@@ -138,10 +233,12 @@ impl IntoTerm for SecurityReportSchema {
 
 #[cfg(test)]
 mod test {
+	use proto_buf::transformer::RejectionKind;
 	use secp256k1::{generate_keypair, rand::thread_rng, Message, Secp256k1};
 	use sha3::{Digest, Keccak256};
 
 	use crate::did::Did;
+	use crate::policy::{DomainWeight, WeightPolicy};
 	use crate::schemas::{Proof, Validation};
 	use crate::utils::address_from_ecdsa_key;
 
@@ -152,7 +249,7 @@ mod test {
 		let did_string = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
 		let did = Did::parse_snap(did_string.clone()).unwrap();
 		let security_status = SecurityStatus::Unsecure;
-		let finding = SecurityFinding::new(0.5, None, None, None);
+		let finding = SecurityFinding::new(0.5, Some("XSS".to_string()), None, None);
 
 		let mut keccak = Keccak256::default();
 		keccak.update([did.schema.into()]);
@@ -179,11 +276,109 @@ mod test {
 		let address = address_from_ecdsa_key(&pk);
 		let issuer = format!("did:pkh:eth:{}", hex::encode(address));
 		let cs = CredentialSubject::new(did_string, security_status, vec![finding]);
-		let proof = Proof::new(sig_string);
+		let proof = Proof::new(sig_string.clone());
 
 		let aa_schema = SecurityReportSchema::new(kind, issuer, cs, proof);
 		let rec_pk = aa_schema.validate().unwrap();
 
 		assert_eq!(rec_pk, pk);
+
+		// Malformed signatures are rejected as such, not as unparsable DIDs.
+		let rs = &sig_string[..128];
+		for signature in [rs.to_string(), format!("{}1f", rs)] {
+			let mut schema = aa_schema.clone();
+			schema.proof = Proof::new(signature);
+			let err = schema.validate().unwrap_err();
+			assert_eq!(RejectionKind::from(&err), RejectionKind::Signature);
+		}
+	}
+
+	#[test]
+	fn should_aggregate_findings_by_severity() {
+		let finding = |criticality| SecurityFinding::new(criticality, None, None, None);
+		assert_eq!(finding(0.95).severity(), Severity::Critical);
+		assert_eq!(finding(0.7).severity(), Severity::High);
+		assert_eq!(finding(0.5).severity(), Severity::Medium);
+		assert_eq!(finding(0.01).severity(), Severity::Low);
+		assert_eq!(finding(0.).severity(), Severity::None);
+
+		let subject = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
+		let mut findings = vec![finding(0.1); 20];
+		findings.push(SecurityFinding::new(
+			0.95,
+			Some("XSS".to_string()),
+			Some("Unescaped input".to_string()),
+			Some("en".to_string()),
+		));
+		let cs =
+			CredentialSubject::new(subject.clone(), SecurityStatus::Unsecure, findings.clone());
+		let kind = "SecurityReportCredential".to_string();
+		let schema = SecurityReportSchema::new(kind, String::new(), cs, Proof::new(String::new()));
+		let mut weights = WeightPolicy::default().weights("SecurityCredential").unwrap();
+
+		let terms = schema.clone().into_verified_term("did:pkh:eth:0x1".to_string(), 3, &weights);
+		let term = Term::new("did:pkh:eth:0x1".to_string(), subject, 50., 2, false, 3);
+		assert_eq!(
+			terms.unwrap(),
+			vec![term.clone().with_findings(findings.clone())]
+		);
+
+		// Fanned out, the findings are kept on the first term only.
+		let fan_out = vec![Domain::SoftwareSecurity, Domain::SoftwareDevelopment];
+		let rule = DomainWeight { weight: 50., fan_out: Some(fan_out) };
+		weights.domains.insert(Domain::SoftwareSecurity, rule);
+		let terms = schema.into_verified_term("did:pkh:eth:0x1".to_string(), 3, &weights);
+		let development = Term::new(
+			"did:pkh:eth:0x1".to_string(),
+			term.to().into(),
+			50.,
+			1,
+			false,
+			3,
+		);
+		assert_eq!(
+			terms.unwrap(),
+			vec![term.with_findings(findings), development]
+		);
+	}
+
+	#[test]
+	fn should_keep_unsigned_finding_details_out_of_the_weight() {
+		let json =
+			r#"{"criticality": 0.5, "type": "XSS", "description": "Unescaped", "lang": "en"}"#;
+		let finding: SecurityFinding = serde_json::from_str(json).unwrap();
+		let details = (
+			Some("XSS".to_string()),
+			Some("Unescaped".to_string()),
+			Some("en".to_string()),
+		);
+		assert_eq!(
+			finding,
+			SecurityFinding::new(0.5, details.0, details.1, details.2)
+		);
+
+		// Neither the message nor the weight depends on the details.
+		let subject = "npm:foo".to_string();
+		let report = |finding| {
+			let cs =
+				CredentialSubject::new(subject.clone(), SecurityStatus::Unsecure, vec![finding]);
+			let kind = "SecurityReportCredential".to_string();
+			SecurityReportSchema::new(kind, String::new(), cs, Proof::new(String::new()))
+		};
+		let (detailed, bare) = (
+			report(finding),
+			report(SecurityFinding::new(0.5, None, None, None)),
+		);
+		assert_eq!(detailed.get_message().unwrap(), bare.get_message().unwrap());
+		let weights = WeightPolicy::default().weights("SecurityCredential").unwrap();
+		let from = "did:pkh:eth:0x1".to_string();
+		let detailed = detailed.into_verified_term(from.clone(), 3, &weights).unwrap();
+		let bare = bare.into_verified_term(from, 3, &weights).unwrap();
+		assert_eq!(detailed.len(), 1);
+		assert_eq!(
+			detailed[0].clone().with_findings(Vec::new()),
+			bare[0].clone().with_findings(Vec::new())
+		);
+		assert_eq!(detailed[0].findings()[0].kind(), Some("XSS"));
 	}
 }
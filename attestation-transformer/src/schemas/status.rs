@@ -1,10 +1,13 @@
+use mm_spd_vc::OneOrMore;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::did::{Did, Schema};
+use crate::did::Did;
 use crate::error::AttTrError;
+use crate::policy::Weights;
+use crate::schemas::typed_data::{TypedData, Value};
+use crate::schemas::validity::ValidityPeriod;
 use crate::schemas::{Domain, IntoTerm, Proof, Validation};
 use crate::term::Term;
-use crate::utils::address_from_ecdsa_key;
 
 #[derive(Deserialize, Serialize, Clone)]
 pub enum CurrentStatus {
@@ -38,81 +41,123 @@ impl CredentialSubject {
 #[serde(rename_all = "camelCase")]
 pub struct StatusSchema {
 	#[serde(alias = "type")]
-	kind: String,
+	kind: OneOrMore<String>,
 	issuer: String,
 	credential_subject: CredentialSubject,
 	proof: Proof,
+	#[serde(flatten)]
+	validity: ValidityPeriod,
 }
 
 impl StatusSchema {
 	pub fn new(
 		kind: String, issuer: String, credential_subject: CredentialSubject, proof: Proof,
 	) -> Self {
-		Self { kind, issuer, credential_subject, proof }
+		Self {
+			kind: OneOrMore::One(kind),
+			issuer,
+			credential_subject,
+			proof,
+			validity: ValidityPeriod::default(),
+		}
 	}
 
 	pub fn get_issuer(&self) -> String {
 		self.issuer.clone()
 	}
+
+	/// The subject as its signed snap id, which its terms are about.
+	fn subject(&self) -> Result<Did, AttTrError> {
+		Did::parse_snap_id(self.credential_subject.id.clone())
+	}
 }
 
 impl Validation for StatusSchema {
-	fn get_trimmed_signature(&self) -> String {
-		self.proof.get_signature().trim_start_matches("0x").to_owned()
+	fn get_proof(&self) -> &Proof {
+		&self.proof
 	}
 
 	fn get_message(&self) -> Result<Vec<u8>, AttTrError> {
-		let did = Did::parse_snap(self.credential_subject.id.clone())?;
+		let did = self.subject()?;
 		let mut bytes = Vec::new();
-		bytes.push(did.schema.into());
-		bytes.extend_from_slice(&did.key);
+		bytes.extend_from_slice(&did.to_bytes()?);
 		bytes.push(self.credential_subject.current_status.clone().into());
 
 		Ok(bytes)
 	}
+
+	fn get_typed_data(&self) -> Result<TypedData, AttTrError> {
+		let status = match self.credential_subject.current_status {
+			CurrentStatus::Endorsed => "Endorsed",
+			CurrentStatus::Disputed => "Disputed",
+		};
+		Ok(TypedData::new("StatusCredential")
+			.member("subject", Value::String(self.credential_subject.id.clone()))
+			.member("currentStatus", Value::String(status.to_string())))
+	}
 }
 
 impl IntoTerm for StatusSchema {
-	fn into_term(self, timestamp: u64) -> Result<Vec<Term>, AttTrError> {
-		let pk = self.validate()?;
+	fn get_issuer_did(&self) -> &str {
+		&self.issuer
+	}
 
-		let from_address = address_from_ecdsa_key(&pk);
-		let from_did: String = Did::new(Schema::PkhEth, from_address).into();
-		if from_did != self.issuer {
-			return Err(AttTrError::VerificationError);
-		}
+	fn get_validity_period(&self) -> &ValidityPeriod {
+		&self.validity
+	}
 
-		let weight = 50.;
-		let domain = Domain::SoftwareSecurity;
+	fn into_verified_term(
+		self, from_did: String, timestamp: u64, weights: &Weights,
+	) -> Result<Vec<Term>, AttTrError> {
+		let subject: String = self.subject()?.into();
+		let (domains, weight) = weights.terms(&Domain::SoftwareSecurity)?;
 		let form = match self.credential_subject.current_status {
 			CurrentStatus::Endorsed => true,
 			CurrentStatus::Disputed => false,
 		};
 
-		let term = Term::new(
-			from_did,
-			self.credential_subject.id,
-			weight,
-			domain.into(),
-			form,
-			timestamp,
-		);
-		Ok(vec![term])
+		let terms = domains
+			.into_iter()
+			.map(|domain| {
+				Term::new(
+					from_did.clone(),
+					subject.clone(),
+					weight,
+					domain.into(),
+					form,
+					timestamp,
+				)
+				.with_policy_version(weights.version)
+			})
+			.collect();
+		Ok(terms)
 	}
 }
 
 #[cfg(test)]
 mod test {
+	use proto_buf::transformer::RejectionKind;
 	use secp256k1::rand::thread_rng;
-	use secp256k1::{generate_keypair, Message, Secp256k1};
+	use secp256k1::{generate_keypair, Message, PublicKey, Secp256k1};
 	use sha3::{Digest, Keccak256};
 
 	use crate::did::Did;
-	use crate::schemas::{Proof, Validation};
+	use mm_spd_vc::error::VcError;
+	use mm_spd_vc::integrity;
+	use mm_spd_vc::proof::Signer;
+	use serde_json::json;
+
+	use crate::policy::WeightPolicy;
+	use crate::schemas::{
+		personal_message_digest, JsonSchema, Proof, ProofType, SchemaParser, Validation,
+	};
 	use crate::utils::address_from_ecdsa_key;
 
 	use super::*;
 
+	/// 2024-02-01T00:00:00Z, in milliseconds.
+	const FEB_1: u64 = 1706745600000;
+
 	#[test]
 	fn should_validate_endorse_credential() {
 		let did_string = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
@@ -143,12 +188,205 @@ mod test {
 		let address = address_from_ecdsa_key(&pk);
 		let issuer = format!("did:pkh:eth:0x{}", hex::encode(address));
 		let cs = CredentialSubject { id: did_string, current_status };
-		let proof = Proof { signature: sig_string };
+		let proof = Proof::new(sig_string);
 
-		let follow_schema = StatusSchema { kind, issuer, credential_subject: cs, proof };
+		let follow_schema = StatusSchema {
+			kind: OneOrMore::One(kind),
+			issuer,
+			credential_subject: cs,
+			proof,
+			validity: ValidityPeriod::default(),
+		};
 
 		let rec_pk = follow_schema.validate().unwrap();
 
 		assert_eq!(rec_pk, pk);
 	}
+
+	fn sign(digest: &[u8; 32]) -> (String, PublicKey) {
+		let message = Message::from_digest_slice(digest).unwrap();
+		let (sk, pk) = generate_keypair(&mut thread_rng());
+		let res = Secp256k1::new().sign_ecdsa_recoverable(&message, &sk);
+		let (rec_id, sig_bytes) = res.serialize_compact();
+
+		let mut bytes = sig_bytes.to_vec();
+		// Wallets use 27/28 recovery ids.
+		bytes.push(27 + rec_id.to_i32() as u8);
+		(format!("0x{}", hex::encode(bytes)), pk)
+	}
+
+	#[test]
+	fn should_validate_wallet_proofs() {
+		let id = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
+		let cs = CredentialSubject::new(id, CurrentStatus::Disputed);
+		let unsigned = |proof| {
+			StatusSchema::new(
+				"StatusCredential".to_string(),
+				String::new(),
+				cs.clone(),
+				proof,
+			)
+		};
+
+		let schema = unsigned(Proof::new(String::new()));
+		let (signature, pk) = sign(&personal_message_digest(&schema.get_message().unwrap()));
+		let json = format!(r#"{{"type": "Eip191", "signature": "{}"}}"#, signature);
+		let schema = unsigned(serde_json::from_str(&json).unwrap());
+		assert_eq!(schema.validate().unwrap(), pk);
+
+		let (signature, pk) = sign(&schema.get_typed_data().unwrap().digest().unwrap());
+		let json = format!(r#"{{"type": "Eip712", "signature": "{}"}}"#, signature);
+		let schema = unsigned(serde_json::from_str(&json).unwrap());
+		assert_eq!(schema.validate().unwrap(), pk);
+
+		// The same signature under another proof type recovers another key.
+		let proof = Proof::with_type(ProofType::Eip191, signature);
+		assert_ne!(unsigned(proof).validate().ok(), Some(pk));
+	}
+
+	#[test]
+	fn should_emit_terms_about_signed_subject() {
+		// Signed as the same bytes as `snap://0x90f8...`.
+		let id = "snap://90F8BF6A479F320EAD074411A4B0E7944EA8C9C2".to_owned();
+		let cs = CredentialSubject::new(id, CurrentStatus::Endorsed);
+		let schema = StatusSchema::new(
+			"StatusCredential".to_string(),
+			String::new(),
+			cs,
+			Proof::new(String::new()),
+		);
+		let weights = WeightPolicy::default().weights("StatusCredential").unwrap();
+
+		let terms = schema.into_verified_term("did:pkh:eth:0x1".to_string(), 5, &weights).unwrap();
+		assert_eq!(
+			terms[0].to(),
+			"snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2"
+		);
+	}
+
+	#[test]
+	fn should_check_but_not_apply_unsigned_validity_period() {
+		let id = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
+		let cs = CredentialSubject::new(id.clone(), CurrentStatus::Endorsed);
+		let mut schema = StatusSchema::new(
+			"StatusCredential".to_string(),
+			String::new(),
+			cs,
+			Proof::new(String::new()),
+		);
+		let (signature, pk) = sign(&Keccak256::digest(schema.get_message().unwrap()).into());
+		schema.proof = Proof::new(signature);
+		schema.issuer = format!("did:pkh:eth:0x{}", hex::encode(address_from_ecdsa_key(&pk)));
+		let weights = WeightPolicy::default().weights("StatusCredential").unwrap();
+		let parse = |issuance_date: &str, expiration_date: &str| {
+			let mut schema = schema.clone();
+			schema.validity = ValidityPeriod {
+				issuance_date: Some(issuance_date.to_string()),
+				expiration_date: Some(expiration_date.to_string()),
+				..Default::default()
+			};
+			let json = serde_json::to_string(&schema).unwrap();
+			JsonSchema::<StatusSchema>::new().parse(&json, FEB_1, &weights)
+		};
+
+		// Neither dated nor expired by the period its legacy signature doesn't cover.
+		let terms = parse("2024-01-01T00:00:00Z", "2030-01-01T00:00:00Z").unwrap();
+		let term = Term::new(schema.issuer.clone(), id, 50., 2, true, FEB_1);
+		assert_eq!(
+			terms,
+			vec![term.into_canonical(&Default::default()).unwrap()]
+		);
+
+		// Still rejected if not valid at the event.
+		for (from, until) in [
+			("2030-01-01T00:00:00Z", "2031-01-01T00:00:00Z"),
+			("2020-01-01T00:00:00Z", "2024-01-01T00:00:00Z"),
+			("2024-01-01", "2030-01-01T00:00:00Z"),
+		] {
+			let err = parse(from, until).unwrap_err();
+			assert_eq!(
+				RejectionKind::from(&err),
+				RejectionKind::Validity,
+				"{} {}",
+				from,
+				until
+			);
+		}
+	}
+
+	#[test]
+	fn should_keep_snap_versions_under_shipped_policy() {
+		let id = "npm:@Consensys/Foo@1.2.0".to_owned();
+		let cs = CredentialSubject::new(id, CurrentStatus::Endorsed);
+		let mut schema = StatusSchema::new(
+			"StatusCredential".to_string(),
+			String::new(),
+			cs,
+			Proof::new(String::new()),
+		);
+		let (signature, pk) = sign(&Keccak256::digest(schema.get_message().unwrap()).into());
+		schema.proof = Proof::new(signature);
+		schema.issuer = format!("did:pkh:eth:0x{}", hex::encode(address_from_ecdsa_key(&pk)));
+		let json = serde_json::to_string(&schema).unwrap();
+
+		let path = concat!(env!("CARGO_MANIFEST_DIR"), "/weights.json");
+		let policy = WeightPolicy::from_file(path).unwrap();
+		let weights = policy.weights("StatusCredential").unwrap();
+		let terms = JsonSchema::<StatusSchema>::new().parse(&json, 5, &weights).unwrap();
+		assert_eq!(terms[0].to(), "npm:@consensys/foo@1.2.0");
+
+		// Without the setting, the versions are grouped under the package.
+		let weights = WeightPolicy::default().weights("StatusCredential").unwrap();
+		let terms = JsonSchema::<StatusSchema>::new().parse(&json, 5, &weights).unwrap();
+		assert_eq!(terms[0].to(), "npm:@consensys/foo");
+	}
+
+	#[test]
+	fn should_parse_data_integrity_credential() {
+		let sk = secp256k1::SecretKey::new(&mut thread_rng());
+		let signer = Signer::new(sk);
+		let subject = "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2";
+		let mut credential = json!({
+			"@context": ["https://www.w3.org/2018/credentials/v1"],
+			"type": ["VerifiableCredential", "StatusCredential"],
+			"issuer": signer.did(),
+			"issuanceDate": "2024-01-01T00:00:00Z",
+			"expirationDate": "2024-03-01T00:00:00Z",
+			"credentialSubject": { "id": subject, "currentStatus": "Endorsed" },
+			"proof": {
+				"type": integrity::ECDSA_SECP256K1_SIGNATURE_2019,
+				"proofPurpose": "assertionMethod",
+				"verificationMethod": signer.verification_method(),
+			}
+		});
+		credential["proof"]["jws"] = json!(integrity::sign_jws(&sk, &credential).unwrap());
+		let weights = WeightPolicy::default().weights("StatusCredential").unwrap();
+		let parser = JsonSchema::<StatusSchema>::new();
+
+		// 2024-02-01 and 2024-03-01, in milliseconds.
+		let (timestamp, expiry) = (1706745600000, 1709251200000);
+		let terms = parser.parse(&credential.to_string(), timestamp, &weights).unwrap();
+		let term = Term::new(
+			signer.did().to_string(),
+			subject.to_string(),
+			50.,
+			2,
+			true,
+			timestamp,
+		);
+		assert_eq!(
+			terms,
+			vec![term.with_expiry(Some(expiry)).into_canonical(&Default::default()).unwrap()]
+		);
+		assert!(matches!(
+			parser.parse(&credential.to_string(), expiry, &weights),
+			Err(AttTrError::Expired(_))
+		));
+
+		credential["credentialSubject"]["currentStatus"] = json!("Disputed");
+		assert!(matches!(
+			parser.parse(&credential.to_string(), timestamp, &weights),
+			Err(AttTrError::ProofError(VcError::InvalidSignature))
+		));
+	}
 }
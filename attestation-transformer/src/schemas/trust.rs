@@ -1,10 +1,26 @@
+use std::ops::RangeInclusive;
+
+use mm_spd_vc::OneOrMore;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::did::{Did, Schema};
+use crate::did::Did;
 use crate::error::AttTrError;
-use crate::schemas::{Domain, IntoTerm, Proof, Validation};
+use crate::policy::Weights;
+use crate::schemas::typed_data::{TypedData, Value};
+use crate::schemas::validity::ValidityPeriod;
+use crate::schemas::{Domain, IntoTerm, Proof, ProofType, Validation, DEFAULT_MESSAGE_VERSION};
 use crate::term::Term;
-use crate::utils::address_from_ecdsa_key;
+
+/// Version of the signed message covering the trust levels and reasons.
+pub const MESSAGE_VERSION_V2: u32 = 2;
+/// Prefix of the version 2 message, so that it can't be taken for a version 1 one.
+const MESSAGE_V2_TAG: &[u8] = b"trust-v2";
+
+fn push_len(bytes: &mut Vec<u8>, len: usize) -> Result<(), AttTrError> {
+	let len = u32::try_from(len).map_err(|_| AttTrError::SerialisationError)?;
+	bytes.extend_from_slice(&len.to_be_bytes());
+	Ok(())
+}
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct DomainTrust {
@@ -35,96 +51,131 @@ impl CredentialSubject {
 #[serde(rename_all = "camelCase")]
 pub struct TrustSchema {
 	#[serde(alias = "type")]
-	kind: String,
+	kind: OneOrMore<String>,
 	issuer: String,
 	credential_subject: CredentialSubject,
 	proof: Proof,
+	#[serde(flatten)]
+	validity: ValidityPeriod,
 }
 
 impl TrustSchema {
 	pub fn new(
 		kind: String, issuer: String, credential_subject: CredentialSubject, proof: Proof,
 	) -> Self {
-		Self { kind, issuer, credential_subject, proof }
+		Self {
+			kind: OneOrMore::One(kind),
+			issuer,
+			credential_subject,
+			proof,
+			validity: ValidityPeriod::default(),
+		}
+	}
+
+	/// The subject as its signed peer DID, which its terms are about.
+	fn subject(&self) -> Result<Did, AttTrError> {
+		Did::parse_peer(self.credential_subject.id.clone())
 	}
 }
 
 impl Validation for TrustSchema {
-	fn get_trimmed_signature(&self) -> String {
-		self.proof.get_signature().trim_start_matches("0x").to_owned()
+	fn get_proof(&self) -> &Proof {
+		&self.proof
+	}
+
+	fn get_message_versions(&self) -> RangeInclusive<u32> {
+		DEFAULT_MESSAGE_VERSION..=MESSAGE_VERSION_V2
 	}
 
+	/// Version 1 only covers the scopes, version 2 also the levels and reasons:
+	///
+	/// ```text
+	/// version 1: schema, key, [scope]
+	/// version 2: "trust-v2", schema, key, [scope, level (f32), u32 count, [u32 length, reason]]
+	/// ```
 	fn get_message(&self) -> Result<Vec<u8>, AttTrError> {
-		let did = Did::parse_pkh_eth(self.credential_subject.id.clone())?;
+		let did = self.subject()?;
+		let is_v2 = self.proof.get_message_version() == MESSAGE_VERSION_V2;
 
 		let mut bytes = Vec::new();
-		bytes.push(did.schema.into());
-		bytes.extend_from_slice(&did.key);
+		if is_v2 {
+			bytes.extend_from_slice(MESSAGE_V2_TAG);
+		}
+		bytes.extend_from_slice(&did.to_bytes()?);
 		for arc in &self.credential_subject.trustworthiness {
 			bytes.push(arc.scope.clone().into());
-			// TODO: Uncomment when supported
-			// bytes.extend_from_slice(&arc.level.to_be_bytes());
+			if is_v2 {
+				bytes.extend_from_slice(&arc.level.to_be_bytes());
+				push_len(&mut bytes, arc.reason.len())?;
+				for reason in &arc.reason {
+					push_len(&mut bytes, reason.len())?;
+					bytes.extend_from_slice(reason.as_bytes());
+				}
+			}
 		}
 
 		Ok(bytes)
 	}
+
+	/// Version 2 adds the levels, as fixed-point decimals, and the reasons.
+	fn get_typed_data(&self) -> Result<TypedData, AttTrError> {
+		let arcs = &self.credential_subject.trustworthiness;
+		let scopes = arcs.iter().map(|arc| arc.scope.name().to_string());
+		let typed_data = TypedData::new("TrustCredential")
+			.member("subject", Value::String(self.credential_subject.id.clone()))
+			.member("scopes", Value::strings(scopes));
+		if self.proof.get_message_version() != MESSAGE_VERSION_V2 {
+			return Ok(typed_data);
+		}
+
+		let levels = arcs.iter().map(|arc| arc.level);
+		let reasons = arcs.iter().map(|arc| Value::strings(arc.reason.clone())).collect();
+		Ok(typed_data
+			.member("levels", Value::decimals(levels))
+			.member("reasons", Value::Array("string[]", reasons)))
+	}
 }
 
 impl IntoTerm for TrustSchema {
-	fn into_term(self, timestamp: u64) -> Result<Vec<Term>, AttTrError> {
-		let pk = self.validate()?;
+	fn get_issuer_did(&self) -> &str {
+		&self.issuer
+	}
+
+	fn get_validity_period(&self) -> &ValidityPeriod {
+		&self.validity
+	}
 
-		let from_address = address_from_ecdsa_key(&pk);
-		let from_did: String = Did::new(Schema::PkhEth, from_address).into();
-		if from_did != self.issuer {
-			return Err(AttTrError::VerificationError);
+	fn into_verified_term(
+		self, from_did: String, timestamp: u64, weights: &Weights,
+	) -> Result<Vec<Term>, AttTrError> {
+		// Data Integrity proofs cover the whole credential, levels included.
+		let message_version = match self.proof.get_type() {
+			ProofType::DataIntegrity => MESSAGE_VERSION_V2,
+			_ => self.proof.get_message_version(),
+		};
+		if message_version == DEFAULT_MESSAGE_VERSION && !weights.accepts_legacy_message(timestamp)
+		{
+			return Err(AttTrError::UnsupportedMessageVersion(message_version));
 		}
 
+		let subject: String = self.subject()?.into();
 		let mut terms = Vec::new();
 		for trust_arc in &self.credential_subject.trustworthiness {
 			let form = trust_arc.level >= 0.;
-			let term_group = match trust_arc.scope {
-				Domain::SoftwareDevelopment => vec![Term::new(
+			let (domains, weight) = weights.terms(&trust_arc.scope)?;
+			for domain in domains {
+				let term = Term::new(
 					from_did.clone(),
-					self.credential_subject.id.clone(),
-					trust_arc.level.abs() * 10.,
-					Domain::SoftwareDevelopment.into(),
+					subject.clone(),
+					trust_arc.level.abs() * weight,
+					domain.into(),
 					form,
 					timestamp,
-				)],
-				Domain::SoftwareSecurity => {
-					vec![Term::new(
-						from_did.clone(),
-						self.credential_subject.id.clone(),
-						trust_arc.level.abs() * 10.,
-						Domain::SoftwareSecurity.into(),
-						form,
-						timestamp,
-					)]
-				},
-				Domain::Honesty => {
-					vec![
-						Term::new(
-							from_did.clone(),
-							self.credential_subject.id.clone(),
-							trust_arc.level.abs() * 1.,
-							Domain::SoftwareDevelopment.into(),
-							form,
-							timestamp,
-						),
-						Term::new(
-							from_did.clone(),
-							self.credential_subject.id.clone(),
-							trust_arc.level.abs() * 1.,
-							Domain::SoftwareSecurity.into(),
-							form,
-							timestamp,
-						),
-					]
-				},
-			};
-
-			terms.extend(term_group);
+				)
+				.with_policy_version(weights.version)
+				.with_message_version(message_version);
+				terms.push(term);
+			}
 		}
 
 		Ok(terms)
@@ -138,7 +189,8 @@ mod test {
 	use sha3::{Digest, Keccak256};
 
 	use crate::did::Did;
-	use crate::schemas::{Domain, Proof, Validation};
+	use crate::policy::WeightPolicy;
+	use crate::schemas::{Domain, Proof, ProofType, Validation};
 	use crate::utils::address_from_ecdsa_key;
 
 	use super::*;
@@ -175,12 +227,102 @@ mod test {
 		let addr = address_from_ecdsa_key(&pk);
 		let issuer = format!("did:pkh:eth:0x{}", hex::encode(addr));
 		let cs = CredentialSubject { id: did_string, trustworthiness: vec![trust_arc] };
-		let proof = Proof { signature: sig_string };
+		let proof = Proof::new(sig_string);
 
-		let aa_schema = TrustSchema { kind, issuer, credential_subject: cs, proof };
+		let aa_schema = TrustSchema {
+			kind: OneOrMore::One(kind),
+			issuer,
+			credential_subject: cs,
+			proof,
+			validity: ValidityPeriod::default(),
+		};
 
 		let rec_pk = aa_schema.validate().unwrap();
 
 		assert_eq!(rec_pk, pk);
 	}
+
+	/// Sign the credential's message for its proof type and version, as its issuer.
+	fn signed(mut schema: TrustSchema) -> TrustSchema {
+		let (sk, pk) = generate_keypair(&mut thread_rng());
+		let digest = match schema.proof.get_type() {
+			ProofType::Eip712 => schema.get_typed_data().unwrap().digest().unwrap(),
+			_ => Keccak256::digest(schema.get_message().unwrap()).into(),
+		};
+		let message = Message::from_digest_slice(&digest).unwrap();
+		let (rec_id, sig_bytes) =
+			Secp256k1::new().sign_ecdsa_recoverable(&message, &sk).serialize_compact();
+		let mut bytes = sig_bytes.to_vec();
+		bytes.push(rec_id.to_i32() as u8);
+
+		let proof = Proof::with_type(schema.proof.get_type(), hex::encode(bytes));
+		schema.proof = proof.with_message_version(schema.proof.get_message_version());
+		schema.issuer = format!("did:pkh:eth:0x{}", hex::encode(address_from_ecdsa_key(&pk)));
+		schema
+	}
+
+	fn unsigned(level: f32, proof: Proof) -> TrustSchema {
+		let id = "did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned();
+		let reason = vec!["Audited".to_string()];
+		let arc = DomainTrust::new(Domain::SoftwareSecurity, level, reason);
+		let cs = CredentialSubject::new(id, vec![arc]);
+		TrustSchema::new("TrustCredential".to_string(), String::new(), cs, proof)
+	}
+
+	fn flip_level(mut schema: TrustSchema) -> TrustSchema {
+		let arc = &mut schema.credential_subject.trustworthiness[0];
+		arc.level = -arc.level;
+		schema
+	}
+
+	#[test]
+	fn should_sign_trust_levels_in_v2() {
+		let weights = WeightPolicy::default().weights("TrustCredential").unwrap();
+		for proof_type in [ProofType::Legacy, ProofType::Eip712] {
+			let proof = Proof::with_type(proof_type, String::new());
+			let schema = signed(unsigned(
+				0.5,
+				proof.with_message_version(MESSAGE_VERSION_V2),
+			));
+
+			let terms = schema.clone().into_term(0, &weights).unwrap();
+			let expected = Term::new(
+				schema.issuer.clone(),
+				schema.credential_subject.id.clone(),
+				5.,
+				Domain::SoftwareSecurity.into(),
+				true,
+				0,
+			)
+			.with_message_version(MESSAGE_VERSION_V2);
+			assert_eq!(terms, vec![expected]);
+
+			// Flipping the endorsement into a report invalidates the proof.
+			assert!(matches!(
+				flip_level(schema).into_term(0, &weights),
+				Err(AttTrError::VerificationError)
+			));
+		}
+	}
+
+	#[test]
+	fn should_accept_v1_within_transition_window() {
+		let policy = WeightPolicy { legacy_messages_until: 10, ..Default::default() };
+		let weights = policy.weights("TrustCredential").unwrap();
+
+		// Version 1 doesn't cover the level.
+		let schema = flip_level(signed(unsigned(0.5, Proof::new(String::new()))));
+		let terms = schema.clone().into_term(10, &weights).unwrap();
+		assert_eq!(terms[0].message_version(), DEFAULT_MESSAGE_VERSION);
+		assert!(matches!(
+			schema.into_term(11, &weights),
+			Err(AttTrError::UnsupportedMessageVersion(1))
+		));
+
+		let proof = Proof::new(String::new()).with_message_version(3);
+		assert!(matches!(
+			signed(unsigned(0.5, proof)).into_term(0, &weights),
+			Err(AttTrError::UnsupportedMessageVersion(3))
+		));
+	}
 }
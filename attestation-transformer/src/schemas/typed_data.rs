@@ -0,0 +1,148 @@
+use mm_spd_vc::eip712::{self, Member, Types};
+use mm_spd_vc::error::VcError;
+use serde_json::json;
+
+/// Decimal places of the numbers signed as strings, as with JavaScript's `toFixed(6)`.
+pub const DECIMAL_PLACES: usize = 6;
+
+/// Value of an EIP-712 struct member. Only the types used by the schemas are supported.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	String(String),
+	Uint8(u8),
+	Uint256(u64),
+	Address([u8; 20]),
+	/// Array of values of the given element type, e.g. `string` for `string[]`.
+	Array(&'static str, Vec<Value>),
+}
+
+impl Value {
+	/// Array of strings.
+	pub fn strings(values: impl IntoIterator<Item = String>) -> Self {
+		Self::Array("string", values.into_iter().map(Self::String).collect())
+	}
+
+	/// Array of numbers, as strings of [`DECIMAL_PLACES`] decimal places, e.g. `0.500000`.
+	pub fn decimals(values: impl IntoIterator<Item = f32>) -> Self {
+		Self::strings(values.into_iter().map(|value| format!("{:.*}", DECIMAL_PLACES, value)))
+	}
+
+	fn type_name(&self) -> String {
+		match self {
+			Self::String(_) => "string".to_string(),
+			Self::Uint8(_) => "uint8".to_string(),
+			Self::Uint256(_) => "uint256".to_string(),
+			Self::Address(_) => "address".to_string(),
+			Self::Array(element, _) => format!("{}[]", element),
+		}
+	}
+
+	fn to_json(&self) -> serde_json::Value {
+		match self {
+			Self::String(value) => json!(value),
+			Self::Uint8(value) => json!(value),
+			Self::Uint256(value) => json!(value),
+			Self::Address(value) => json!(format!("0x{}", hex::encode(value))),
+			Self::Array(_, values) => values.iter().map(Self::to_json).collect(),
+		}
+	}
+}
+
+/// EIP-712 struct, i.e. the typed data signed with `eth_signTypedData_v4`,
+/// encoded by [`eip712::TypedData`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypedData {
+	primary_type: &'static str,
+	members: Vec<(&'static str, Value)>,
+}
+
+impl TypedData {
+	pub fn new(primary_type: &'static str) -> Self {
+		Self { primary_type, members: Vec::new() }
+	}
+
+	pub fn member(mut self, name: &'static str, value: Value) -> Self {
+		self.members.push((name, value));
+		self
+	}
+
+	/// The struct as typed data in the [`eip712::attestation_domain`].
+	pub fn to_eip712(&self) -> eip712::TypedData {
+		let members = self
+			.members
+			.iter()
+			.map(|(name, value)| Member { name: name.to_string(), type_: value.type_name() })
+			.collect();
+		let message =
+			self.members.iter().map(|(name, value)| (name.to_string(), value.to_json())).collect();
+		eip712::TypedData {
+			types: Types::from([(self.primary_type.to_string(), members)]),
+			primary_type: self.primary_type.to_string(),
+			domain: eip712::attestation_domain(),
+			message: serde_json::Value::Object(message),
+		}
+	}
+
+	/// Digest signed for the struct in the attestation domain.
+	pub fn digest(&self) -> Result<[u8; 32], VcError> {
+		self.to_eip712().digest()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use sha3::{Digest, Keccak256};
+
+	use super::*;
+
+	fn keccak(bytes: &[u8]) -> [u8; 32] {
+		Keccak256::digest(bytes).into()
+	}
+
+	#[test]
+	fn should_hash_string_arrays() {
+		let data = TypedData::new("Scopes").member(
+			"scopes",
+			Value::Array(
+				"string[]",
+				vec![Value::strings(["a".to_string(), "b".to_string()]), Value::strings([])],
+			),
+		);
+		let mut hashes = keccak(b"a").to_vec();
+		hashes.extend_from_slice(&keccak(b"b"));
+		let mut words = keccak(&hashes).to_vec();
+		words.extend_from_slice(&keccak(&[]));
+		let mut bytes = keccak(b"Scopes(string[][] scopes)").to_vec();
+		bytes.extend_from_slice(&keccak(&words));
+
+		let typed_data = data.to_eip712();
+		let hash = eip712::hash_struct(&typed_data.types, "Scopes", &typed_data.message);
+		assert_eq!(hash.unwrap(), keccak(&bytes));
+	}
+
+	#[test]
+	fn should_sign_in_attestation_domain() {
+		let data = TypedData::new("StatusCredential")
+			.member("subject", Value::String("snap://0x90f8".to_string()))
+			.member("currentStatus", Value::Uint8(1));
+		let domain = TypedData::new("EIP712Domain")
+			.member("name", Value::String("EigenTrust Attestation".to_string()))
+			.member("version", Value::String("1".to_string()))
+			.to_eip712();
+		let domain_hash = eip712::hash_struct(&domain.types, "EIP712Domain", &domain.message);
+
+		let mut bytes = vec![0x19, 0x01];
+		bytes.extend_from_slice(&domain_hash.unwrap());
+		let typed_data = data.to_eip712();
+		let hash = eip712::hash_struct(&typed_data.types, "StatusCredential", &typed_data.message);
+		bytes.extend_from_slice(&hash.unwrap());
+		assert_eq!(data.digest().unwrap(), keccak(&bytes));
+	}
+
+	#[test]
+	fn should_sign_decimals_in_fixed_format() {
+		let decimals = Value::decimals([0.5, 0.3, 1., 0.]);
+		let strings = ["0.500000", "0.300000", "1.000000", "0.000000"];
+		assert_eq!(decimals, Value::strings(strings.map(str::to_string)));
+	}
+}
@@ -0,0 +1,137 @@
+use serde_derive::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::error::AttTrError;
+use crate::policy::ValidityPolicy;
+
+/// Validity period of a credential, as either VC Data Model 1.1 (`issuanceDate`, `expirationDate`)
+/// or 2.0 (`validFrom`, `validUntil`) RFC 3339 dates. The 2.0 ones take precedence.
+///
+/// Checked for every credential, but only dates and expires the terms of Data Integrity proofs,
+/// the only ones whose signature covers the dates.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidityPeriod {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub issuance_date: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub expiration_date: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub valid_from: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub valid_until: Option<String>,
+}
+
+impl ValidityPeriod {
+	/// Start of the period, in milliseconds since the epoch.
+	pub fn from(&self) -> Result<Option<u64>, AttTrError> {
+		self.valid_from
+			.as_ref()
+			.or(self.issuance_date.as_ref())
+			.map(|d| parse_date(d))
+			.transpose()
+	}
+
+	/// End of the period, in milliseconds since the epoch.
+	pub fn until(&self) -> Result<Option<u64>, AttTrError> {
+		self.valid_until
+			.as_ref()
+			.or(self.expiration_date.as_ref())
+			.map(|d| parse_date(d))
+			.transpose()
+	}
+
+	/// Check that the credential is valid at the event timestamp (in milliseconds),
+	/// give or take the clock skew, and return the timestamp of its terms.
+	pub fn term_timestamp(
+		&self, timestamp: u64, policy: &ValidityPolicy,
+	) -> Result<u64, AttTrError> {
+		let from = self.from()?;
+		if let Some(from) = from.filter(|&from| from > timestamp.saturating_add(policy.clock_skew))
+		{
+			return Err(AttTrError::NotYetValid(from));
+		}
+		if let Some(until) = self.until()?.filter(|&until| until <= timestamp) {
+			return Err(AttTrError::Expired(until));
+		}
+
+		match from {
+			Some(from) if policy.use_issuance_time => Ok(from),
+			_ => Ok(timestamp),
+		}
+	}
+}
+
+fn parse_date(date: &str) -> Result<u64, AttTrError> {
+	let date_time = OffsetDateTime::parse(date, &Rfc3339)
+		.map_err(|e| AttTrError::DateError(format!("{}: {}", date, e)))?;
+	let millis = date_time.unix_timestamp_nanos() / 1_000_000;
+	u64::try_from(millis).map_err(|_| AttTrError::DateError(format!("{}: before 1970", date)))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const JAN_1: u64 = 1704067200000;
+	const FEB_1: u64 = 1706745600000;
+
+	fn period(from: &str, until: &str) -> ValidityPeriod {
+		ValidityPeriod {
+			issuance_date: Some(from.to_string()),
+			expiration_date: Some(until.to_string()),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn should_check_validity_period() {
+		let period = period("2024-01-01T00:00:00Z", "2024-02-01T00:00:00Z");
+		let policy = ValidityPolicy { use_issuance_time: false, clock_skew: 1000 };
+		assert_eq!(
+			(period.from().unwrap(), period.until().unwrap()),
+			(Some(JAN_1), Some(FEB_1))
+		);
+
+		assert_eq!(
+			period.term_timestamp(JAN_1 + 5, &policy).unwrap(),
+			JAN_1 + 5
+		);
+		assert_eq!(
+			period.term_timestamp(JAN_1 - 1000, &policy).unwrap(),
+			JAN_1 - 1000
+		);
+		assert!(matches!(
+			period.term_timestamp(JAN_1 - 1001, &policy),
+			Err(AttTrError::NotYetValid(JAN_1))
+		));
+		assert!(matches!(
+			period.term_timestamp(FEB_1, &policy),
+			Err(AttTrError::Expired(FEB_1))
+		));
+
+		let policy = ValidityPolicy { use_issuance_time: true, ..policy };
+		assert_eq!(period.term_timestamp(JAN_1 + 5, &policy).unwrap(), JAN_1);
+		assert_eq!(
+			ValidityPeriod::default().term_timestamp(7, &policy).unwrap(),
+			7
+		);
+	}
+
+	#[test]
+	fn should_prefer_valid_from_and_until() {
+		let period = ValidityPeriod {
+			valid_from: Some("2024-02-01T00:00:00Z".to_string()),
+			valid_until: Some("2024-02-01T01:00:00+01:00".to_string()),
+			..period("2024-01-01T00:00:00Z", "2024-01-02T00:00:00Z")
+		};
+		assert_eq!(
+			(period.from().unwrap(), period.until().unwrap()),
+			(Some(FEB_1), Some(FEB_1))
+		);
+
+		let period = ValidityPeriod { valid_from: Some("2024-02-01".to_string()), ..period };
+		assert!(matches!(period.from(), Err(AttTrError::DateError(_))));
+	}
+}
@@ -1,6 +1,11 @@
-use proto_buf::transformer::{Form, TermObject};
+use mm_spd_did::{canonicalize_did_with, CanonicalizeOptions};
+use proto_buf::transformer::{
+	Finding, Form, Severity as SeverityObject, TermKind as TermKindObject, TermObject,
+};
 
 use crate::error::AttTrError;
+use crate::schemas::security::{SecurityFinding, Severity};
+use crate::schemas::DEFAULT_MESSAGE_VERSION;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum TermForm {
@@ -8,12 +13,14 @@ enum TermForm {
 	Distrust,
 }
 
-impl From<u8> for TermForm {
-	fn from(value: u8) -> Self {
+impl TryFrom<u8> for TermForm {
+	type Error = AttTrError;
+
+	fn try_from(value: u8) -> Result<Self, Self::Error> {
 		match value {
-			0 => Self::Trust,
-			1 => Self::Distrust,
-			_ => panic!("Invalid Term form"),
+			0 => Ok(Self::Trust),
+			1 => Ok(Self::Distrust),
+			_ => Err(AttTrError::SerialisationError),
 		}
 	}
 }
@@ -36,6 +43,44 @@ impl From<TermForm> for Form {
 	}
 }
 
+/// Whether a term asserts an opinion or revokes an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TermKind {
+	#[default]
+	Assertion,
+	Revocation,
+}
+
+impl TryFrom<u8> for TermKind {
+	type Error = AttTrError;
+
+	fn try_from(value: u8) -> Result<Self, Self::Error> {
+		match value {
+			0 => Ok(Self::Assertion),
+			1 => Ok(Self::Revocation),
+			_ => Err(AttTrError::SerialisationError),
+		}
+	}
+}
+
+impl From<TermKind> for u8 {
+	fn from(value: TermKind) -> Self {
+		match value {
+			TermKind::Assertion => 0,
+			TermKind::Revocation => 1,
+		}
+	}
+}
+
+impl From<TermKind> for TermKindObject {
+	fn from(value: TermKind) -> Self {
+		match value {
+			TermKind::Assertion => Self::Assertion,
+			TermKind::Revocation => Self::Revocation,
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Term {
 	from: String,
@@ -44,6 +89,11 @@ pub struct Term {
 	domain: u32,
 	form: TermForm,
 	timestamp: u64,
+	policy_version: u32,
+	kind: TermKind,
+	message_version: u32,
+	expires_at: Option<u64>,
+	findings: Vec<SecurityFinding>,
 }
 
 impl Term {
@@ -57,112 +107,275 @@ impl Term {
 			domain,
 			form: if is_trust { TermForm::Trust } else { TermForm::Distrust },
 			timestamp,
+			policy_version: 0,
+			kind: TermKind::Assertion,
+			message_version: DEFAULT_MESSAGE_VERSION,
+			expires_at: None,
+			findings: Vec::new(),
 		}
 	}
 
-	pub fn into_bytes(self) -> Result<Vec<u8>, AttTrError> {
-		let mut bytes = Vec::new();
+	/// Revocation of all the earlier opinions of `from` about `to`,
+	/// resolved into revocations of the individual terms before being stored.
+	pub fn revoke_all(from: String, to: String, timestamp: u64) -> Term {
+		Term { kind: TermKind::Revocation, ..Term::new(from, to, 0., 0, true, timestamp) }
+	}
 
-		let from_bytes = self.from.as_bytes();
-		let to_bytes = self.to.as_bytes();
-		let weight_bytes = self.weight.to_be_bytes();
-		let domain_bytes = self.domain.to_be_bytes();
-		let form_byte: u8 = self.form.into();
-		let timestamp_bytes = self.timestamp.to_be_bytes();
+	/// Revocation of this (asserted) term, e.g. when a newer credential supersedes it.
+	/// The findings stay with the assertion.
+	pub fn revocation(&self, timestamp: u64) -> Term {
+		Term { kind: TermKind::Revocation, timestamp, findings: Vec::new(), ..self.clone() }
+	}
+
+	pub fn from(&self) -> &str {
+		&self.from
+	}
 
-		bytes.extend_from_slice(from_bytes);
-		bytes.extend_from_slice(to_bytes);
-		bytes.extend_from_slice(&weight_bytes);
-		bytes.extend_from_slice(&domain_bytes);
-		bytes.push(form_byte);
-		bytes.extend_from_slice(&timestamp_bytes);
+	pub fn to(&self) -> &str {
+		&self.to
+	}
+
+	pub fn domain(&self) -> u32 {
+		self.domain
+	}
+
+	pub fn timestamp(&self) -> u64 {
+		self.timestamp
+	}
+
+	pub fn kind(&self) -> TermKind {
+		self.kind
+	}
+
+	pub fn message_version(&self) -> u32 {
+		self.message_version
+	}
+
+	/// When the credential the term came from expires, in milliseconds since the epoch.
+	pub fn expires_at(&self) -> Option<u64> {
+		self.expires_at
+	}
+
+	pub fn findings(&self) -> &[SecurityFinding] {
+		&self.findings
+	}
+
+	/// Record the version of the weighting policy the term was emitted under.
+	pub fn with_policy_version(mut self, policy_version: u32) -> Self {
+		self.policy_version = policy_version;
+		self
+	}
+
+	/// Record the version of the signed message of the credential the term came from.
+	pub fn with_message_version(mut self, message_version: u32) -> Self {
+		self.message_version = message_version;
+		self
+	}
+
+	/// Record when the credential the term came from expires.
+	pub fn with_expiry(mut self, expires_at: Option<u64>) -> Self {
+		self.expires_at = expires_at;
+		self
+	}
+
+	/// The term with its issuer and subject in their canonical forms,
+	/// which the combiner indexes them by.
+	pub fn into_canonical(mut self, options: &CanonicalizeOptions) -> Result<Self, AttTrError> {
+		self.from = canonicalize_did_with(&self.from, options).map_err(AttTrError::DidError)?;
+		self.to = canonicalize_did_with(&self.to, options).map_err(AttTrError::DidError)?;
+		Ok(self)
+	}
+
+	/// Keep the findings the term's weight was aggregated from, to explain it.
+	pub fn with_findings(mut self, findings: Vec<SecurityFinding>) -> Self {
+		self.findings = findings;
+		self
+	}
+
+	/// Encode the term in the current storage format:
+	///
+	/// ```text
+	/// 1: u8 - encoding version (TERM_ENCODING_VERSION)
+	/// 4 + n: u32 length, UTF-8 - from
+	/// 4 + n: u32 length, UTF-8 - to
+	/// 4: f32 - weight
+	/// 4: u32 - domain
+	/// 1: u8 - form
+	/// 8: u64 - timestamp
+	/// 4: u32 - policy version
+	/// 1: u8 - kind
+	/// 4: u32 - message version
+	/// 8: u64 - expiry in milliseconds, 0 if none
+	/// 4: u32 - number of findings, each:
+	///   4: f32 - criticality
+	///   3 * (4 + n): u32 length, UTF-8 - unsigned kind, description and lang, empty if none
+	/// ```
+	///
+	/// All integers are big-endian.
+	pub fn into_bytes(self) -> Result<Vec<u8>, AttTrError> {
+		let mut bytes = vec![TERM_ENCODING_VERSION];
+		write_string(&mut bytes, &self.from)?;
+		write_string(&mut bytes, &self.to)?;
+		bytes.extend_from_slice(&self.weight.to_be_bytes());
+		bytes.extend_from_slice(&self.domain.to_be_bytes());
+		bytes.push(self.form.into());
+		bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+		bytes.extend_from_slice(&self.policy_version.to_be_bytes());
+		bytes.push(self.kind.into());
+		bytes.extend_from_slice(&self.message_version.to_be_bytes());
+		bytes.extend_from_slice(&self.expires_at.unwrap_or(0).to_be_bytes());
+		let num_findings =
+			u32::try_from(self.findings.len()).map_err(|_| AttTrError::SerialisationError)?;
+		bytes.extend_from_slice(&num_findings.to_be_bytes());
+		for finding in &self.findings {
+			bytes.extend_from_slice(&finding.criticality().to_be_bytes());
+			for detail in [finding.kind(), finding.description(), finding.lang()] {
+				write_string(&mut bytes, detail.unwrap_or_default())?;
+			}
+		}
 
 		Ok(bytes)
 	}
 
-	pub fn from_bytes(mut bytes: Vec<u8>) -> Result<Self, AttTrError> {
-		let term: Term = match bytes.len() {
-			// 54 + 49 + 4 + 4 + 1 + 8 = 120
-			// 54: did:pkh:eth:0x152d4dd8afe95f7c38103d7460befbed07dedd8f - from
-			// 49: snap://0x9dc6c239a0f3abad2094cd6891cdc56cdf8994f8 - to
-			// 4: f32 - weight
-			// 4: u32 - domain
-			// 1: u8 - form
-			// 8: u63 - timestamp
-			120 => {
-				let from_bytes: Vec<u8> = bytes.drain(..54).collect();
-				let to_bytes: Vec<u8> = bytes.drain(..49).collect();
-				let weight_bytes: [u8; 4] = bytes
-					.drain(..4)
-					.collect::<Vec<u8>>()
-					.try_into()
-					.map_err(|_| AttTrError::SerialisationError)?;
-				let domain_bytes: [u8; 4] = bytes
-					.drain(..4)
-					.collect::<Vec<u8>>()
-					.try_into()
-					.map_err(|_| AttTrError::SerialisationError)?;
-				let form_byte: u8 = bytes.remove(0);
-				let timestamp_bytes = bytes
-					.drain(..8)
-					.collect::<Vec<u8>>()
-					.try_into()
-					.map_err(|_| AttTrError::SerialisationError)?;
-
-				let from =
-					String::from_utf8(from_bytes).map_err(|_| AttTrError::SerialisationError)?;
-				let to = String::from_utf8(to_bytes).map_err(|_| AttTrError::SerialisationError)?;
-				let weight = f32::from_be_bytes(weight_bytes);
-				let domain = u32::from_be_bytes(domain_bytes);
-				let form = TermForm::from(form_byte);
-				let timestamp = u64::from_be_bytes(timestamp_bytes);
-
-				Term { from, to, weight, domain, form, timestamp }
-			},
-			// 54 + 54 + 4 + 4 + 1 + 8 = 125
-			// 54: did:pkh:eth:0x152d4dd8afe95f7c38103d7460befbed07dedd8f - from
-			// 54: did:pkh:eth:0x152d4dd8afe95f7c38103d7460befbed07dedd8f - to
-			// 4: f32 - weight
-			// 4: u32 - domain
-			// 1: u8 - form
-			// 8: u63 - timestamp
-			125 => {
-				let from_bytes: Vec<u8> = bytes.drain(..54).collect();
-				let to_bytes: Vec<u8> = bytes.drain(..54).collect();
-				let weight_bytes: [u8; 4] = bytes
-					.drain(..4)
-					.collect::<Vec<u8>>()
-					.try_into()
-					.map_err(|_| AttTrError::SerialisationError)?;
-				let domain_bytes: [u8; 4] = bytes
-					.drain(..4)
-					.collect::<Vec<u8>>()
-					.try_into()
-					.map_err(|_| AttTrError::SerialisationError)?;
-				let form_byte: u8 = bytes.remove(0);
-				let timestamp_bytes = bytes
-					.drain(..8)
-					.collect::<Vec<u8>>()
-					.try_into()
-					.map_err(|_| AttTrError::SerialisationError)?;
-
-				let from =
-					String::from_utf8(from_bytes).map_err(|_| AttTrError::SerialisationError)?;
-				let to = String::from_utf8(to_bytes).map_err(|_| AttTrError::SerialisationError)?;
-				let weight = f32::from_be_bytes(weight_bytes);
-				let domain = u32::from_be_bytes(domain_bytes);
-				let form = TermForm::from(form_byte);
-				let timestamp = u64::from_be_bytes(timestamp_bytes);
-
-				Term { from, to, weight, domain, form, timestamp }
-			},
+	/// Decode a term in the current or the legacy storage format.
+	pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, AttTrError> {
+		match bytes.first() {
+			Some(&TERM_ENCODING_VERSION) => Self::from_versioned_bytes(&bytes[1..]),
+			_ if is_legacy(&bytes) => Self::from_legacy_bytes(&bytes),
+			_ => Err(AttTrError::SerialisationError),
+		}
+	}
+
+	fn from_versioned_bytes(mut bytes: &[u8]) -> Result<Self, AttTrError> {
+		let bytes = &mut bytes;
+		let from = read_string(bytes)?;
+		let to = read_string(bytes)?;
+		let weight = f32::from_be_bytes(read_array(bytes)?);
+		let domain = u32::from_be_bytes(read_array(bytes)?);
+		let [form_byte] = read_array(bytes)?;
+		let form = TermForm::try_from(form_byte)?;
+		let timestamp = u64::from_be_bytes(read_array(bytes)?);
+		let policy_version = u32::from_be_bytes(read_array(bytes)?);
+		let [kind_byte] = read_array(bytes)?;
+		let kind = TermKind::try_from(kind_byte)?;
+		let message_version = u32::from_be_bytes(read_array(bytes)?);
+		let expires_at =
+			Some(u64::from_be_bytes(read_array(bytes)?)).filter(|&expires_at| expires_at != 0);
+		let num_findings = u32::from_be_bytes(read_array(bytes)?);
+		let mut findings = Vec::new();
+		for _ in 0..num_findings {
+			let criticality = f32::from_be_bytes(read_array(bytes)?);
+			let mut detail = || -> Result<_, AttTrError> {
+				Ok(Some(read_string(bytes)?).filter(|detail| !detail.is_empty()))
+			};
+			let (kind, description, lang) = (detail()?, detail()?, detail()?);
+			findings.push(SecurityFinding::new(criticality, kind, description, lang));
+		}
+		if !bytes.is_empty() {
+			return Err(AttTrError::SerialisationError);
+		}
+
+		Ok(Term {
+			from,
+			to,
+			weight,
+			domain,
+			form,
+			timestamp,
+			policy_version,
+			kind,
+			message_version,
+			expires_at,
+			findings,
+		})
+	}
+
+	/// Decode a term in the legacy, fixed-length format:
+	///
+	/// ```text
+	/// 54: did:pkh:eth:0x152d4dd8afe95f7c38103d7460befbed07dedd8f - from
+	/// 49: snap://0x9dc6c239a0f3abad2094cd6891cdc56cdf8994f8 - to
+	///  or 54: did:pkh:eth:0x152d4dd8afe95f7c38103d7460befbed07dedd8f - to
+	/// 4: f32 - weight
+	/// 4: u32 - domain
+	/// 1: u8 - form
+	/// 8: u64 - timestamp
+	/// ```
+	///
+	/// Legacy terms predate weighting policies, so they are of policy version 0.
+	fn from_legacy_bytes(bytes: &[u8]) -> Result<Self, AttTrError> {
+		let to_len = match bytes.len() {
+			120 => 49,
+			125 => 54,
 			_ => return Err(AttTrError::SerialisationError),
 		};
 
-		Ok(term)
+		let mut bytes = bytes;
+		let bytes = &mut bytes;
+		let from = String::from_utf8(take(bytes, 54)?.to_vec())
+			.map_err(|_| AttTrError::SerialisationError)?;
+		let to = String::from_utf8(take(bytes, to_len)?.to_vec())
+			.map_err(|_| AttTrError::SerialisationError)?;
+		let weight = f32::from_be_bytes(read_array(bytes)?);
+		let domain = u32::from_be_bytes(read_array(bytes)?);
+		let [form_byte] = read_array(bytes)?;
+		let form = TermForm::try_from(form_byte)?;
+		let timestamp = u64::from_be_bytes(read_array(bytes)?);
+		let policy_version = 0;
+		let kind = TermKind::Assertion;
+		let message_version = DEFAULT_MESSAGE_VERSION;
+
+		Ok(Term {
+			from,
+			to,
+			weight,
+			domain,
+			form,
+			timestamp,
+			policy_version,
+			kind,
+			message_version,
+			expires_at: None,
+			findings: Vec::new(),
+		})
 	}
 }
 
+/// Version of the term storage format, i.e. the first byte of an encoded term.
+/// Legacy terms start with their issuer DID instead, i.e. with `d`.
+pub const TERM_ENCODING_VERSION: u8 = 1;
+
+/// Whether the encoded term is in the legacy, fixed-length format.
+pub fn is_legacy(bytes: &[u8]) -> bool {
+	bytes.first() == Some(&b'd')
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], AttTrError> {
+	if bytes.len() < n {
+		return Err(AttTrError::SerialisationError);
+	}
+	let (head, tail) = bytes.split_at(n);
+	*bytes = tail;
+	Ok(head)
+}
+
+fn read_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], AttTrError> {
+	take(bytes, N)?.try_into().map_err(|_| AttTrError::SerialisationError)
+}
+
+fn write_string(bytes: &mut Vec<u8>, s: &str) -> Result<(), AttTrError> {
+	let len = u32::try_from(s.len()).map_err(|_| AttTrError::SerialisationError)?;
+	bytes.extend_from_slice(&len.to_be_bytes());
+	bytes.extend_from_slice(s.as_bytes());
+	Ok(())
+}
+
+fn read_string(bytes: &mut &[u8]) -> Result<String, AttTrError> {
+	let len = u32::from_be_bytes(read_array(bytes)?);
+	let len = usize::try_from(len).map_err(|_| AttTrError::SerialisationError)?;
+	String::from_utf8(take(bytes, len)?.to_vec()).map_err(|_| AttTrError::SerialisationError)
+}
+
 impl From<Term> for TermObject {
 	fn from(value: Term) -> Self {
 		let form: Form = value.form.into();
@@ -173,12 +386,43 @@ impl From<Term> for TermObject {
 			domain: value.domain,
 			form: form.into(),
 			timestamp: value.timestamp,
+			policy_version: value.policy_version,
+			kind: TermKindObject::from(value.kind).into(),
+			message_version: value.message_version,
+			expires_at: value.expires_at.unwrap_or(0),
+			findings: value.findings.into_iter().map(Finding::from).collect(),
+		}
+	}
+}
+
+impl From<Severity> for SeverityObject {
+	fn from(value: Severity) -> Self {
+		match value {
+			Severity::None => Self::None,
+			Severity::Low => Self::Low,
+			Severity::Medium => Self::Medium,
+			Severity::High => Self::High,
+			Severity::Critical => Self::Critical,
+		}
+	}
+}
+
+impl From<SecurityFinding> for Finding {
+	fn from(value: SecurityFinding) -> Self {
+		Self {
+			criticality: value.criticality(),
+			severity: SeverityObject::from(value.severity()).into(),
+			kind: value.kind().unwrap_or_default().to_string(),
+			description: value.description().unwrap_or_default().to_string(),
+			lang: value.lang().unwrap_or_default().to_string(),
 		}
 	}
 }
 
 #[cfg(test)]
 mod test {
+	use proptest::prelude::*;
+
 	use super::*;
 
 	#[test]
@@ -190,6 +434,14 @@ mod test {
 			domain: 67834578,
 			form: TermForm::Trust,
 			timestamp: 0,
+			policy_version: 0,
+			kind: TermKind::Assertion,
+			message_version: 2,
+			expires_at: Some(1704067200000),
+			findings: vec![
+				SecurityFinding::new(0.95, Some("XSS".to_owned()), None, Some("en".to_owned())),
+				SecurityFinding::new(0.1, None, None, None),
+			],
 		};
 
 		let bytes = term.clone().into_bytes().unwrap();
@@ -197,4 +449,114 @@ mod test {
 
 		assert_eq!(term, rec_term);
 	}
+
+	/// Encode the term in the legacy, fixed-length format.
+	fn legacy_bytes(term: &Term) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		bytes.extend_from_slice(term.from.as_bytes());
+		bytes.extend_from_slice(term.to.as_bytes());
+		bytes.extend_from_slice(&term.weight.to_be_bytes());
+		bytes.extend_from_slice(&term.domain.to_be_bytes());
+		bytes.push(term.form.clone().into());
+		bytes.extend_from_slice(&term.timestamp.to_be_bytes());
+		bytes
+	}
+
+	#[test]
+	fn should_read_legacy_terms() {
+		let term = Term::new(
+			"did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1".to_owned(),
+			"snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_owned(),
+			50.,
+			2,
+			false,
+			1234,
+		);
+
+		let bytes = legacy_bytes(&term);
+		assert!(is_legacy(&bytes));
+		assert_eq!(Term::from_bytes(bytes.clone()).unwrap(), term);
+
+		// Only the fixed lengths of the legacy format are read.
+		let mut bytes = bytes;
+		bytes.extend_from_slice(&7u32.to_be_bytes());
+		assert!(Term::from_bytes(bytes).is_err());
+	}
+
+	#[test]
+	fn should_reject_unknown_versions() {
+		let term = Term::new(
+			"did:key:z6Mk".to_owned(),
+			"npm:@scope/name".to_owned(),
+			1.,
+			0,
+			true,
+			0,
+		);
+		let mut bytes = term.into_bytes().unwrap();
+		for version in [0, TERM_ENCODING_VERSION + 1] {
+			bytes[0] = version;
+			assert!(
+				Term::from_bytes(bytes.clone()).is_err(),
+				"version {}",
+				version
+			);
+		}
+	}
+
+	#[test]
+	fn should_reject_truncated_terms() {
+		let term = Term::new(
+			"did:key:z6Mk".to_owned(),
+			"npm:@scope/name".to_owned(),
+			1.,
+			0,
+			true,
+			0,
+		);
+		let bytes = term.into_bytes().unwrap();
+		assert!(!is_legacy(&bytes));
+		for len in 0..bytes.len() {
+			assert!(Term::from_bytes(bytes[..len].to_vec()).is_err(), "{}", len);
+		}
+		let mut bytes = bytes;
+		bytes.push(0);
+		assert!(Term::from_bytes(bytes).is_err());
+	}
+
+	proptest! {
+		#[test]
+		fn should_round_trip_arbitrary_terms(
+			from in any::<String>(),
+			to in any::<String>(),
+			weight in -1e9f32..1e9,
+			domain in any::<u32>(),
+			is_trust in any::<bool>(),
+			timestamp in any::<u64>(),
+			policy_version in any::<u32>(),
+			is_revocation in any::<bool>(),
+			message_version in any::<u32>(),
+			expires_at in proptest::option::of(1..u64::MAX),
+		) {
+			let mut term = Term::new(from, to, weight, domain, is_trust, timestamp)
+				.with_policy_version(policy_version)
+				.with_message_version(message_version)
+				.with_expiry(expires_at);
+			if is_revocation {
+				term = term.revocation(timestamp);
+			}
+			let bytes = term.clone().into_bytes().unwrap();
+			prop_assert_eq!(Term::from_bytes(bytes).unwrap(), term);
+		}
+
+		#[test]
+		fn should_round_trip_did_strings(
+			from in "did:[a-z0-9]{1,10}:[a-zA-Z0-9:._%-]{1,100}",
+			to in "(did:[a-z0-9]{1,10}:|snap://|npm:@?)[a-zA-Z0-9:._/@%-]{1,100}",
+		) {
+			let term = Term::new(from, to, 50., 2, true, 0);
+			let bytes = term.clone().into_bytes().unwrap();
+			prop_assert_eq!(Term::from_bytes(bytes).unwrap(), term);
+		}
+	}
 }
@@ -41,10 +41,9 @@ where
 
 	pub async fn basic_compute(
 		&mut self, params: Params,
-	) -> Result<(), Box<dyn std::error::Error>> {
+	) -> Result<BasicComputeResponse, Box<dyn std::error::Error>> {
 		let params = Some(params);
-		self.raw.basic_compute(BasicComputeRequest { params }).await?.into_inner();
-		Ok(())
+		Ok(self.raw.basic_compute(BasicComputeRequest { params }).await?.into_inner())
 	}
 
 	pub async fn create_job(
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use compute::Params;
+use num::BigUint;
+use tokio::task;
+use tracing::{error, info};
+
+use crate::distrust;
+use crate::eigentrust::{self, compute_with_convergence, Convergence};
+use crate::error::ComputeError;
+use crate::matrix::SparseMatrix;
+use crate::store::{PeerIndex, SharedStore, Store, TrustMatrix, TrustVector};
+
+/// Check the request parameters against the store contents.
+pub fn validate_params(store: &Store, params: &Params) -> Result<(), ComputeError> {
+	store.matrix(&params.local_trust_id)?;
+	store.vector(&params.pre_trust_id)?;
+	store.vector(&params.global_trust_id)?;
+	if !params.positive_global_trust_id.is_empty() {
+		store.vector(&params.positive_global_trust_id)?;
+	}
+	Ok(())
+}
+
+/// Inputs of a compute, copied out of the store,
+/// so that the compute can run without holding the store lock.
+#[derive(Debug, Clone)]
+pub struct ComputeInputs {
+	local_trust: TrustMatrix,
+	pre_trust: TrustVector,
+	global_trust: TrustVector,
+}
+
+/// Results of a compute, to be saved into the store.
+#[derive(Debug, Clone)]
+pub struct ComputeOutput {
+	positive: TrustVector,
+	adjusted: TrustVector,
+	convergence: Convergence,
+}
+
+impl ComputeInputs {
+	/// Copy the inputs specified by `params` out of the store.
+	pub fn snapshot(store: &Store, params: &Params) -> Result<Self, ComputeError> {
+		validate_params(store, params)?;
+		Ok(Self {
+			local_trust: store.matrix(&params.local_trust_id)?.clone(),
+			pre_trust: store.vector(&params.pre_trust_id)?.clone(),
+			global_trust: store.vector(&params.global_trust_id)?.clone(),
+		})
+	}
+
+	/// The later of the local trust and pre-trust timestamps.
+	pub fn timestamp(&self) -> BigUint {
+		self.local_trust.timestamp.clone().max(self.pre_trust.timestamp.clone())
+	}
+
+	/// Run the compute, and stamp the result with the given timestamp.
+	///
+	/// Negative local trust entries are rejected, unless `params.distrust` is set;
+	/// they are then treated as distrust:
+	/// EigenTrust runs on the positive entries only,
+	/// then the distrust adjustment is applied to its result.
+	pub fn compute(
+		&self, params: &Params, timestamp: BigUint,
+	) -> Result<ComputeOutput, ComputeError> {
+		let mut peers = PeerIndex::new();
+		let lt_matrix = peers.matrix(&self.local_trust);
+		self.pre_trust.entries.keys().chain(self.global_trust.entries.keys()).for_each(|id| {
+			peers.insert(id);
+		});
+		let pt_vector = peers.vector(&self.pre_trust);
+		// The previous result may have been distrust-adjusted below zero.
+		let gt_vector: Vec<_> =
+			peers.vector(&self.global_trust).into_iter().map(|v| v.max(0.)).collect();
+
+		let mut trust = SparseMatrix::new(lt_matrix.dim());
+		let mut distrust = SparseMatrix::new(lt_matrix.dim());
+		for (truster, trustee, value) in lt_matrix.entries() {
+			if value < 0. {
+				if !params.distrust {
+					return Err(ComputeError::NegativeLocalTrust { truster, trustee, value });
+				}
+				distrust.set(truster, trustee, -value);
+			} else {
+				trust.set(truster, trustee, value);
+			}
+		}
+
+		let engine_params =
+			eigentrust::Params::new(params.alpha, params.epsilon, params.max_iterations)
+				.with_flat_tail(params.flat_tail, params.num_leaders);
+		let (positive, convergence) =
+			compute_with_convergence(&trust, &pt_vector, Some(&gt_vector), &engine_params)?;
+		let adjusted = distrust::adjust(&positive, &distrust)?;
+
+		Ok(ComputeOutput {
+			positive: TrustVector {
+				timestamp: timestamp.clone(),
+				entries: peers.entries(&positive),
+			},
+			adjusted: TrustVector { timestamp, entries: peers.entries(&adjusted) },
+			convergence,
+		})
+	}
+}
+
+impl ComputeOutput {
+	/// Save the results as specified by `params`;
+	/// the positive-only result only if `positive_global_trust_id` is given.
+	pub fn save(self, store: &mut Store, params: &Params) -> Result<Convergence, ComputeError> {
+		if !params.positive_global_trust_id.is_empty() {
+			store.set_vector(&params.positive_global_trust_id, self.positive)?;
+		}
+		store.set_vector(&params.global_trust_id, self.adjusted)?;
+		Ok(self.convergence)
+	}
+}
+
+/// Run a basic compute on the store contents, as specified by `params`.
+///
+/// The result bears the later of the local trust and pre-trust timestamps.
+pub fn basic_compute(store: &mut Store, params: &Params) -> Result<Convergence, ComputeError> {
+	let inputs = ComputeInputs::snapshot(store, params)?;
+	let timestamp = inputs.timestamp();
+	inputs.compute(params, timestamp)?.save(store, params)
+}
+
+/// A re-compute of a job, triggered by an input update,
+/// on the inputs as they were before the update.
+#[derive(Debug, Clone)]
+pub struct TriggeredCompute {
+	pub job_id: String,
+	pub params: Params,
+	/// Start of the window that the update opened, which the result is stamped with.
+	pub timestamp: BigUint,
+	inputs: ComputeInputs,
+}
+
+impl TriggeredCompute {
+	pub fn new(
+		store: &Store, job_id: String, params: Params, timestamp: BigUint,
+	) -> Result<Self, ComputeError> {
+		let inputs = ComputeInputs::snapshot(store, &params)?;
+		Ok(Self { job_id, params, timestamp, inputs })
+	}
+
+	pub fn compute(&self) -> Result<ComputeOutput, ComputeError> {
+		self.inputs.compute(&self.params, self.timestamp.clone())
+	}
+}
+
+/// Run the triggered re-computes off the async runtime and without holding the store lock,
+/// then save each result under the lock. Failures are logged, as the update went through.
+pub async fn run_triggered(store: &SharedStore, triggered: Vec<TriggeredCompute>) {
+	for job in triggered {
+		info!(job = job.job_id, timestamp = %job.timestamp, "re-computing");
+		let job = Arc::new(job);
+		let compute_job = job.clone();
+		let output = match task::spawn_blocking(move || compute_job.compute()).await {
+			Ok(output) => output,
+			Err(e) => {
+				error!(job = job.job_id, err = %e, "compute task failed");
+				continue;
+			},
+		};
+		let Ok(mut store) = store.write() else {
+			error!(job = job.job_id, "store lock poisoned");
+			return;
+		};
+		match output.and_then(|output| output.save(&mut store, &job.params)) {
+			Ok(convergence) => {
+				info!(
+					job = job.job_id,
+					iterations = convergence.iterations,
+					"converged"
+				)
+			},
+			Err(e) => error!(job = job.job_id, err = %e, "compute failed"),
+		}
+	}
+}
@@ -0,0 +1,173 @@
+use crate::error::ComputeError;
+use crate::matrix::SparseMatrix;
+
+/// Apply the one-shot distrust adjustment (Phase 1b) to a positive-only global trust vector.
+///
+/// Each truster's distrust opinions are normalized to the truster's own trust score,
+/// i.e. a truster with score t distrusting peers with weights w_1..w_n
+/// deducts t * w_i / (w_1 + ... + w_n) from peer i.
+/// Trusters with zero score have no say.
+///
+/// Since the positive scores sum up to (at most) 1,
+/// the adjusted scores fall within [-1, 1].
+pub fn adjust(positive: &[f64], distrust: &SparseMatrix) -> Result<Vec<f64>, ComputeError> {
+	if distrust.dim() > positive.len() {
+		return Err(ComputeError::DimensionMismatch {
+			expected: positive.len(),
+			actual: distrust.dim(),
+		});
+	}
+	if let Some((peer, value)) = positive.iter().enumerate().find(|(_, v)| **v < 0.) {
+		return Err(ComputeError::NegativePreTrust { peer, value: *value });
+	}
+
+	let mut adjusted = positive.to_vec();
+	for (truster, t) in positive.iter().enumerate() {
+		if *t == 0. {
+			continue;
+		}
+		let mut sum = 0.;
+		for (trustee, value) in distrust.row(truster) {
+			if value < 0. {
+				return Err(ComputeError::NegativeLocalTrust { truster, trustee, value });
+			}
+			sum += value;
+		}
+		if sum > 0. {
+			distrust.row(truster).for_each(|(trustee, value)| adjusted[trustee] -= t * value / sum);
+		}
+	}
+	adjusted.iter_mut().for_each(|x| *x = x.clamp(-1., 1.));
+	Ok(adjusted)
+}
+
+#[cfg(test)]
+mod test {
+	use compute::Params;
+
+	use super::*;
+	use crate::computation::basic_compute;
+	use crate::store::{Store, TrustMatrix, TrustVector};
+
+	const X: &str = "did:pkh:eth:0xa9572220348b1080264e81c0779f77c144790cd6";
+	const Y: &str = "did:pkh:eth:0xba9090181312bd0e40254a3dc29841980dd392d2";
+	const Z: &str = "did:pkh:eth:0x9a2954b87d8745df0b1010291c51d68ae9269d43";
+	const P: &str = "did:pkh:eth:0x651a3c584f4c71b54c50ea73f41b936845ab4fdf";
+	const Q: &str = "did:pkh:eth:0x138aaabbc2ad61f8ea7f2d4155cc7323f26f8775";
+
+	/// Run a compute with distrust, as the compute service does:
+	/// Phase 1a on the trust arcs, then Phase 1b on the distrust arcs,
+	/// which the local trust holds as negative entries.
+	/// Returns the positive-only and the adjusted global trust.
+	fn run(
+		trust: &[(&str, &str)], distrust: &[(&str, &str)], pre_trusted: &[&str],
+	) -> (TrustVector, TrustVector) {
+		let mut store = Store::new();
+		store.create_matrix("lt").unwrap();
+		["pt", "gt", "pgt"].iter().for_each(|id| {
+			store.create_vector(id).unwrap();
+		});
+		let arcs = |arcs: &[(&str, &str)], value: f64| {
+			arcs.iter()
+				.map(move |(i, j)| ((i.to_string(), j.to_string()), value))
+				.collect::<Vec<_>>()
+		};
+		let entries = arcs(trust, 1.).into_iter().chain(arcs(distrust, -1.)).collect();
+		store.set_matrix("lt", TrustMatrix { entries, ..Default::default() }).unwrap();
+		let entries = pre_trusted.iter().map(|p| (p.to_string(), 1.)).collect();
+		store.set_vector("pt", TrustVector { entries, ..Default::default() }).unwrap();
+
+		let params = Params {
+			local_trust_id: "lt".to_string(),
+			pre_trust_id: "pt".to_string(),
+			alpha: Some(0.5),
+			epsilon: Some(1e-12),
+			global_trust_id: "gt".to_string(),
+			positive_global_trust_id: "pgt".to_string(),
+			distrust: true,
+			..Default::default()
+		};
+		basic_compute(&mut store, &params).unwrap();
+		(
+			store.vector("pgt").unwrap().clone(),
+			store.vector("gt").unwrap().clone(),
+		)
+	}
+
+	fn score(v: &TrustVector, peer: &str) -> f64 {
+		v.entries.get(peer).copied().unwrap_or(0.)
+	}
+
+	#[test]
+	fn should_normalize_deductions_to_truster_score() {
+		let positive = [0.6, 0.4, 0., 0.];
+		// 0 distrusts 2 and 3 (weights 1:3); 2 has no standing, so its opinion is ignored.
+		let distrust = SparseMatrix::from_entries(4, [(0, 2, 1.), (0, 3, 3.), (2, 0, 5.)]);
+
+		let adjusted = adjust(&positive, &distrust).unwrap();
+
+		let expected = [0.6, 0.4, -0.15, -0.45];
+		assert!(
+			adjusted.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-12),
+			"{:?}",
+			adjusted
+		);
+	}
+
+	#[test]
+	fn should_reject_negative_inputs() {
+		let distrust = SparseMatrix::from_entries(2, [(0, 1, 1.)]);
+		assert_eq!(
+			adjust(&[0.5, -0.5], &distrust),
+			Err(ComputeError::NegativePreTrust { peer: 1, value: -0.5 })
+		);
+		let distrust = SparseMatrix::from_entries(2, [(0, 1, -1.)]);
+		assert_eq!(
+			adjust(&[0.5, 0.5], &distrust),
+			Err(ComputeError::NegativeLocalTrust { truster: 0, trustee: 1, value: -1. })
+		);
+	}
+
+	#[test]
+	fn should_discredit_sybil_attacker() {
+		// x, y, z trust each other; p distrusts all of them, and they distrust p back.
+		// q (a sybil) trusts y.
+		let trust = [(X, Y), (X, Z), (Y, X), (Y, Z), (Z, X), (Z, Y), (Q, Y)];
+		let distrust = [(P, X), (P, Y), (P, Z), (X, P), (Y, P), (Z, P)];
+
+		let (positive, adjusted) = run(&trust, &distrust, &[X, Y, Z]);
+
+		// Nobody trusts p or q, so p's distrust does not matter...
+		assert_eq!(score(&positive, P), 0.);
+		assert_eq!(score(&positive, Q), 0.);
+		for peer in [X, Y, Z] {
+			assert!((score(&adjusted, peer) - score(&positive, peer)).abs() < 1e-12);
+		}
+		// ...while p receives the full deduction of x, y and z.
+		assert!((score(&adjusted, P) + 1.).abs() < 1e-9);
+	}
+
+	#[test]
+	fn should_let_sleeping_agents_discredit_only_with_their_standing() {
+		// p and q trust each other and z, then turn on x and y.
+		let trust = [(P, Q), (Q, P), (P, Z), (Q, Z)];
+		let distrust = [(P, X), (P, Y), (Q, X), (Q, Y)];
+
+		let (positive, adjusted) = run(&trust, &distrust, &[P, Q]);
+
+		let (p, q) = (score(&positive, P), score(&positive, Q));
+		assert!(p > 0. && q > 0.);
+		// x and y split the deductions of p and q evenly.
+		assert!((score(&adjusted, X) + (p + q) / 2.).abs() < 1e-12);
+		assert!((score(&adjusted, Y) + (p + q) / 2.).abs() < 1e-12);
+		for peer in [P, Q, Z] {
+			assert_eq!(score(&adjusted, peer), score(&positive, peer));
+		}
+		// Once nobody trusts p anymore, only q's opinion counts.
+		let trust = [(P, Q), (P, Z), (Q, Z)];
+		let (positive, adjusted) = run(&trust, &distrust, &[Q]);
+		assert_eq!(score(&positive, P), 0.);
+		let q = score(&positive, Q);
+		assert!((score(&adjusted, X) + q / 2.).abs() < 1e-12);
+	}
+}
@@ -0,0 +1,365 @@
+use crate::error::ComputeError;
+use crate::matrix::SparseMatrix;
+
+/// Default pre-trust strength, used when `Params.alpha` is not given.
+pub const DEFAULT_ALPHA: f64 = 0.5;
+/// Default convergence threshold, used when `Params.epsilon` is not given.
+pub const DEFAULT_EPSILON: f64 = 1e-6;
+/// Iteration cap, which also applies when `Params.max_iterations` is 0 (unlimited),
+/// as e.g. a periodic chain without pre-trust pull never converges.
+pub const MAX_ITERATIONS: u32 = 100_000;
+
+/// EigenTrust parameters, with the same semantics as `compute::Params`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params {
+	/// Pre-trust strength.
+	pub alpha: Option<f64>,
+	/// Convergence exit criteria.
+	pub epsilon: Option<f64>,
+	/// Maximum number of iterations to perform, 0: up to [`MAX_ITERATIONS`].
+	pub max_iterations: u32,
+	/// Minimum flat-tail length, 0: do not check the ranking.
+	pub flat_tail: u32,
+	/// Number of top-ranked peers considered by the flat-tail check, 0: all.
+	pub num_leaders: u32,
+}
+
+impl Params {
+	pub fn new(alpha: Option<f64>, epsilon: Option<f64>, max_iterations: u32) -> Self {
+		Self { alpha, epsilon, max_iterations, ..Default::default() }
+	}
+
+	/// Also require the top `num_leaders` ranking to stay unchanged
+	/// for `flat_tail` consecutive iterations before stopping.
+	pub fn with_flat_tail(mut self, flat_tail: u32, num_leaders: u32) -> Self {
+		self.flat_tail = flat_tail;
+		self.num_leaders = num_leaders;
+		self
+	}
+
+	fn alpha(&self) -> Result<f64, ComputeError> {
+		let alpha = self.alpha.unwrap_or(DEFAULT_ALPHA);
+		if !(0. ..=1.).contains(&alpha) {
+			return Err(ComputeError::InvalidAlpha(alpha));
+		}
+		Ok(alpha)
+	}
+
+	fn epsilon(&self) -> Result<f64, ComputeError> {
+		let epsilon = self.epsilon.unwrap_or(DEFAULT_EPSILON);
+		if epsilon.is_nan() || epsilon <= 0. {
+			return Err(ComputeError::InvalidEpsilon(epsilon));
+		}
+		Ok(epsilon)
+	}
+
+	fn max_iterations(&self) -> u32 {
+		match self.max_iterations {
+			0 => MAX_ITERATIONS,
+			n => n.min(MAX_ITERATIONS),
+		}
+	}
+}
+
+/// How far a compute went, see `compute::BasicComputeResponse`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Convergence {
+	/// Number of iterations performed.
+	pub iterations: u32,
+	/// Distance between the last two iterations.
+	pub delta: f64,
+	/// Number of consecutive iterations with an unchanged leader ranking.
+	pub flat_tail: u32,
+}
+
+/// Compute global trust by power iteration:
+///
+/// t' = (1 - alpha) * C^T * t + alpha * p
+///
+/// where C is the row-normalized local trust and p is the normalized pre-trust.
+/// Peers with no outgoing local trust (dangling peers)
+/// distribute their trust according to the pre-trust.
+///
+/// `initial` is the iteration starting point;
+/// if it is absent or all zero, the pre-trust is used instead.
+/// Iteration stops once the Euclidean distance between
+/// two consecutive vectors is at most epsilon,
+/// or after `max_iterations` iterations (if non-zero, and at most [`MAX_ITERATIONS`]).
+/// If `flat_tail` is non-zero, the ranking of the top `num_leaders` peers
+/// must also have stayed unchanged for that many iterations.
+pub fn compute(
+	local_trust: &SparseMatrix, pre_trust: &[f64], initial: Option<&[f64]>, params: &Params,
+) -> Result<Vec<f64>, ComputeError> {
+	compute_with_convergence(local_trust, pre_trust, initial, params).map(|(t, _)| t)
+}
+
+/// Same as [`compute`], also reporting how far the iteration went.
+pub fn compute_with_convergence(
+	local_trust: &SparseMatrix, pre_trust: &[f64], initial: Option<&[f64]>, params: &Params,
+) -> Result<(Vec<f64>, Convergence), ComputeError> {
+	let alpha = params.alpha()?;
+	let epsilon = params.epsilon()?;
+	let dim = local_trust.dim().max(pre_trust.len());
+	if local_trust.entries().any(|(_, _, v)| !v.is_finite()) {
+		return Err(ComputeError::NonFiniteInput("local trust"));
+	}
+	if pre_trust.iter().any(|v| !v.is_finite()) {
+		return Err(ComputeError::NonFiniteInput("pre-trust"));
+	}
+	if initial.map_or(false, |v| v.iter().any(|x| !x.is_finite())) {
+		return Err(ComputeError::NonFiniteInput("initial trust"));
+	}
+
+	let c = normalize_rows(local_trust)?;
+	let p = normalize_pre_trust(pre_trust, dim)?;
+	let mut t = match initial {
+		Some(v) if v.len() > dim => {
+			return Err(ComputeError::DimensionMismatch { expected: dim, actual: v.len() })
+		},
+		Some(v) if v.iter().any(|x| *x != 0.) => normalize(pad(v, dim)),
+		_ => p.clone(),
+	};
+
+	let mut convergence = Convergence::default();
+	let mut ranking = leaders(&t, params.num_leaders);
+	while convergence.iterations < params.max_iterations() {
+		let next = iterate(&c, &p, &t, alpha);
+		convergence.delta = distance(&next, &t);
+		t = next;
+		convergence.iterations += 1;
+		if params.flat_tail > 0 {
+			let next_ranking = leaders(&t, params.num_leaders);
+			if next_ranking == ranking {
+				convergence.flat_tail += 1;
+			} else {
+				convergence.flat_tail = 0;
+				ranking = next_ranking;
+			}
+		}
+		if convergence.delta <= epsilon && convergence.flat_tail >= params.flat_tail {
+			break;
+		}
+	}
+
+	Ok((t, convergence))
+}
+
+/// Indices of the top `n` peers (0: all), highest score first.
+fn leaders(t: &[f64], n: u32) -> Vec<usize> {
+	let mut ranking: Vec<usize> = (0..t.len()).collect();
+	ranking.sort_by(|a, b| t[*b].total_cmp(&t[*a]).then(a.cmp(b)));
+	if n > 0 {
+		ranking.truncate(n as usize);
+	}
+	ranking
+}
+
+/// One power iteration step.
+fn iterate(c: &SparseMatrix, p: &[f64], t: &[f64], alpha: f64) -> Vec<f64> {
+	let mut next = vec![0.; p.len()];
+	let mut dangling = 0.;
+	for (i, ti) in t.iter().enumerate() {
+		if *ti == 0. {
+			continue;
+		}
+		let mut row = c.row(i).peekable();
+		if row.peek().is_none() {
+			dangling += ti;
+			continue;
+		}
+		for (j, cij) in row {
+			next[j] += cij * ti;
+		}
+	}
+	next.iter_mut()
+		.zip(p)
+		.for_each(|(x, pi)| *x = (1. - alpha) * (*x + dangling * pi) + alpha * pi);
+	next
+}
+
+/// Scale each row so that it sums up to 1.
+fn normalize_rows(m: &SparseMatrix) -> Result<SparseMatrix, ComputeError> {
+	let mut c = SparseMatrix::new(m.dim());
+	for (truster, trustee, value) in m.entries() {
+		if value < 0. {
+			return Err(ComputeError::NegativeLocalTrust { truster, trustee, value });
+		}
+	}
+	for truster in 0..m.dim() {
+		let sum: f64 = m.row(truster).map(|(_, v)| v).sum();
+		if sum > 0. {
+			m.row(truster).for_each(|(trustee, v)| c.set(truster, trustee, v / sum));
+		}
+	}
+	Ok(c)
+}
+
+fn normalize_pre_trust(pre_trust: &[f64], dim: usize) -> Result<Vec<f64>, ComputeError> {
+	if let Some((peer, value)) = pre_trust.iter().enumerate().find(|(_, v)| **v < 0.) {
+		return Err(ComputeError::NegativePreTrust { peer, value: *value });
+	}
+	if pre_trust.iter().sum::<f64>() <= 0. {
+		return Err(ComputeError::EmptyPreTrust);
+	}
+	Ok(normalize(pad(pre_trust, dim)))
+}
+
+fn pad(v: &[f64], dim: usize) -> Vec<f64> {
+	let mut v = v.to_vec();
+	v.resize(dim, 0.);
+	v
+}
+
+fn normalize(mut v: Vec<f64>) -> Vec<f64> {
+	let sum: f64 = v.iter().sum();
+	v.iter_mut().for_each(|x| *x /= sum);
+	v
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+	a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn assert_close(a: &[f64], b: &[f64]) {
+		assert_eq!(a.len(), b.len());
+		for (x, y) in a.iter().zip(b) {
+			assert!((x - y).abs() < 1e-6, "{:?} != {:?}", a, b);
+		}
+	}
+
+	#[test]
+	fn should_return_pre_trust_when_alpha_is_one() {
+		let lt = SparseMatrix::from_entries(3, [(0, 1, 1.), (1, 2, 1.), (2, 0, 1.)]);
+		let pt = [2., 0., 2.];
+		let params = Params::new(Some(1.), None, 0);
+
+		let gt = compute(&lt, &pt, None, &params).unwrap();
+
+		assert_close(&gt, &[0.5, 0., 0.5]);
+	}
+
+	#[test]
+	fn should_converge_on_cycle() {
+		// A symmetric cycle converges to the uniform vector regardless of pre-trust.
+		let lt = SparseMatrix::from_entries(3, [(0, 1, 1.), (1, 2, 1.), (2, 0, 1.)]);
+		let pt = [1., 1., 1.];
+		let params = Params::new(Some(0.1), Some(1e-9), 0);
+
+		let gt = compute(&lt, &pt, None, &params).unwrap();
+
+		assert_close(&gt, &[1. / 3., 1. / 3., 1. / 3.]);
+	}
+
+	#[test]
+	fn should_redistribute_dangling_trust() {
+		// 0 trusts 1, 1 trusts nobody; pre-trust is on 0.
+		// t0 = a + (1-a) * t1, t1 = (1-a) * t0  =>  t0 = 1 / (2 - a), t1 = (1-a) / (2 - a)
+		let lt = SparseMatrix::from_entries(2, [(0, 1, 3.)]);
+		let pt = [1., 0.];
+		let params = Params::new(Some(0.5), Some(1e-12), 0);
+
+		let gt = compute(&lt, &pt, None, &params).unwrap();
+
+		assert_close(&gt, &[1. / 1.5, 0.5 / 1.5]);
+		assert!((gt.iter().sum::<f64>() - 1.).abs() < 1e-9);
+	}
+
+	#[test]
+	fn should_stop_at_max_iterations() {
+		let lt = SparseMatrix::from_entries(2, [(0, 1, 1.), (1, 0, 1.)]);
+		let pt = [1., 0.];
+		let params = Params::new(Some(0.), Some(1e-12), 1);
+
+		// Without pre-trust pull, one iteration moves all trust from 0 to 1.
+		let gt = compute(&lt, &pt, None, &params).unwrap();
+
+		assert_close(&gt, &[0., 1.]);
+	}
+
+	#[test]
+	fn should_wait_for_flat_tail() {
+		// 0 <-> 1 with pre-trust on 0 oscillates with a dampening amplitude,
+		// so the ranking flips every iteration until the scores settle.
+		let lt = SparseMatrix::from_entries(2, [(0, 1, 1.), (1, 0, 1.)]);
+		let pt = [1., 0.];
+
+		let params = Params::new(Some(0.1), Some(0.1), 0);
+		let (_, loose) = compute_with_convergence(&lt, &pt, None, &params).unwrap();
+		assert_eq!(loose.flat_tail, 0);
+		assert!(loose.delta <= 0.1);
+
+		let params = params.with_flat_tail(3, 1);
+		let (gt, flat) = compute_with_convergence(&lt, &pt, None, &params).unwrap();
+		assert_eq!(flat.flat_tail, 3);
+		assert!(flat.delta <= 0.1);
+		assert!(flat.iterations > loose.iterations);
+		// The fixed point is t0 = 0.1 / (1 - 0.81), t1 = 0.9 * t0.
+		assert!(gt[0] > gt[1]);
+	}
+
+	#[test]
+	fn should_start_from_initial_vector() {
+		let lt = SparseMatrix::from_entries(2, [(0, 1, 1.), (1, 0, 1.)]);
+		let pt = [1., 0.];
+		let params = Params::new(Some(0.), Some(1e-12), 1);
+
+		let gt = compute(&lt, &pt, Some(&[0., 4.]), &params).unwrap();
+
+		assert_close(&gt, &[1., 0.]);
+	}
+
+	#[test]
+	fn should_reject_invalid_input() {
+		let lt = SparseMatrix::from_entries(2, [(0, 1, -1.)]);
+		let params = Params::default();
+		assert_eq!(
+			compute(&lt, &[1., 0.], None, &params),
+			Err(ComputeError::NegativeLocalTrust { truster: 0, trustee: 1, value: -1. })
+		);
+
+		let lt = SparseMatrix::new(2);
+		assert_eq!(
+			compute(&lt, &[0., 0.], None, &params),
+			Err(ComputeError::EmptyPreTrust)
+		);
+		assert_eq!(
+			compute(&lt, &[1., 0.], None, &Params::new(Some(2.), None, 0)),
+			Err(ComputeError::InvalidAlpha(2.))
+		);
+		assert_eq!(
+			compute(&lt, &[1., 0.], None, &Params::new(None, Some(0.), 0)),
+			Err(ComputeError::InvalidEpsilon(0.))
+		);
+
+		let nan_lt = SparseMatrix::from_entries(2, [(0, 1, f64::NAN)]);
+		assert_eq!(
+			compute(&nan_lt, &[1., 0.], None, &params),
+			Err(ComputeError::NonFiniteInput("local trust"))
+		);
+		assert_eq!(
+			compute(&lt, &[f64::INFINITY, 0.], None, &params),
+			Err(ComputeError::NonFiniteInput("pre-trust"))
+		);
+		assert_eq!(
+			compute(&lt, &[1., 0.], Some(&[f64::NAN, 0.]), &params),
+			Err(ComputeError::NonFiniteInput("initial trust"))
+		);
+	}
+
+	#[test]
+	fn should_cap_unbounded_iterations() {
+		// Without pre-trust pull, trust oscillates between 0 and 1 forever.
+		let lt = SparseMatrix::from_entries(2, [(0, 1, 1.), (1, 0, 1.)]);
+		let params = Params::new(Some(0.), Some(1e-12), 0);
+
+		let (gt, convergence) = compute_with_convergence(&lt, &[1., 0.], None, &params).unwrap();
+
+		assert_eq!(convergence.iterations, MAX_ITERATIONS);
+		assert_eq!(convergence.delta, 2f64.sqrt());
+		assert_close(&gt, &[1., 0.]);
+	}
+}
@@ -0,0 +1,69 @@
+use num::BigUint;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ComputeError {
+	#[error("alpha must be within [0, 1], got {0}")]
+	InvalidAlpha(f64),
+
+	#[error("epsilon must be positive, got {0}")]
+	InvalidEpsilon(f64),
+
+	#[error("negative local trust {value} from {truster} to {trustee}")]
+	NegativeLocalTrust { truster: usize, trustee: usize, value: f64 },
+
+	#[error("negative pre-trust {value} for {peer}")]
+	NegativePreTrust { peer: usize, value: f64 },
+
+	#[error("{0} is not finite")]
+	NonFiniteInput(&'static str),
+
+	#[error("pre-trust vector is empty (all zero)")]
+	EmptyPreTrust,
+
+	#[error("dimension mismatch: expected {expected}, got {actual}")]
+	DimensionMismatch { expected: usize, actual: usize },
+
+	#[error("trust matrix {0} not found")]
+	MatrixNotFound(String),
+
+	#[error("trust vector {0} not found")]
+	VectorNotFound(String),
+
+	#[error("job {0} not found")]
+	JobNotFound(String),
+
+	#[error("{0} already exists")]
+	AlreadyExists(String),
+
+	#[error("invalid request: {0}")]
+	InvalidRequest(String),
+
+	#[error("update at {given} is older than the current timestamp {current}")]
+	StaleUpdate { current: BigUint, given: BigUint },
+
+	#[error("DbError: {0}")]
+	DbError(rocksdb::Error),
+
+	#[error("NotFoundError")]
+	NotFoundError,
+
+	#[error("corrupt record in storage")]
+	CorruptRecord,
+}
+
+impl From<ComputeError> for tonic::Status {
+	fn from(value: ComputeError) -> Self {
+		match value {
+			ComputeError::MatrixNotFound(_)
+			| ComputeError::VectorNotFound(_)
+			| ComputeError::JobNotFound(_) => Self::not_found(value.to_string()),
+			ComputeError::AlreadyExists(_) => Self::already_exists(value.to_string()),
+			ComputeError::StaleUpdate { .. } => Self::failed_precondition(value.to_string()),
+			ComputeError::DbError(_)
+			| ComputeError::NotFoundError
+			| ComputeError::CorruptRecord => Self::internal(format!("Internal error: {}", value)),
+			_ => Self::invalid_argument(value.to_string()),
+		}
+	}
+}
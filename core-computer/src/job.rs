@@ -0,0 +1,78 @@
+use compute::{JobSpec, Params};
+use num::{BigUint, Zero};
+use trustvector::qwords_to_big;
+
+use crate::error::ComputeError;
+
+/// A periodic compute job, see `compute::JobSpec`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+	pub params: Params,
+	pub period: BigUint,
+}
+
+impl Job {
+	pub fn new(spec: JobSpec) -> Result<Self, ComputeError> {
+		let params = spec
+			.params
+			.ok_or_else(|| ComputeError::InvalidRequest("missing params".to_string()))?;
+		let period = qwords_to_big(&spec.period_qwords);
+		if period.is_zero() {
+			return Err(ComputeError::InvalidRequest(
+				"period must be positive".to_string(),
+			));
+		}
+		Ok(Self { params, period })
+	}
+
+	/// Starting timestamp of the window that the given timestamp belongs to.
+	pub fn window_start(&self, timestamp: &BigUint) -> BigUint {
+		timestamp / &self.period * &self.period
+	}
+
+	/// Check whether an input update at `input_timestamp` triggers a re-compute,
+	/// given the current result timestamp.
+	/// Returns the timestamp of the new result if so.
+	pub fn trigger(
+		&self, result_timestamp: &BigUint, input_timestamp: &BigUint,
+	) -> Option<BigUint> {
+		let start = self.window_start(input_timestamp);
+		(start > *result_timestamp).then_some(start)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_trigger_as_documented() {
+		let spec = JobSpec { params: Some(Params::default()), period_qwords: vec![1000] };
+		let job = Job::new(spec).unwrap();
+
+		// The example table from compute.proto.
+		let table = [
+			(9947u32, None),
+			(10814, Some(10000u32)),
+			(11438, Some(11000)),
+			(11975, None),
+			(11999, None),
+			(12000, Some(12000)),
+			(12014, None),
+		];
+		let mut result = BigUint::from(9000u32);
+		for (input, expected) in table {
+			let triggered = job.trigger(&result, &input.into());
+			assert_eq!(triggered, expected.map(BigUint::from), "input {}", input);
+			if let Some(t) = triggered {
+				result = t;
+			}
+		}
+	}
+
+	#[test]
+	fn should_reject_zero_period() {
+		let spec = JobSpec { params: Some(Params::default()), period_qwords: vec![0, 0] };
+		assert!(Job::new(spec).is_err());
+	}
+}
@@ -0,0 +1,11 @@
+pub mod computation;
+pub mod distrust;
+pub mod eigentrust;
+pub mod error;
+pub mod job;
+pub mod managers;
+pub mod matrix;
+pub mod matrix_service;
+pub mod service;
+pub mod store;
+pub mod vector_service;
@@ -1,3 +1,32 @@
-fn main() {
-	println!("Hello, world!");
+use std::error::Error;
+use std::io::{stderr, IsTerminal};
+use std::sync::{Arc, RwLock};
+
+use compute::service_server::ServiceServer as ComputeServer;
+use core_compute::matrix_service::TrustMatrixService;
+use core_compute::service::ComputeService;
+use core_compute::store::Store;
+use core_compute::vector_service::TrustVectorService;
+use tonic::transport::Server;
+use trustmatrix::service_server::ServiceServer as TrustMatrixServer;
+use trustvector::service_server::ServiceServer as TrustVectorServer;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+	let subscriber = tracing_subscriber::FmtSubscriber::builder()
+		.with_writer(stderr)
+		.with_ansi(stderr().is_terminal())
+		.finish();
+	tracing::subscriber::set_global_default(subscriber)?;
+	let addr = "[::1]:8080".parse()?;
+	let store = Arc::new(RwLock::new(Store::open("cc-storage")?));
+	Server::builder()
+		.add_service(ComputeServer::new(ComputeService::new(store.clone())))
+		.add_service(TrustMatrixServer::new(TrustMatrixService::new(
+			store.clone(),
+		)))
+		.add_service(TrustVectorServer::new(TrustVectorService::new(store)))
+		.serve(addr)
+		.await?;
+	Ok(())
 }
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use compute::JobSpec;
+use prost::Message;
+use rocksdb::{IteratorMode, DB};
+use trustvector::big_to_qwords;
+
+use crate::error::ComputeError;
+use crate::job::Job;
+
+/// Persists periodic compute jobs in the "job" column family (ID -> encoded `JobSpec`).
+#[derive(Debug)]
+pub struct JobManager;
+
+impl JobManager {
+	pub fn write(db: &DB, id: &str, job: &Job) -> Result<(), ComputeError> {
+		let cf = db.cf_handle("job").ok_or(ComputeError::NotFoundError)?;
+		let spec =
+			JobSpec { params: Some(job.params.clone()), period_qwords: big_to_qwords(&job.period) };
+		db.put_cf(&cf, id.as_bytes(), spec.encode_to_vec()).map_err(ComputeError::DbError)
+	}
+
+	pub fn delete(db: &DB, id: &str) -> Result<(), ComputeError> {
+		let cf = db.cf_handle("job").ok_or(ComputeError::NotFoundError)?;
+		db.delete_cf(&cf, id.as_bytes()).map_err(ComputeError::DbError)
+	}
+
+	/// Read all the stored jobs.
+	pub fn load(db: &DB) -> Result<HashMap<String, Job>, ComputeError> {
+		let cf = db.cf_handle("job").ok_or(ComputeError::NotFoundError)?;
+
+		let mut jobs = HashMap::new();
+		for item in db.iterator_cf(&cf, IteratorMode::Start) {
+			let (key, value) = item.map_err(ComputeError::DbError)?;
+			let id = String::from_utf8(key.to_vec()).map_err(|_| ComputeError::CorruptRecord)?;
+			let spec = JobSpec::decode(&*value).map_err(|_| ComputeError::CorruptRecord)?;
+			let job = Job::new(spec).map_err(|_| ComputeError::CorruptRecord)?;
+			jobs.insert(id, job);
+		}
+		Ok(jobs)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use compute::Params;
+	use rocksdb::{Options, DB};
+
+	use super::*;
+
+	#[test]
+	fn should_write_delete_and_load_jobs() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(&opts, "cc-job-test-storage", vec!["job"]).unwrap();
+
+		let params = Params { local_trust_id: "lt".to_string(), ..Default::default() };
+		let job = Job { params, period: 1000u32.into() };
+		JobManager::write(&db, "j1", &job).unwrap();
+		JobManager::write(&db, "j2", &job).unwrap();
+		JobManager::delete(&db, "j2").unwrap();
+
+		let jobs = JobManager::load(&db).unwrap();
+		assert_eq!(jobs.len(), 1);
+		assert_eq!(jobs["j1"], job);
+	}
+}
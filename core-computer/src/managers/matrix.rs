@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use num::BigUint;
+use rocksdb::{IteratorMode, WriteBatch, DB};
+
+use super::{key_part, split_key_part};
+use crate::error::ComputeError;
+use crate::store::TrustMatrix;
+
+/// Persists trust matrices in two column families:
+/// "matrix" (ID -> timestamp) and "matrix_entry" (ID, truster, trustee -> value).
+#[derive(Debug)]
+pub struct MatrixManager;
+
+impl MatrixManager {
+	fn prefix(id: &str) -> Vec<u8> {
+		let mut key = Vec::new();
+		key_part(&mut key, id);
+		key
+	}
+
+	fn entry_key(id: &str, truster: &str, trustee: &str) -> Vec<u8> {
+		let mut key = Self::prefix(id);
+		key_part(&mut key, truster);
+		key.extend_from_slice(trustee.as_bytes());
+		key
+	}
+
+	/// Write the timestamp and the given entries; zero-valued entries are removed.
+	pub fn write(
+		db: &DB, id: &str, timestamp: &BigUint, entries: &[(String, String, f64)],
+	) -> Result<(), ComputeError> {
+		let mut batch = WriteBatch::default();
+		Self::write_into(&mut batch, db, id, timestamp, entries)?;
+		db.write(batch).map_err(ComputeError::DbError)
+	}
+
+	/// Replace all entries of the given matrix, and its timestamp, in one write.
+	pub fn replace(
+		db: &DB, id: &str, timestamp: &BigUint, entries: &[(String, String, f64)],
+	) -> Result<(), ComputeError> {
+		let mut batch = WriteBatch::default();
+		Self::flush_into(&mut batch, db, id)?;
+		Self::write_into(&mut batch, db, id, timestamp, entries)?;
+		db.write(batch).map_err(ComputeError::DbError)
+	}
+
+	/// Remove all entries of the given matrix.
+	pub fn flush(db: &DB, id: &str) -> Result<(), ComputeError> {
+		let mut batch = WriteBatch::default();
+		Self::flush_into(&mut batch, db, id)?;
+		db.write(batch).map_err(ComputeError::DbError)
+	}
+
+	fn write_into(
+		batch: &mut WriteBatch, db: &DB, id: &str, timestamp: &BigUint,
+		entries: &[(String, String, f64)],
+	) -> Result<(), ComputeError> {
+		let header_cf = db.cf_handle("matrix").ok_or(ComputeError::NotFoundError)?;
+		let entry_cf = db.cf_handle("matrix_entry").ok_or(ComputeError::NotFoundError)?;
+
+		batch.put_cf(&header_cf, id.as_bytes(), timestamp.to_bytes_be());
+		for (truster, trustee, value) in entries {
+			let key = Self::entry_key(id, truster, trustee);
+			if *value == 0. {
+				batch.delete_cf(&entry_cf, key);
+			} else {
+				batch.put_cf(&entry_cf, key, value.to_be_bytes());
+			}
+		}
+		Ok(())
+	}
+
+	fn flush_into(batch: &mut WriteBatch, db: &DB, id: &str) -> Result<(), ComputeError> {
+		let entry_cf = db.cf_handle("matrix_entry").ok_or(ComputeError::NotFoundError)?;
+		let prefix = Self::prefix(id);
+
+		for item in db.prefix_iterator_cf(&entry_cf, &prefix) {
+			let (key, _) = item.map_err(ComputeError::DbError)?;
+			if !key.starts_with(&prefix) {
+				break;
+			}
+			batch.delete_cf(&entry_cf, key);
+		}
+		Ok(())
+	}
+
+	/// Remove the given matrix altogether.
+	pub fn delete(db: &DB, id: &str) -> Result<(), ComputeError> {
+		Self::flush(db, id)?;
+		let header_cf = db.cf_handle("matrix").ok_or(ComputeError::NotFoundError)?;
+		db.delete_cf(&header_cf, id.as_bytes()).map_err(ComputeError::DbError)
+	}
+
+	/// Read all the stored matrices.
+	pub fn load(db: &DB) -> Result<HashMap<String, TrustMatrix>, ComputeError> {
+		let header_cf = db.cf_handle("matrix").ok_or(ComputeError::NotFoundError)?;
+		let entry_cf = db.cf_handle("matrix_entry").ok_or(ComputeError::NotFoundError)?;
+
+		let mut matrices = HashMap::new();
+		for item in db.iterator_cf(&header_cf, IteratorMode::Start) {
+			let (key, value) = item.map_err(ComputeError::DbError)?;
+			let id = String::from_utf8(key.to_vec()).map_err(|_| ComputeError::CorruptRecord)?;
+			let timestamp = BigUint::from_bytes_be(&value);
+			matrices.insert(id, TrustMatrix { timestamp, ..Default::default() });
+		}
+		for item in db.iterator_cf(&entry_cf, IteratorMode::Start) {
+			let (key, value) = item.map_err(ComputeError::DbError)?;
+			let (id, rest) = split_key_part(&key).ok_or(ComputeError::CorruptRecord)?;
+			let (truster, rest) = split_key_part(rest).ok_or(ComputeError::CorruptRecord)?;
+			let trustee =
+				String::from_utf8(rest.to_vec()).map_err(|_| ComputeError::CorruptRecord)?;
+			let value =
+				f64::from_be_bytes((*value).try_into().map_err(|_| ComputeError::CorruptRecord)?);
+			let matrix = matrices.get_mut(&id).ok_or(ComputeError::CorruptRecord)?;
+			matrix.entries.insert((truster, trustee), value);
+		}
+		Ok(matrices)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use rocksdb::{Options, DB};
+
+	use super::*;
+
+	#[test]
+	fn should_write_flush_and_load_matrices() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(
+			&opts,
+			"cc-matrix-test-storage",
+			vec!["matrix", "matrix_entry"],
+		)
+		.unwrap();
+
+		let entries =
+			vec![("a".to_string(), "b".to_string(), 1.), ("a".to_string(), "c".to_string(), 2.)];
+		MatrixManager::write(&db, "m1", &5u32.into(), &entries).unwrap();
+		MatrixManager::write(&db, "m2", &6u32.into(), &entries).unwrap();
+		let removal = vec![("a".to_string(), "b".to_string(), 0.)];
+		MatrixManager::write(&db, "m1", &7u32.into(), &removal).unwrap();
+		MatrixManager::flush(&db, "m2").unwrap();
+
+		let matrices = MatrixManager::load(&db).unwrap();
+		let m1 = &matrices["m1"];
+		assert_eq!(m1.timestamp, 7u32.into());
+		assert_eq!(m1.entries.len(), 1);
+		assert_eq!(m1.entries[&("a".to_string(), "c".to_string())], 2.);
+		assert!(matrices["m2"].entries.is_empty());
+
+		MatrixManager::delete(&db, "m2").unwrap();
+		assert!(!MatrixManager::load(&db).unwrap().contains_key("m2"));
+	}
+}
@@ -0,0 +1,23 @@
+pub mod job;
+pub mod matrix;
+pub mod vector;
+
+/// Length-prefixed key component, so that IDs never collide as prefixes.
+fn key_part(key: &mut Vec<u8>, part: &str) {
+	key.extend_from_slice(&(part.len() as u32).to_be_bytes());
+	key.extend_from_slice(part.as_bytes());
+}
+
+/// Split a length-prefixed key component off the front of `key`.
+fn split_key_part(key: &[u8]) -> Option<(String, &[u8])> {
+	if key.len() < 4 {
+		return None;
+	}
+	let (len, rest) = key.split_at(4);
+	let len = u32::from_be_bytes(len.try_into().ok()?) as usize;
+	if rest.len() < len {
+		return None;
+	}
+	let (part, rest) = rest.split_at(len);
+	Some((String::from_utf8(part.to_vec()).ok()?, rest))
+}
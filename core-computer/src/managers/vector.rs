@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use num::BigUint;
+use rocksdb::{IteratorMode, WriteBatch, DB};
+
+use super::{key_part, split_key_part};
+use crate::error::ComputeError;
+use crate::store::TrustVector;
+
+/// Persists trust vectors in two column families:
+/// "vector" (ID -> timestamp) and "vector_entry" (ID, trustee -> value).
+#[derive(Debug)]
+pub struct VectorManager;
+
+impl VectorManager {
+	fn prefix(id: &str) -> Vec<u8> {
+		let mut key = Vec::new();
+		key_part(&mut key, id);
+		key
+	}
+
+	fn entry_key(id: &str, trustee: &str) -> Vec<u8> {
+		let mut key = Self::prefix(id);
+		key.extend_from_slice(trustee.as_bytes());
+		key
+	}
+
+	/// Write the timestamp and the given entries; zero-valued entries are removed.
+	pub fn write(
+		db: &DB, id: &str, timestamp: &BigUint, entries: &[(String, f64)],
+	) -> Result<(), ComputeError> {
+		let mut batch = WriteBatch::default();
+		Self::write_into(&mut batch, db, id, timestamp, entries)?;
+		db.write(batch).map_err(ComputeError::DbError)
+	}
+
+	/// Replace all entries of the given vector, and its timestamp, in one write.
+	pub fn replace(
+		db: &DB, id: &str, timestamp: &BigUint, entries: &[(String, f64)],
+	) -> Result<(), ComputeError> {
+		let mut batch = WriteBatch::default();
+		Self::flush_into(&mut batch, db, id)?;
+		Self::write_into(&mut batch, db, id, timestamp, entries)?;
+		db.write(batch).map_err(ComputeError::DbError)
+	}
+
+	/// Remove all entries of the given vector.
+	pub fn flush(db: &DB, id: &str) -> Result<(), ComputeError> {
+		let mut batch = WriteBatch::default();
+		Self::flush_into(&mut batch, db, id)?;
+		db.write(batch).map_err(ComputeError::DbError)
+	}
+
+	fn write_into(
+		batch: &mut WriteBatch, db: &DB, id: &str, timestamp: &BigUint, entries: &[(String, f64)],
+	) -> Result<(), ComputeError> {
+		let header_cf = db.cf_handle("vector").ok_or(ComputeError::NotFoundError)?;
+		let entry_cf = db.cf_handle("vector_entry").ok_or(ComputeError::NotFoundError)?;
+
+		batch.put_cf(&header_cf, id.as_bytes(), timestamp.to_bytes_be());
+		for (trustee, value) in entries {
+			let key = Self::entry_key(id, trustee);
+			if *value == 0. {
+				batch.delete_cf(&entry_cf, key);
+			} else {
+				batch.put_cf(&entry_cf, key, value.to_be_bytes());
+			}
+		}
+		Ok(())
+	}
+
+	fn flush_into(batch: &mut WriteBatch, db: &DB, id: &str) -> Result<(), ComputeError> {
+		let entry_cf = db.cf_handle("vector_entry").ok_or(ComputeError::NotFoundError)?;
+		let prefix = Self::prefix(id);
+
+		for item in db.prefix_iterator_cf(&entry_cf, &prefix) {
+			let (key, _) = item.map_err(ComputeError::DbError)?;
+			if !key.starts_with(&prefix) {
+				break;
+			}
+			batch.delete_cf(&entry_cf, key);
+		}
+		Ok(())
+	}
+
+	/// Remove the given vector altogether.
+	pub fn delete(db: &DB, id: &str) -> Result<(), ComputeError> {
+		Self::flush(db, id)?;
+		let header_cf = db.cf_handle("vector").ok_or(ComputeError::NotFoundError)?;
+		db.delete_cf(&header_cf, id.as_bytes()).map_err(ComputeError::DbError)
+	}
+
+	/// Read all the stored vectors.
+	pub fn load(db: &DB) -> Result<HashMap<String, TrustVector>, ComputeError> {
+		let header_cf = db.cf_handle("vector").ok_or(ComputeError::NotFoundError)?;
+		let entry_cf = db.cf_handle("vector_entry").ok_or(ComputeError::NotFoundError)?;
+
+		let mut vectors = HashMap::new();
+		for item in db.iterator_cf(&header_cf, IteratorMode::Start) {
+			let (key, value) = item.map_err(ComputeError::DbError)?;
+			let id = String::from_utf8(key.to_vec()).map_err(|_| ComputeError::CorruptRecord)?;
+			let timestamp = BigUint::from_bytes_be(&value);
+			vectors.insert(id, TrustVector { timestamp, ..Default::default() });
+		}
+		for item in db.iterator_cf(&entry_cf, IteratorMode::Start) {
+			let (key, value) = item.map_err(ComputeError::DbError)?;
+			let (id, rest) = split_key_part(&key).ok_or(ComputeError::CorruptRecord)?;
+			let trustee =
+				String::from_utf8(rest.to_vec()).map_err(|_| ComputeError::CorruptRecord)?;
+			let value =
+				f64::from_be_bytes((*value).try_into().map_err(|_| ComputeError::CorruptRecord)?);
+			let vector = vectors.get_mut(&id).ok_or(ComputeError::CorruptRecord)?;
+			vector.entries.insert(trustee, value);
+		}
+		Ok(vectors)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use rocksdb::{Options, DB};
+
+	use super::*;
+
+	#[test]
+	fn should_write_flush_and_load_vectors() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(
+			&opts,
+			"cc-vector-test-storage",
+			vec!["vector", "vector_entry"],
+		)
+		.unwrap();
+
+		let entries = vec![("a".to_string(), 1.), ("b".to_string(), 2.)];
+		VectorManager::write(&db, "v1", &5u32.into(), &entries).unwrap();
+		VectorManager::write(&db, "v10", &6u32.into(), &entries).unwrap();
+		VectorManager::write(&db, "v1", &7u32.into(), &[("a".to_string(), 0.)]).unwrap();
+		VectorManager::flush(&db, "v10").unwrap();
+
+		let vectors = VectorManager::load(&db).unwrap();
+		let v1 = &vectors["v1"];
+		assert_eq!(v1.timestamp, 7u32.into());
+		assert_eq!(v1.entries.len(), 1);
+		assert_eq!(v1.entries["b"], 2.);
+		assert!(vectors["v10"].entries.is_empty());
+
+		VectorManager::delete(&db, "v10").unwrap();
+		assert!(!VectorManager::load(&db).unwrap().contains_key("v10"));
+	}
+}
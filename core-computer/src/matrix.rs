@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+/// Sparse trust matrix, stored row-major: truster -> (trustee -> value).
+///
+/// Peers are identified by their numeric index,
+/// i.e. the same index space the linear combiner assigns to DIDs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SparseMatrix {
+	dim: usize,
+	rows: BTreeMap<usize, BTreeMap<usize, f64>>,
+}
+
+impl SparseMatrix {
+	pub fn new(dim: usize) -> Self {
+		Self { dim, rows: BTreeMap::new() }
+	}
+
+	/// Build a matrix from (truster, trustee, value) entries.
+	/// The dimension grows to fit the largest index seen.
+	pub fn from_entries(
+		dim: usize, entries: impl IntoIterator<Item = (usize, usize, f64)>,
+	) -> Self {
+		let mut m = Self::new(dim);
+		for (truster, trustee, value) in entries {
+			m.set(truster, trustee, value);
+		}
+		m
+	}
+
+	pub fn dim(&self) -> usize {
+		self.dim
+	}
+
+	/// Grow the matrix so that it covers at least `dim` peers.
+	pub fn grow(&mut self, dim: usize) {
+		self.dim = self.dim.max(dim);
+	}
+
+	/// Set an entry; a zero value removes it.
+	pub fn set(&mut self, truster: usize, trustee: usize, value: f64) {
+		self.grow(truster.max(trustee) + 1);
+		if value == 0. {
+			if let Some(row) = self.rows.get_mut(&truster) {
+				row.remove(&trustee);
+				if row.is_empty() {
+					self.rows.remove(&truster);
+				}
+			}
+		} else {
+			self.rows.entry(truster).or_default().insert(trustee, value);
+		}
+	}
+
+	pub fn get(&self, truster: usize, trustee: usize) -> f64 {
+		self.rows.get(&truster).and_then(|row| row.get(&trustee)).copied().unwrap_or(0.)
+	}
+
+	pub fn row(&self, truster: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+		self.rows.get(&truster).into_iter().flat_map(|row| row.iter().map(|(j, v)| (*j, *v)))
+	}
+
+	/// Non-zero entries, in (truster, trustee) order.
+	pub fn entries(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+		self.rows.iter().flat_map(|(i, row)| row.iter().map(move |(j, v)| (*i, *j, *v)))
+	}
+
+	pub fn nnz(&self) -> usize {
+		self.rows.values().map(|row| row.len()).sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.rows.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_set_get_and_remove_entries() {
+		let mut m = SparseMatrix::new(2);
+		m.set(0, 1, 0.5);
+		m.set(3, 0, 1.);
+		assert_eq!(m.dim(), 4);
+		assert_eq!(m.get(0, 1), 0.5);
+		assert_eq!(m.get(1, 0), 0.);
+		assert_eq!(m.nnz(), 2);
+
+		m.set(0, 1, 0.);
+		assert_eq!(m.entries().collect::<Vec<_>>(), vec![(3, 0, 1.)]);
+	}
+}
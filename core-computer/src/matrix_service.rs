@@ -0,0 +1,168 @@
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use tonic::{Request, Response, Status};
+use trustmatrix::service_server::Service;
+use trustmatrix::{
+	big_to_qwords, get_response, qwords_to_big, CreateRequest, CreateResponse, DeleteRequest,
+	DeleteResponse, Entry, FlushRequest, FlushResponse, GetRequest, GetResponse, Header,
+	UpdateRequest, UpdateResponse,
+};
+
+use crate::computation::run_triggered;
+use crate::store::{SharedStore, Store};
+
+/// trustmatrix.proto `Service` implementation, backed by the shared store.
+#[derive(Clone, Default)]
+pub struct TrustMatrixService {
+	store: SharedStore,
+}
+
+impl TrustMatrixService {
+	pub fn new(store: SharedStore) -> Self {
+		Self { store }
+	}
+
+	fn read_store(&self) -> Result<RwLockReadGuard<Store>, Status> {
+		self.store.read().map_err(|_| Status::internal("store lock poisoned"))
+	}
+
+	fn write_store(&self) -> Result<RwLockWriteGuard<Store>, Status> {
+		self.store.write().map_err(|_| Status::internal("store lock poisoned"))
+	}
+}
+
+#[tonic::async_trait]
+impl Service for TrustMatrixService {
+	type GetStream = tokio_stream::Iter<std::vec::IntoIter<Result<GetResponse, Status>>>;
+
+	async fn create(
+		&self, request: Request<CreateRequest>,
+	) -> Result<Response<CreateResponse>, Status> {
+		let id = self.write_store()?.create_matrix(&request.into_inner().id)?;
+		Ok(Response::new(CreateResponse { id }))
+	}
+
+	async fn get(&self, request: Request<GetRequest>) -> Result<Response<Self::GetStream>, Status> {
+		let id = request.into_inner().id;
+		let store = self.read_store()?;
+		let matrix = store.matrix(&id)?;
+
+		let header = Header { id: Some(id), timestamp_qwords: big_to_qwords(&matrix.timestamp) };
+		let mut parts = vec![Ok(GetResponse { part: Some(get_response::Part::Header(header)) })];
+		for ((truster, trustee), value) in &matrix.entries {
+			let entry = Entry { truster: truster.clone(), trustee: trustee.clone(), value: *value };
+			parts.push(Ok(GetResponse {
+				part: Some(get_response::Part::Entry(entry)),
+			}));
+		}
+		Ok(Response::new(tokio_stream::iter(parts)))
+	}
+
+	async fn update(
+		&self, request: Request<UpdateRequest>,
+	) -> Result<Response<UpdateResponse>, Status> {
+		let UpdateRequest { header, entries } = request.into_inner();
+		let header = header.ok_or_else(|| Status::invalid_argument("missing header"))?;
+		let id = header.id.ok_or_else(|| Status::invalid_argument("missing trust matrix ID"))?;
+		let timestamp = qwords_to_big(&header.timestamp_qwords);
+		let entries = entries.into_iter().map(|e| (e.truster, e.trustee, e.value)).collect();
+		let triggered = self.write_store()?.update_matrix(&id, &timestamp, entries)?;
+		run_triggered(&self.store, triggered).await;
+		Ok(Response::new(UpdateResponse {}))
+	}
+
+	async fn flush(
+		&self, request: Request<FlushRequest>,
+	) -> Result<Response<FlushResponse>, Status> {
+		self.write_store()?.flush_matrix(&request.into_inner().id)?;
+		Ok(Response::new(FlushResponse {}))
+	}
+
+	async fn delete(
+		&self, request: Request<DeleteRequest>,
+	) -> Result<Response<DeleteResponse>, Status> {
+		self.write_store()?.delete_matrix(&request.into_inner().id)?;
+		Ok(Response::new(DeleteResponse {}))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{Arc, RwLock};
+
+	use compute::Params;
+	use tokio_stream::StreamExt;
+
+	use super::*;
+	use crate::job::Job;
+	use crate::store::TrustVector;
+
+	fn update(id: &str, timestamp: u64, entries: &[(&str, &str, f64)]) -> Request<UpdateRequest> {
+		let header =
+			Some(Header { id: Some(id.to_string()), timestamp_qwords: vec![0, timestamp] });
+		let entries = entries
+			.iter()
+			.map(|(truster, trustee, value)| Entry {
+				truster: truster.to_string(),
+				trustee: trustee.to_string(),
+				value: *value,
+			})
+			.collect();
+		Request::new(UpdateRequest { header, entries })
+	}
+
+	#[tokio::test]
+	async fn should_update_and_get_matrix() {
+		let service = TrustMatrixService::default();
+		let request = Request::new(CreateRequest { id: String::new() });
+		let id = service.create(request).await.unwrap().into_inner().id;
+
+		service.update(update(&id, 10, &[("a", "b", 1.), ("b", "a", 2.)])).await.unwrap();
+		service.update(update(&id, 11, &[("b", "a", 0.)])).await.unwrap();
+		let status = service.update(update(&id, 9, &[("c", "a", 1.)])).await.unwrap_err();
+		assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+		let request = Request::new(GetRequest { id: id.clone() });
+		let parts: Vec<_> = service.get(request).await.unwrap().into_inner().collect().await;
+		let parts: Vec<_> = parts.into_iter().map(|p| p.unwrap().part.unwrap()).collect();
+		assert_eq!(
+			parts,
+			vec![
+				get_response::Part::Header(Header { id: Some(id), timestamp_qwords: vec![11] }),
+				get_response::Part::Entry(Entry {
+					truster: "a".to_string(),
+					trustee: "b".to_string(),
+					value: 1.
+				}),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn should_run_triggered_jobs() {
+		let mut store = Store::new();
+		store.create_matrix("lt").unwrap();
+		store.create_vector("pt").unwrap();
+		store.create_vector("gt").unwrap();
+		let pre_trust =
+			TrustVector { timestamp: 0u32.into(), entries: [("a".to_string(), 1.)].into() };
+		store.set_vector("pt", pre_trust).unwrap();
+		let params = Params {
+			local_trust_id: "lt".to_string(),
+			pre_trust_id: "pt".to_string(),
+			global_trust_id: "gt".to_string(),
+			..Default::default()
+		};
+		store.create_job(Job { params, period: 1000u32.into() }).unwrap();
+		let store = Arc::new(RwLock::new(store));
+		let service = TrustMatrixService::new(store.clone());
+
+		service.update(update("lt", 500, &[("a", "b", 1.)])).await.unwrap();
+		service.update(update("lt", 1500, &[("a", "c", 1.)])).await.unwrap();
+
+		let store = store.read().unwrap();
+		let gt = store.vector("gt").unwrap();
+		assert_eq!(gt.timestamp, 1000u32.into());
+		assert!(gt.entries.contains_key("b") && !gt.entries.contains_key("c"));
+	}
+}
@@ -0,0 +1,199 @@
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use compute::service_server::Service;
+use compute::{
+	BasicComputeRequest, BasicComputeResponse, CreateJobRequest, CreateJobResponse,
+	DeleteJobRequest, DeleteJobResponse,
+};
+use tokio::task;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::computation::{validate_params, ComputeInputs};
+use crate::eigentrust::Convergence;
+use crate::job::Job;
+use crate::store::{SharedStore, Store};
+
+/// compute.proto `Service` implementation, running EigenTrust in-process.
+#[derive(Clone, Default)]
+pub struct ComputeService {
+	store: SharedStore,
+}
+
+impl ComputeService {
+	pub fn new(store: SharedStore) -> Self {
+		Self { store }
+	}
+
+	pub fn store(&self) -> &SharedStore {
+		&self.store
+	}
+
+	fn read_store(&self) -> Result<RwLockReadGuard<Store>, Status> {
+		self.store.read().map_err(|_| Status::internal("store lock poisoned"))
+	}
+
+	fn write_store(&self) -> Result<RwLockWriteGuard<Store>, Status> {
+		self.store.write().map_err(|_| Status::internal("store lock poisoned"))
+	}
+}
+
+#[tonic::async_trait]
+impl Service for ComputeService {
+	async fn basic_compute(
+		&self, request: Request<BasicComputeRequest>,
+	) -> Result<Response<BasicComputeResponse>, Status> {
+		let params = request
+			.into_inner()
+			.params
+			.ok_or_else(|| Status::invalid_argument("missing params"))?;
+		if !params.destinations.is_empty() {
+			return Err(Status::unimplemented("destinations are not supported yet"));
+		}
+		// The compute runs off the async runtime, and without holding the store lock.
+		let inputs = ComputeInputs::snapshot(&*self.read_store()?, &params)?;
+		let compute_params = params.clone();
+		let output = task::spawn_blocking(move || {
+			let timestamp = inputs.timestamp();
+			inputs.compute(&compute_params, timestamp)
+		})
+		.await
+		.map_err(|e| Status::internal(format!("compute task failed: {}", e)))??;
+		let Convergence { iterations, delta, flat_tail } =
+			output.save(&mut *self.write_store()?, &params)?;
+		Ok(Response::new(BasicComputeResponse {
+			iterations,
+			delta,
+			flat_tail,
+		}))
+	}
+
+	async fn create_job(
+		&self, request: Request<CreateJobRequest>,
+	) -> Result<Response<CreateJobResponse>, Status> {
+		let spec =
+			request.into_inner().spec.ok_or_else(|| Status::invalid_argument("missing spec"))?;
+		let params =
+			spec.params.as_ref().ok_or_else(|| Status::invalid_argument("missing params"))?;
+		if !params.destinations.is_empty() {
+			return Err(Status::unimplemented("destinations are not supported yet"));
+		}
+		let mut store = self.write_store()?;
+		validate_params(&store, params)?;
+		let id = store.create_job(Job::new(spec)?)?;
+		info!(id, "created job");
+		Ok(Response::new(CreateJobResponse { id }))
+	}
+
+	async fn delete_job(
+		&self, request: Request<DeleteJobRequest>,
+	) -> Result<Response<DeleteJobResponse>, Status> {
+		let id = request.into_inner().id;
+		self.write_store()?.delete_job(&id)?;
+		info!(id, "deleted job");
+		Ok(Response::new(DeleteJobResponse {}))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::sync::{Arc, RwLock};
+
+	use compute::{JobSpec, Params};
+
+	use super::*;
+	use crate::store::{TrustMatrix, TrustVector};
+
+	fn setup() -> ComputeService {
+		let mut store = Store::new();
+		store.create_matrix("lt").unwrap();
+		store.create_vector("pt").unwrap();
+		store.create_vector("gt").unwrap();
+		store.create_vector("pgt").unwrap();
+		let entries = [("a", "b"), ("b", "c"), ("c", "a")]
+			.into_iter()
+			.map(|(i, j)| ((i.to_string(), j.to_string()), 1.))
+			.collect();
+		store.set_matrix("lt", TrustMatrix { timestamp: 5u32.into(), entries }).unwrap();
+		let entries = ["a", "b", "c"].into_iter().map(|id| (id.to_string(), 1.)).collect();
+		store.set_vector("pt", TrustVector { timestamp: 7u32.into(), entries }).unwrap();
+		ComputeService::new(Arc::new(RwLock::new(store)))
+	}
+
+	fn params() -> Params {
+		Params {
+			local_trust_id: "lt".to_string(),
+			pre_trust_id: "pt".to_string(),
+			alpha: Some(0.1),
+			epsilon: Some(1e-9),
+			global_trust_id: "gt".to_string(),
+			positive_global_trust_id: "pgt".to_string(),
+			..Default::default()
+		}
+	}
+
+	#[tokio::test]
+	async fn should_compute_into_global_trust() {
+		let service = setup();
+		let request = Request::new(BasicComputeRequest { params: Some(params()) });
+		let response = service.basic_compute(request).await.unwrap().into_inner();
+		assert!(response.iterations > 0);
+		assert!(response.delta <= 1e-9);
+
+		let store = service.store().read().unwrap();
+		let gt = store.vector("gt").unwrap();
+		assert_eq!(gt.timestamp, 7u32.into());
+		assert_eq!(gt.entries.len(), 3);
+		assert!(gt.entries.values().all(|v| (v - 1. / 3.).abs() < 1e-6));
+		assert_eq!(store.vector("pgt").unwrap(), gt);
+	}
+
+	#[tokio::test]
+	async fn should_apply_negative_local_trust_as_distrust() {
+		let service = setup();
+		{
+			let mut store = service.store().write().unwrap();
+			let entries = vec![("a".to_string(), "c".to_string(), -1.)];
+			store.update_matrix("lt", &6u32.into(), entries).unwrap();
+		}
+		// Distrust is opt-in; negative local trust is rejected otherwise.
+		let request = Request::new(BasicComputeRequest { params: Some(params()) });
+		let status = service.basic_compute(request).await.unwrap_err();
+		assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+		let params = Params { distrust: true, ..params() };
+		let request = Request::new(BasicComputeRequest { params: Some(params) });
+		service.basic_compute(request).await.unwrap();
+
+		let store = service.store().read().unwrap();
+		let (gt, pgt) = (store.vector("gt").unwrap(), store.vector("pgt").unwrap());
+		// a distrusts c, so c loses a's entire score.
+		assert!(pgt.entries.values().all(|v| *v > 0.));
+		let c = gt.entries.get("c").copied().unwrap_or(0.);
+		assert!((c - (pgt.entries["c"] - pgt.entries["a"])).abs() < 1e-12);
+		assert_eq!(gt.entries["b"], pgt.entries["b"]);
+	}
+
+	#[tokio::test]
+	async fn should_reject_unknown_ids() {
+		let service = setup();
+		let params = Params { local_trust_id: "nope".to_string(), ..params() };
+		let request = Request::new(BasicComputeRequest { params: Some(params) });
+		let status = service.basic_compute(request).await.unwrap_err();
+		assert_eq!(status.code(), tonic::Code::NotFound);
+	}
+
+	#[tokio::test]
+	async fn should_create_and_delete_jobs() {
+		let service = setup();
+		let spec = JobSpec { params: Some(params()), period_qwords: vec![1000] };
+		let request = Request::new(CreateJobRequest { spec: Some(spec) });
+		let id = service.create_job(request).await.unwrap().into_inner().id;
+
+		let request = Request::new(DeleteJobRequest { id: id.clone() });
+		service.delete_job(request).await.unwrap();
+		let request = Request::new(DeleteJobRequest { id });
+		let status = service.delete_job(request).await.unwrap_err();
+		assert_eq!(status.code(), tonic::Code::NotFound);
+	}
+}
@@ -0,0 +1,534 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use compute::Params;
+use num::BigUint;
+use rand::Rng;
+use rocksdb::{Options, DB};
+use tracing::error;
+
+use crate::computation::TriggeredCompute;
+use crate::error::ComputeError;
+use crate::job::Job;
+use crate::managers::job::JobManager;
+use crate::managers::matrix::MatrixManager;
+use crate::managers::vector::VectorManager;
+use crate::matrix::SparseMatrix;
+
+/// Local trust matrix contents, keyed by (truster, trustee) peer IDs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustMatrix {
+	pub timestamp: BigUint,
+	pub entries: BTreeMap<(String, String), f64>,
+}
+
+/// Trust vector contents (pre-trust or global trust), keyed by peer ID.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustVector {
+	pub timestamp: BigUint,
+	pub entries: BTreeMap<String, f64>,
+}
+
+/// Store shared between the compute service and the trust matrix/vector services.
+pub type SharedStore = Arc<RwLock<Store>>;
+
+/// In-process store of named trust matrices and vectors.
+///
+/// If opened with a database path, every change (including periodic jobs) is also
+/// written through to rocksdb, and the contents are reloaded from there upon the next start.
+#[derive(Default)]
+pub struct Store {
+	db: Option<Arc<DB>>,
+	matrices: HashMap<String, TrustMatrix>,
+	vectors: HashMap<String, TrustVector>,
+	jobs: HashMap<String, Job>,
+	/// Start of the latest window each job was triggered for, while its result may be pending.
+	triggered_windows: HashMap<String, BigUint>,
+}
+
+impl Store {
+	/// Create a memory-only store.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Open a persistent store, loading its existing contents.
+	pub fn open(db_url: &str) -> Result<Self, ComputeError> {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(
+			&opts,
+			db_url,
+			vec!["matrix", "matrix_entry", "vector", "vector_entry", "job"],
+		)
+		.map_err(ComputeError::DbError)?;
+		let matrices = MatrixManager::load(&db)?;
+		let vectors = VectorManager::load(&db)?;
+		let jobs = JobManager::load(&db)?;
+		Ok(Self { db: Some(Arc::new(db)), matrices, vectors, jobs, ..Default::default() })
+	}
+
+	/// Create an empty trust matrix, return its ID.
+	/// A random ID is generated if `id` is empty.
+	pub fn create_matrix(&mut self, id: &str) -> Result<String, ComputeError> {
+		let id = if id.is_empty() { random_id() } else { id.to_string() };
+		if self.matrices.contains_key(&id) {
+			return Err(ComputeError::AlreadyExists(id));
+		}
+		let matrix = TrustMatrix::default();
+		if let Some(db) = &self.db {
+			MatrixManager::write(db, &id, &matrix.timestamp, &[])?;
+		}
+		self.matrices.insert(id.clone(), matrix);
+		Ok(id)
+	}
+
+	pub fn matrix(&self, id: &str) -> Result<&TrustMatrix, ComputeError> {
+		self.matrices.get(id).ok_or_else(|| ComputeError::MatrixNotFound(id.to_string()))
+	}
+
+	/// Replace the contents of a trust matrix.
+	///
+	/// Like updates, replacements cannot go back in time:
+	/// one older than the matrix is rejected, leaving the matrix as it is.
+	pub fn set_matrix(&mut self, id: &str, matrix: TrustMatrix) -> Result<(), ComputeError> {
+		let m = self
+			.matrices
+			.get_mut(id)
+			.ok_or_else(|| ComputeError::MatrixNotFound(id.to_string()))?;
+		check_timestamp(&m.timestamp, &matrix.timestamp)?;
+		if let Some(db) = &self.db {
+			let entries: Vec<_> = matrix
+				.entries
+				.iter()
+				.map(|((truster, trustee), value)| (truster.clone(), trustee.clone(), *value))
+				.collect();
+			MatrixManager::replace(db, id, &matrix.timestamp, &entries)?;
+		}
+		m.timestamp = matrix.timestamp;
+		m.entries = matrix.entries.into_iter().filter(|(_, value)| *value != 0.).collect();
+		Ok(())
+	}
+
+	/// Update a trust matrix with the given (truster, trustee, value) entries,
+	/// and advance its timestamp.  Zero-valued entries are removed.
+	///
+	/// Updates cannot go back in time:
+	/// an update older than the matrix is rejected.
+	/// Returns the re-computes of the jobs using the matrix as local trust that the update
+	/// triggers, on their inputs before it; see [`crate::computation::run_triggered`].
+	pub fn update_matrix(
+		&mut self, id: &str, timestamp: &BigUint, entries: Vec<(String, String, f64)>,
+	) -> Result<Vec<TriggeredCompute>, ComputeError> {
+		check_timestamp(&self.matrix(id)?.timestamp, timestamp)?;
+		let triggered = self.triggered_jobs(|params| params.local_trust_id == id, timestamp);
+		self.apply_matrix(id, timestamp, entries)?;
+		Ok(triggered)
+	}
+
+	fn apply_matrix(
+		&mut self, id: &str, timestamp: &BigUint, entries: Vec<(String, String, f64)>,
+	) -> Result<(), ComputeError> {
+		let m = self
+			.matrices
+			.get_mut(id)
+			.ok_or_else(|| ComputeError::MatrixNotFound(id.to_string()))?;
+		if let Some(db) = &self.db {
+			MatrixManager::write(db, id, timestamp, &entries)?;
+		}
+		m.timestamp = timestamp.clone();
+		for (truster, trustee, value) in entries {
+			if value == 0. {
+				m.entries.remove(&(truster, trustee));
+			} else {
+				m.entries.insert((truster, trustee), value);
+			}
+		}
+		Ok(())
+	}
+
+	/// Remove all entries of a trust matrix, keeping its timestamp.
+	pub fn flush_matrix(&mut self, id: &str) -> Result<(), ComputeError> {
+		let m = self
+			.matrices
+			.get_mut(id)
+			.ok_or_else(|| ComputeError::MatrixNotFound(id.to_string()))?;
+		if let Some(db) = &self.db {
+			MatrixManager::flush(db, id)?;
+		}
+		m.entries.clear();
+		Ok(())
+	}
+
+	pub fn delete_matrix(&mut self, id: &str) -> Result<(), ComputeError> {
+		if !self.matrices.contains_key(id) {
+			return Err(ComputeError::MatrixNotFound(id.to_string()));
+		}
+		if let Some(db) = &self.db {
+			MatrixManager::delete(db, id)?;
+		}
+		self.matrices.remove(id);
+		Ok(())
+	}
+
+	/// Create an empty trust vector, return its ID.
+	/// A random ID is generated if `id` is empty.
+	pub fn create_vector(&mut self, id: &str) -> Result<String, ComputeError> {
+		let id = if id.is_empty() { random_id() } else { id.to_string() };
+		if self.vectors.contains_key(&id) {
+			return Err(ComputeError::AlreadyExists(id));
+		}
+		let vector = TrustVector::default();
+		if let Some(db) = &self.db {
+			VectorManager::write(db, &id, &vector.timestamp, &[])?;
+		}
+		self.vectors.insert(id.clone(), vector);
+		Ok(id)
+	}
+
+	pub fn vector(&self, id: &str) -> Result<&TrustVector, ComputeError> {
+		self.vectors.get(id).ok_or_else(|| ComputeError::VectorNotFound(id.to_string()))
+	}
+
+	/// Replace the contents of a trust vector.
+	///
+	/// Like updates, replacements cannot go back in time:
+	/// one older than the vector is rejected, leaving the vector as it is.
+	pub fn set_vector(&mut self, id: &str, vector: TrustVector) -> Result<(), ComputeError> {
+		let v =
+			self.vectors.get_mut(id).ok_or_else(|| ComputeError::VectorNotFound(id.to_string()))?;
+		check_timestamp(&v.timestamp, &vector.timestamp)?;
+		if let Some(db) = &self.db {
+			let entries: Vec<_> =
+				vector.entries.iter().map(|(trustee, value)| (trustee.clone(), *value)).collect();
+			VectorManager::replace(db, id, &vector.timestamp, &entries)?;
+		}
+		v.timestamp = vector.timestamp;
+		v.entries = vector.entries.into_iter().filter(|(_, value)| *value != 0.).collect();
+		Ok(())
+	}
+
+	/// Update a trust vector with the given (trustee, value) entries,
+	/// and advance its timestamp.  Zero-valued entries are removed.
+	///
+	/// Updates cannot go back in time:
+	/// an update older than the vector is rejected.
+	/// Returns the re-computes of the jobs using the vector as pre-trust that the update
+	/// triggers, on their inputs before it; see [`crate::computation::run_triggered`].
+	pub fn update_vector(
+		&mut self, id: &str, timestamp: &BigUint, entries: Vec<(String, f64)>,
+	) -> Result<Vec<TriggeredCompute>, ComputeError> {
+		check_timestamp(&self.vector(id)?.timestamp, timestamp)?;
+		let triggered = self.triggered_jobs(|params| params.pre_trust_id == id, timestamp);
+		self.apply_vector(id, timestamp, entries)?;
+		Ok(triggered)
+	}
+
+	fn apply_vector(
+		&mut self, id: &str, timestamp: &BigUint, entries: Vec<(String, f64)>,
+	) -> Result<(), ComputeError> {
+		let v =
+			self.vectors.get_mut(id).ok_or_else(|| ComputeError::VectorNotFound(id.to_string()))?;
+		if let Some(db) = &self.db {
+			VectorManager::write(db, id, timestamp, &entries)?;
+		}
+		v.timestamp = timestamp.clone();
+		for (trustee, value) in entries {
+			if value == 0. {
+				v.entries.remove(&trustee);
+			} else {
+				v.entries.insert(trustee, value);
+			}
+		}
+		Ok(())
+	}
+
+	/// Remove all entries of a trust vector, keeping its timestamp.
+	pub fn flush_vector(&mut self, id: &str) -> Result<(), ComputeError> {
+		let v =
+			self.vectors.get_mut(id).ok_or_else(|| ComputeError::VectorNotFound(id.to_string()))?;
+		if let Some(db) = &self.db {
+			VectorManager::flush(db, id)?;
+		}
+		v.entries.clear();
+		Ok(())
+	}
+
+	pub fn delete_vector(&mut self, id: &str) -> Result<(), ComputeError> {
+		if !self.vectors.contains_key(id) {
+			return Err(ComputeError::VectorNotFound(id.to_string()));
+		}
+		if let Some(db) = &self.db {
+			VectorManager::delete(db, id)?;
+		}
+		self.vectors.remove(id);
+		Ok(())
+	}
+
+	/// Register a periodic compute job, return its ID.
+	pub fn create_job(&mut self, job: Job) -> Result<String, ComputeError> {
+		let id = loop {
+			let id = random_id();
+			if !self.jobs.contains_key(&id) {
+				break id;
+			}
+		};
+		if let Some(db) = &self.db {
+			JobManager::write(db, &id, &job)?;
+		}
+		self.jobs.insert(id.clone(), job);
+		Ok(id)
+	}
+
+	pub fn delete_job(&mut self, id: &str) -> Result<Job, ComputeError> {
+		if !self.jobs.contains_key(id) {
+			return Err(ComputeError::JobNotFound(id.to_string()));
+		}
+		if let Some(db) = &self.db {
+			JobManager::delete(db, id)?;
+		}
+		self.triggered_windows.remove(id);
+		self.jobs.remove(id).ok_or_else(|| ComputeError::JobNotFound(id.to_string()))
+	}
+
+	/// Snapshot the inputs of the jobs whose inputs match `is_input`
+	/// and which are triggered by an update at `timestamp`.
+	/// This runs before the update is applied,
+	/// so that results only reflect the inputs before the window start.
+	fn triggered_jobs(
+		&mut self, is_input: impl Fn(&Params) -> bool, timestamp: &BigUint,
+	) -> Vec<TriggeredCompute> {
+		let mut triggered = Vec::new();
+		for (id, job) in &self.jobs {
+			if !is_input(&job.params) {
+				continue;
+			}
+			let Some(result) = self.vectors.get(&job.params.global_trust_id) else {
+				continue;
+			};
+			// A window whose result is still pending is not triggered again.
+			let latest = match self.triggered_windows.get(id) {
+				Some(window) => window.max(&result.timestamp),
+				None => &result.timestamp,
+			};
+			let Some(start) = job.trigger(latest, timestamp) else {
+				continue;
+			};
+			match TriggeredCompute::new(self, id.clone(), job.params.clone(), start) {
+				Ok(compute) => triggered.push(compute),
+				Err(e) => error!(job = id, err = %e, "compute failed"),
+			}
+		}
+		for compute in &triggered {
+			self.triggered_windows.insert(compute.job_id.clone(), compute.timestamp.clone());
+		}
+		triggered
+	}
+}
+
+/// Reject a change older than the current timestamp.
+fn check_timestamp(current: &BigUint, given: &BigUint) -> Result<(), ComputeError> {
+	if given < current {
+		return Err(ComputeError::StaleUpdate { current: current.clone(), given: given.clone() });
+	}
+	Ok(())
+}
+
+fn random_id() -> String {
+	format!("{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+/// Bidirectional mapping between string peer IDs and engine indices.
+#[derive(Debug, Default)]
+pub struct PeerIndex {
+	ids: Vec<String>,
+	indices: HashMap<String, usize>,
+}
+
+impl PeerIndex {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Return the index of the given peer, assigning a new one if needed.
+	pub fn insert(&mut self, id: &str) -> usize {
+		if let Some(i) = self.indices.get(id) {
+			return *i;
+		}
+		let i = self.ids.len();
+		self.ids.push(id.to_string());
+		self.indices.insert(id.to_string(), i);
+		i
+	}
+
+	pub fn id(&self, index: usize) -> &str {
+		&self.ids[index]
+	}
+
+	pub fn len(&self) -> usize {
+		self.ids.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ids.is_empty()
+	}
+
+	pub fn matrix(&mut self, m: &TrustMatrix) -> SparseMatrix {
+		let entries: Vec<_> = m
+			.entries
+			.iter()
+			.map(|((truster, trustee), value)| (self.insert(truster), self.insert(trustee), *value))
+			.collect();
+		SparseMatrix::from_entries(self.len(), entries)
+	}
+
+	/// Dense form of the given vector.
+	/// Call after all peers have been inserted.
+	pub fn vector(&self, v: &TrustVector) -> Vec<f64> {
+		let mut dense = vec![0.; self.len()];
+		for (id, value) in &v.entries {
+			if let Some(i) = self.indices.get(id) {
+				dense[*i] = *value;
+			}
+		}
+		dense
+	}
+
+	/// Sparse (non-zero entries only) form of the given dense vector.
+	pub fn entries(&self, dense: &[f64]) -> BTreeMap<String, f64> {
+		dense
+			.iter()
+			.enumerate()
+			.filter(|(_, v)| **v != 0.)
+			.map(|(i, v)| (self.id(i).to_string(), *v))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_reject_updates_back_in_time() {
+		let mut store = Store::new();
+		let id = store.create_vector("").unwrap();
+		store.update_vector(&id, &10u32.into(), vec![("a".to_string(), 1.)]).unwrap();
+		store.update_vector(&id, &10u32.into(), vec![("b".to_string(), 1.)]).unwrap();
+
+		let res = store.update_vector(&id, &9u32.into(), vec![("a".to_string(), 0.)]);
+		assert_eq!(
+			res.unwrap_err(),
+			ComputeError::StaleUpdate { current: 10u32.into(), given: 9u32.into() }
+		);
+		assert_eq!(store.vector(&id).unwrap().entries.len(), 2);
+	}
+
+	#[test]
+	fn should_keep_contents_on_stale_replacement() {
+		let path = "cc-store-stale-test-storage";
+		let entries = |entries: &[(&str, f64)]| {
+			entries.iter().map(|(id, value)| (id.to_string(), *value)).collect()
+		};
+		{
+			let mut store = Store::open(path).unwrap();
+			let _ = store.delete_vector("v");
+			store.create_vector("v").unwrap();
+			let vector = TrustVector { timestamp: 10u32.into(), entries: entries(&[("a", 1.)]) };
+			store.set_vector("v", vector).unwrap();
+
+			let stale = TrustVector { timestamp: 9u32.into(), entries: entries(&[("b", 1.)]) };
+			assert_eq!(
+				store.set_vector("v", stale),
+				Err(ComputeError::StaleUpdate { current: 10u32.into(), given: 9u32.into() })
+			);
+			let vector =
+				TrustVector { timestamp: 10u32.into(), entries: entries(&[("c", 2.), ("d", 0.)]) };
+			store.set_vector("v", vector).unwrap();
+		}
+		let store = Store::open(path).unwrap();
+		let v = store.vector("v").unwrap();
+		assert_eq!(v.timestamp, 10u32.into());
+		assert_eq!(v.entries, entries(&[("c", 2.)]));
+	}
+
+	/// Run the triggered re-computes in place, as the services do off the lock.
+	fn run(store: &mut Store, triggered: Vec<TriggeredCompute>) {
+		for compute in triggered {
+			compute.compute().unwrap().save(store, &compute.params).unwrap();
+		}
+	}
+
+	#[test]
+	fn should_run_jobs_at_window_boundaries() {
+		let mut store = Store::new();
+		store.create_matrix("lt").unwrap();
+		store.create_vector("pt").unwrap();
+		store.create_vector("gt").unwrap();
+		store.update_vector("pt", &0u32.into(), vec![("a".to_string(), 1.)]).unwrap();
+		store
+			.set_vector(
+				"gt",
+				TrustVector { timestamp: 9000u32.into(), ..Default::default() },
+			)
+			.unwrap();
+		let params = Params {
+			local_trust_id: "lt".to_string(),
+			pre_trust_id: "pt".to_string(),
+			alpha: Some(0.5),
+			global_trust_id: "gt".to_string(),
+			..Default::default()
+		};
+		let job = Job { params, period: 1000u32.into() };
+		store.create_job(job).unwrap();
+
+		let entry = |trustee: &str| vec![("a".to_string(), trustee.to_string(), 1.)];
+		let triggered = store.update_matrix("lt", &9947u32.into(), entry("b")).unwrap();
+		assert!(triggered.is_empty());
+		assert_eq!(store.vector("gt").unwrap().timestamp, 9000u32.into());
+
+		// Triggered, but the result does not reflect the triggering input.
+		let triggered = store.update_matrix("lt", &10814u32.into(), entry("c")).unwrap();
+		// Not triggered again while the result is pending.
+		assert!(store.update_matrix("lt", &10900u32.into(), vec![]).unwrap().is_empty());
+		run(&mut store, triggered);
+		let gt = store.vector("gt").unwrap();
+		assert_eq!(gt.timestamp, 10000u32.into());
+		assert!(gt.entries.contains_key("b"));
+		assert!(!gt.entries.contains_key("c"));
+
+		let triggered = store.update_matrix("lt", &10999u32.into(), entry("b")).unwrap();
+		assert!(triggered.is_empty());
+		assert_eq!(store.vector("gt").unwrap().timestamp, 10000u32.into());
+
+		let triggered = store.update_vector("pt", &11000u32.into(), vec![]).unwrap();
+		run(&mut store, triggered);
+		let gt = store.vector("gt").unwrap();
+		assert_eq!(gt.timestamp, 11000u32.into());
+		assert!(gt.entries.contains_key("c"));
+	}
+
+	#[test]
+	fn should_reload_persisted_contents() {
+		let path = "cc-store-test-storage";
+		let job = Job { params: Params::default(), period: 1000u32.into() };
+		let job_id = {
+			let mut store = Store::open(path).unwrap();
+			let _ = store.delete_matrix("lt");
+			store.create_matrix("lt").unwrap();
+			let entries = vec![("a".to_string(), "b".to_string(), 1.)];
+			store.update_matrix("lt", &3u32.into(), entries).unwrap();
+			for id in store.jobs.keys().cloned().collect::<Vec<_>>() {
+				store.delete_job(&id).unwrap();
+			}
+			store.create_job(job.clone()).unwrap()
+		};
+		let store = Store::open(path).unwrap();
+		let lt = store.matrix("lt").unwrap();
+		assert_eq!(lt.timestamp, 3u32.into());
+		assert_eq!(lt.entries[&("a".to_string(), "b".to_string())], 1.);
+		assert_eq!(store.jobs.len(), 1);
+		assert_eq!(store.jobs[&job_id], job);
+	}
+}
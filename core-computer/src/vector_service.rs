@@ -0,0 +1,87 @@
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use tonic::{Request, Response, Status};
+use trustvector::service_server::Service;
+use trustvector::{
+	big_to_qwords, get_response, qwords_to_big, CreateRequest, CreateResponse, DeleteRequest,
+	DeleteResponse, Entry, FlushRequest, FlushResponse, GetRequest, GetResponse, Header,
+	UpdateRequest, UpdateResponse,
+};
+
+use crate::computation::run_triggered;
+use crate::store::{SharedStore, Store};
+
+/// trustvector.proto `Service` implementation, backed by the shared store.
+#[derive(Clone, Default)]
+pub struct TrustVectorService {
+	store: SharedStore,
+}
+
+impl TrustVectorService {
+	pub fn new(store: SharedStore) -> Self {
+		Self { store }
+	}
+
+	fn read_store(&self) -> Result<RwLockReadGuard<Store>, Status> {
+		self.store.read().map_err(|_| Status::internal("store lock poisoned"))
+	}
+
+	fn write_store(&self) -> Result<RwLockWriteGuard<Store>, Status> {
+		self.store.write().map_err(|_| Status::internal("store lock poisoned"))
+	}
+}
+
+#[tonic::async_trait]
+impl Service for TrustVectorService {
+	type GetStream = tokio_stream::Iter<std::vec::IntoIter<Result<GetResponse, Status>>>;
+
+	async fn create(
+		&self, request: Request<CreateRequest>,
+	) -> Result<Response<CreateResponse>, Status> {
+		let id = self.write_store()?.create_vector(&request.into_inner().id)?;
+		Ok(Response::new(CreateResponse { id }))
+	}
+
+	async fn get(&self, request: Request<GetRequest>) -> Result<Response<Self::GetStream>, Status> {
+		let id = request.into_inner().id;
+		let store = self.read_store()?;
+		let vector = store.vector(&id)?;
+
+		let header = Header { id: Some(id), timestamp_qwords: big_to_qwords(&vector.timestamp) };
+		let mut parts = vec![Ok(GetResponse { part: Some(get_response::Part::Header(header)) })];
+		for (trustee, value) in &vector.entries {
+			let entry = Entry { trustee: trustee.clone(), value: *value };
+			parts.push(Ok(GetResponse {
+				part: Some(get_response::Part::Entry(entry)),
+			}));
+		}
+		Ok(Response::new(tokio_stream::iter(parts)))
+	}
+
+	async fn update(
+		&self, request: Request<UpdateRequest>,
+	) -> Result<Response<UpdateResponse>, Status> {
+		let UpdateRequest { header, entries } = request.into_inner();
+		let header = header.ok_or_else(|| Status::invalid_argument("missing header"))?;
+		let id = header.id.ok_or_else(|| Status::invalid_argument("missing trust vector ID"))?;
+		let timestamp = qwords_to_big(&header.timestamp_qwords);
+		let entries = entries.into_iter().map(|e| (e.trustee, e.value)).collect();
+		let triggered = self.write_store()?.update_vector(&id, &timestamp, entries)?;
+		run_triggered(&self.store, triggered).await;
+		Ok(Response::new(UpdateResponse {}))
+	}
+
+	async fn flush(
+		&self, request: Request<FlushRequest>,
+	) -> Result<Response<FlushResponse>, Status> {
+		self.write_store()?.flush_vector(&request.into_inner().id)?;
+		Ok(Response::new(FlushResponse {}))
+	}
+
+	async fn delete(
+		&self, request: Request<DeleteRequest>,
+	) -> Result<Response<DeleteResponse>, Status> {
+		self.write_store()?.delete_vector(&request.into_inner().id)?;
+		Ok(Response::new(DeleteResponse {}))
+	}
+}
@@ -3,6 +3,9 @@ use std::env;
 use dotenv::dotenv;
 use tracing::Level;
 
+use mm_spd_vc::error::VcError;
+use mm_spd_vc::schema::SchemaConfig;
+
 use crate::clients::clique::types::EVMIndexerConfig;
 use crate::clients::metamask_connector::types::MetamaskConnectorClientConfig;
 use crate::frontends::api::grpc_server::types::GRPCServerConfig;
@@ -21,6 +24,7 @@ pub struct Config {
 	pub grpc_server_config: GRPCServerConfig,
 	pub lm_db_config: LMDBClientConfig,
 	pub metamask_connector_client_config: MetamaskConnectorClientConfig,
+	pub schema_config: SchemaConfig,
 }
 
 fn parse_level_from_string(level: &str) -> Option<Level> {
@@ -36,7 +40,7 @@ fn parse_level_from_string(level: &str) -> Option<Level> {
 
 // todo break down to entities
 impl Config {
-	pub fn from_env() -> Self {
+	pub fn from_env() -> Result<Self, VcError> {
 		dotenv().ok();
 
 		let rpc_url = env::var("CLIQUE_EVM_INDEXER_RPC_URL")
@@ -68,6 +72,11 @@ impl Config {
 		let metamask_connector_client_config =
 			MetamaskConnectorClientConfig { url: metamask_api_url };
 
+		let schema_config = match env::var("SCHEMA_CONFIG_PATH") {
+			Ok(path) => SchemaConfig::from_file(path)?,
+			Err(_) => SchemaConfig::default(),
+		};
+
 		let lm_db_config = LMDBClientConfig {
 			path: lm_db_path,
 			db_name: "indexer".to_string(),
@@ -75,12 +84,13 @@ impl Config {
 			map_size: 10 * 1024 * 1024,
 		};
 
-		Config {
+		Ok(Config {
 			evm_indexer_config,
 			logger_config,
 			grpc_server_config,
 			lm_db_config,
 			metamask_connector_client_config,
-		}
+			schema_config,
+		})
 	}
 }
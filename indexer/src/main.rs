@@ -22,7 +22,7 @@ pub mod tasks;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-	let config = Config::from_env();
+	let config = Config::from_env()?;
 	let args = cli::Args::parse();
 
 	crate::logger::global::init(config.logger_config.clone())?;
@@ -41,7 +41,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 		let metamask_connector_client_config = config.metamask_connector_client_config;
 		let metamask_connector_client =
 			MetamaskConnectorClient::new(metamask_connector_client_config);
-		Box::new(MetamaskConnectorTask::new(metamask_connector_client))
+		Box::new(MetamaskConnectorTask::new(
+			metamask_connector_client, config.schema_config,
+		))
 	};
 
 	let task_service = TaskService::new(task, Box::new(db.clone()));
@@ -1,5 +1,6 @@
 use digest::Digest;
 use hex;
+use mm_spd_vc::schema::SchemaConfig;
 use mm_spd_vc::OneOrMore;
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -21,18 +22,19 @@ pub struct MetamaskConnectorTaskState {
 
 pub struct MetamaskConnectorTask {
 	client: MetamaskConnectorClient,
+	schema_config: SchemaConfig,
 	state: MetamaskConnectorTaskState,
 }
 
 const DEFAULT_SLEEP_INTERVAL_SECONDS: u64 = 5;
 
 impl MetamaskConnectorTask {
-	pub fn new(client: MetamaskConnectorClient) -> Self {
+	pub fn new(client: MetamaskConnectorClient, schema_config: SchemaConfig) -> Self {
 		let global = TaskGlobalState { is_synced: false, is_finished: false, records_total: 0 };
 		let state = MetamaskConnectorTaskState { from: 1, range: 2000, global };
 
 		debug!("Metamask connector task created");
-		MetamaskConnectorTask { client, state }
+		MetamaskConnectorTask { client, schema_config, state }
 	}
 
 	fn update_state(&mut self, new_state: MetamaskConnectorTaskState) {
@@ -72,16 +74,11 @@ impl TaskTrait for MetamaskConnectorTask {
 						return None;
 					},
 				};
-				let schema_id = if type_.matches("SecurityReportCredential") {
-					0
-				} else if type_.matches("ReviewCredential") {
-					1
-				} else if type_.matches("TrustCredential") {
-					2
-				} else {
+				let Some(schema_id) = self.schema_config.schema_id(&type_) else {
 					info!(?type_, ?r.assertion, "invalid VC type");
 					return None;
 				};
+				let schema_id = schema_id as usize;
 				// info!(?type_, schema_id, "matched VC type");
 
 				let timestamp = match time::PrimitiveDateTime::parse(
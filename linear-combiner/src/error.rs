@@ -1,3 +1,4 @@
+use mm_spd_did::CanonicalizeDidError;
 use rocksdb::Error as RocksDbError;
 use thiserror::Error;
 
@@ -14,6 +15,9 @@ pub enum LcError {
 
 	#[error("ParseError")]
 	ParseError,
+
+	#[error("DidError: {0}")]
+	DidError(CanonicalizeDidError),
 }
 
 impl From<LcError> for tonic::Status {
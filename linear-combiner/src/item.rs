@@ -1,5 +1,7 @@
 use proto_buf::combiner::{LtObject, Mapping};
 
+use crate::error::LcError;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct LtItem {
 	x: u32,
@@ -71,14 +73,21 @@ impl MappingItem {
 		Self { id, did }
 	}
 
-	pub fn from_raw<I: AsRef<[u8]>>(id: I, did: I) -> Self {
-		let mut id_bytes = [0; 4];
-		id_bytes.copy_from_slice(id.as_ref());
+	pub fn from_raw<I: AsRef<[u8]>>(id: I, did: I) -> Result<Self, LcError> {
+		let id_bytes: [u8; 4] = id.as_ref().try_into().map_err(|_| LcError::ParseError)?;
 
 		let id = u32::from_be_bytes(id_bytes);
-		let did = hex::encode(did);
+		let did = String::from_utf8(did.as_ref().to_vec()).map_err(|_| LcError::ParseError)?;
 
-		Self { id, did }
+		Ok(Self { id, did })
+	}
+
+	pub fn id(&self) -> u32 {
+		self.id
+	}
+
+	pub fn did(&self) -> &str {
+		&self.did
 	}
 }
 
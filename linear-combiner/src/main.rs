@@ -8,11 +8,11 @@ use tonic::{transport::Server, Request, Response, Status, Streaming};
 use proto_buf::combiner::linear_combiner_server::{LinearCombiner, LinearCombinerServer};
 use proto_buf::combiner::{LtBatch, LtHistoryBatch, LtObject, Mapping, MappingQuery};
 use proto_buf::common::Void;
-use proto_buf::transformer::TermObject;
+use proto_buf::transformer::{TermKind, TermObject};
 
 use crate::error::LcError;
 use crate::managers::checkpoint::CheckpointManager;
-use crate::managers::index::IndexManager;
+use crate::managers::index::{index_did, IndexManager, DID_VERSION};
 use crate::managers::item::ItemManager;
 use crate::managers::mapping::MappingManager;
 use crate::managers::update::UpdateManager;
@@ -38,6 +38,14 @@ impl LinearCombinerService {
 		)
 		.map_err(LcError::DbError)?;
 		CheckpointManager::init(&db)?;
+		if CheckpointManager::read_did_version(&db)? < DID_VERSION {
+			let (rewritten, merged) = IndexManager::migrate_to_canonical_dids(&db)?;
+			println!(
+				"Migrated {} DIDs to their canonical forms, merging {} items",
+				rewritten, merged
+			);
+			CheckpointManager::write_did_version(&db, DID_VERSION)?;
+		}
 
 		Ok(Self { db_url: db_url.to_string() })
 	}
@@ -63,8 +71,19 @@ impl LinearCombiner for LinearCombinerService {
 
 		let mut terms = Vec::new();
 		let mut stream = request.into_inner();
-		while let Some(term) = stream.message().await? {
-			terms.push(term);
+		while let Some(mut term) = stream.message().await? {
+			// Indexed by canonical DIDs, whichever form of them the terms come with.
+			// Terms of DIDs that can't be indexed are skipped rather than failing the rest.
+			match (index_did(&term.from), index_did(&term.to)) {
+				(Ok(from), Ok(to)) => {
+					term.from = from;
+					term.to = to;
+					terms.push(term);
+				},
+				(Err(e), _) | (_, Err(e)) => {
+					println!("Skipping term from {} to {}: {}", term.from, term.to, e);
+				},
+			}
 		}
 
 		for term in terms {
@@ -99,7 +118,13 @@ impl LinearCombiner for LinearCombinerService {
 				term.weight
 			);
 
-			let value = ItemManager::update_value(&db, key.clone(), term.weight, term.timestamp)?;
+			// Revocations take back the weight of the term they revoke.
+			let weight = if term.kind == TermKind::Revocation as i32 {
+				-term.weight
+			} else {
+				term.weight
+			};
+			let value = ItemManager::update_value(&db, key.clone(), weight, term.timestamp)?;
 			UpdateManager::set_value(&db, key.clone(), value, term.timestamp)?;
 		}
 
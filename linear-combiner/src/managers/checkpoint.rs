@@ -34,6 +34,22 @@ impl CheckpointManager {
 		db.put_cf(&cf, b"participant_count", count.to_be_bytes()).map_err(LcError::DbError)?;
 		Ok(())
 	}
+
+	/// Version of the DID forms the index and the mapping hold, 0 before they were canonicalized.
+	pub fn read_did_version(db: &DB) -> Result<u32, LcError> {
+		let cf = db.cf_handle("checkpoint").ok_or(LcError::NotFoundError)?;
+		let version = db.get_cf(&cf, b"did_version").map_err(LcError::DbError)?;
+		version.map_or(Ok(0), |bytes| {
+			let bytes: [u8; 4] = bytes.try_into().map_err(|_| LcError::ParseError)?;
+			Ok(u32::from_be_bytes(bytes))
+		})
+	}
+
+	pub fn write_did_version(db: &DB, version: u32) -> Result<(), LcError> {
+		let cf = db.cf_handle("checkpoint").ok_or(LcError::NotFoundError)?;
+		db.put_cf(&cf, b"did_version", version.to_be_bytes()).map_err(LcError::DbError)?;
+		Ok(())
+	}
 }
 
 #[cfg(test)]
@@ -53,4 +69,18 @@ mod test {
 		let checkpoint = CheckpointManager::read_checkpoint(&db).unwrap();
 		assert_eq!(checkpoint, 15);
 	}
+
+	#[test]
+	fn should_write_read_did_version() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let path = "lc-rwdv-test-storage";
+		DB::destroy(&opts, path).unwrap();
+		let db = DB::open_cf(&opts, path, vec!["checkpoint"]).unwrap();
+
+		assert_eq!(CheckpointManager::read_did_version(&db).unwrap(), 0);
+		CheckpointManager::write_did_version(&db, 1).unwrap();
+		assert_eq!(CheckpointManager::read_did_version(&db).unwrap(), 1);
+	}
 }
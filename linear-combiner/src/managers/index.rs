@@ -1,6 +1,23 @@
-use rocksdb::DB;
+use std::collections::HashMap;
+
+use mm_spd_did::{canonicalize_did_with, CanonicalizeDidError, CanonicalizeOptions};
+use rocksdb::{IteratorMode, WriteBatch, DB};
 
 use crate::error::LcError;
+use crate::item::MappingItem;
+
+/// Snap versions are kept as they come: whether they are grouped is up to the transformer's policy.
+const DID_OPTIONS: CanonicalizeOptions =
+	CanonicalizeOptions { keep_chain_id: false, keep_snap_version: true };
+
+/// Version of the DID forms [`index_did`] gives, bumped whenever they change,
+/// so that [`IndexManager::migrate_to_canonical_dids`] runs once per change.
+pub const DID_VERSION: u32 = 1;
+
+/// Canonical form of a DID, as the index and the mapping hold it.
+pub fn index_did(did: &str) -> Result<String, CanonicalizeDidError> {
+	canonicalize_did_with(did, &DID_OPTIONS)
+}
 
 #[derive(Debug)]
 pub struct IndexManager;
@@ -23,12 +40,145 @@ impl IndexManager {
 
 		Ok(x)
 	}
+
+	/// Rewrite the DIDs of the index and the mapping into their canonical forms.
+	///
+	/// DIDs that turn out to be the same are merged into the lowest of their indices: their items
+	/// are added up under it, with updates of the merged items and of the emptied ones.
+	/// The other indices keep mapping to the DID, but are no longer resolved to.
+	///
+	/// Returns the numbers of rewritten DIDs and of merged items.
+	pub fn migrate_to_canonical_dids(db: &DB) -> Result<(u32, u32), LcError> {
+		let index_cf = db.cf_handle("index").ok_or(LcError::NotFoundError)?;
+		let mapping_cf = db.cf_handle("mapping").ok_or(LcError::NotFoundError)?;
+
+		let mut mappings = Vec::new();
+		let mut lowest: HashMap<String, u32> = HashMap::new();
+		for item in db.iterator_cf(&mapping_cf, IteratorMode::Start) {
+			let (key, value) = item.map_err(LcError::DbError)?;
+			let mapping = MappingItem::from_raw(key, value)?;
+			let did = index_did(mapping.did()).unwrap_or_else(|e| {
+				println!(
+					"Keeping DID {} of index {}: {}",
+					mapping.did(),
+					mapping.id(),
+					e
+				);
+				mapping.did().to_string()
+			});
+			let index = lowest.entry(did.clone()).or_insert(mapping.id());
+			*index = (*index).min(mapping.id());
+			mappings.push((mapping, did));
+		}
+
+		let mut batch = WriteBatch::default();
+		let mut merged = HashMap::new();
+		let mut num_rewritten = 0;
+		for (mapping, did) in mappings {
+			let index = lowest[&did];
+			if index != mapping.id() {
+				merged.insert(mapping.id(), index);
+			}
+			if did != mapping.did() {
+				batch.delete_cf(&index_cf, mapping.did().as_bytes());
+				batch.put_cf(&mapping_cf, mapping.id().to_be_bytes(), did.as_bytes());
+			} else if read_index(db, &did)? == Some(index) {
+				continue;
+			}
+			batch.put_cf(&index_cf, did.as_bytes(), index.to_be_bytes());
+			num_rewritten += 1;
+		}
+		let num_merged = if merged.is_empty() { 0 } else { merge_items(db, &mut batch, &merged)? };
+		db.write(batch).map_err(LcError::DbError)?;
+
+		Ok((num_rewritten, num_merged))
+	}
+}
+
+fn read_index(db: &DB, did: &str) -> Result<Option<u32>, LcError> {
+	let cf = db.cf_handle("index").ok_or(LcError::NotFoundError)?;
+	let index = db.get_cf(&cf, did.as_bytes()).map_err(LcError::DbError)?;
+	index
+		.map(|index| index.try_into().map(u32::from_be_bytes))
+		.transpose()
+		.map_err(|_| LcError::ParseError)
+}
+
+/// Move the items of the merged indices onto the indices they are merged into, adding them up.
+/// Returns the number of moved items.
+fn merge_items(
+	db: &DB, batch: &mut WriteBatch, merged: &HashMap<u32, u32>,
+) -> Result<u32, LcError> {
+	let item_cf = db.cf_handle("item").ok_or(LcError::NotFoundError)?;
+	let update_cf = db.cf_handle("update").ok_or(LcError::NotFoundError)?;
+	let remap = |index: &[u8]| -> Result<[u8; 4], LcError> {
+		let index = u32::from_be_bytes(index.try_into().map_err(|_| LcError::ParseError)?);
+		Ok(merged.get(&index).copied().unwrap_or(index).to_be_bytes())
+	};
+
+	// Items are keyed by domain, form, x and y, and hold their value and timestamp.
+	let mut sums: HashMap<Vec<u8>, (f32, u64)> = HashMap::new();
+	let mut num_moved = 0;
+	for item in db.iterator_cf(&item_cf, IteratorMode::Start) {
+		let (key, value) = item.map_err(LcError::DbError)?;
+		if key.len() != 16 {
+			return Err(LcError::ParseError);
+		}
+		let mut new_key = key[..8].to_vec();
+		new_key.extend_from_slice(&remap(&key[8..12])?);
+		new_key.extend_from_slice(&remap(&key[12..])?);
+		if *new_key == *key {
+			continue;
+		}
+
+		let (value, timestamp) = item_value(&value)?;
+		let sum = sums.entry(new_key).or_insert((0., 0));
+		*sum = (sum.0 + value, sum.1.max(timestamp));
+		batch.delete_cf(&item_cf, &key);
+		// The emptied item is taken back from the computed trust.
+		batch.put_cf(&update_cf, &key, item_bytes(0., timestamp));
+		num_moved += 1;
+	}
+	for (key, (value, timestamp)) in sums {
+		let existing = db.get_cf(&item_cf, &key).map_err(LcError::DbError)?;
+		let (value, timestamp) = match existing {
+			Some(existing) => {
+				let (existing, existing_timestamp) = item_value(&existing)?;
+				(existing + value, existing_timestamp.max(timestamp))
+			},
+			None => (value, timestamp),
+		};
+		batch.put_cf(&item_cf, &key, item_bytes(value, timestamp));
+		batch.put_cf(&update_cf, &key, item_bytes(value, timestamp));
+	}
+
+	Ok(num_moved)
+}
+
+fn item_value(bytes: &[u8]) -> Result<(f32, u64), LcError> {
+	let value = bytes.get(..4).ok_or(LcError::ParseError)?;
+	let timestamp = bytes.get(4..12).ok_or(LcError::ParseError)?;
+	Ok((
+		f32::from_be_bytes(value.try_into().unwrap()),
+		u64::from_be_bytes(timestamp.try_into().unwrap()),
+	))
+}
+
+fn item_bytes(value: f32, timestamp: u64) -> Vec<u8> {
+	let mut bytes = value.to_be_bytes().to_vec();
+	bytes.extend_from_slice(&timestamp.to_be_bytes());
+	bytes
 }
 
 #[cfg(test)]
 mod test {
 	use rocksdb::{Options, DB};
 
+	use crate::item::LtItem;
+	use crate::managers::item::ItemManager;
+	use crate::managers::mapping::MappingManager;
+	use crate::managers::update::UpdateManager;
+
 	use super::*;
 
 	#[test]
@@ -49,4 +199,83 @@ mod test {
 
 		assert_eq!(i, 15);
 	}
+
+	#[test]
+	fn should_index_snap_versions_apart() {
+		let versions = ["npm:Foo@1.0.0", "npm:foo@1.1.0", "npm:foo"];
+		let dids: Vec<String> = versions.iter().map(|id| index_did(id).unwrap()).collect();
+		assert_eq!(dids, vec!["npm:foo@1.0.0", "npm:foo@1.1.0", "npm:foo"]);
+	}
+
+	#[test]
+	fn should_migrate_to_canonical_dids() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let path = "lc-migrate-test-storage";
+		DB::destroy(&opts, path).unwrap();
+		let db = DB::open_cf(&opts, path, vec!["index", "mapping", "item", "update"]).unwrap();
+
+		let dids = [
+			"did:pkh:eip155:1:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+			"snap://0x90F8BF6A479F320EAD074411A4B0E7944EA8C9C3",
+			"did:pkh:eth:0x90F8BF6A479F320EAD074411A4B0E7944EA8C9C2",
+			"unknown:0xAB",
+		];
+		for (offset, did) in dids.iter().enumerate() {
+			let (index, _) = IndexManager::get_index(&db, did.to_string(), offset as u32).unwrap();
+			MappingManager::write_mapping(&db, index.to_vec(), did.to_string()).unwrap();
+		}
+
+		let prefix = [0u8; 8];
+		let item_key = |x: u32, y: u32| {
+			let mut key = prefix.to_vec();
+			key.extend_from_slice(&LtItem::new(x, y, 0., 0).key_bytes());
+			key
+		};
+		ItemManager::update_value(&db, item_key(0, 1), 1., 1).unwrap();
+		ItemManager::update_value(&db, item_key(2, 1), 2., 3).unwrap();
+		ItemManager::update_value(&db, item_key(3, 2), 4., 2).unwrap();
+
+		assert_eq!(
+			IndexManager::migrate_to_canonical_dids(&db).unwrap(),
+			(2, 2)
+		);
+		assert_eq!(
+			IndexManager::migrate_to_canonical_dids(&db).unwrap(),
+			(0, 0)
+		);
+
+		// The items of the merged index are added up under the index it is merged into.
+		let items = ItemManager::read_window(&db, prefix.to_vec(), (0, 0), (3, 3)).unwrap();
+		assert_eq!(
+			items,
+			vec![LtItem::new(0, 1, 3., 3), LtItem::new(3, 0, 4., 2)]
+		);
+		let mut updates = UpdateManager::read_batch(&db, prefix.to_vec(), 10).unwrap();
+		updates.sort_by_key(|item| item.key_bytes());
+		assert_eq!(
+			updates,
+			vec![
+				LtItem::new(0, 1, 3., 3),
+				LtItem::new(2, 1, 0., 3),
+				LtItem::new(3, 0, 4., 2),
+				LtItem::new(3, 2, 0., 2),
+			]
+		);
+
+		let mappings = MappingManager::read_mappings(&db, 0, 4).unwrap();
+		let mapped: Vec<&str> = mappings.iter().map(|m| m.did()).collect();
+		assert_eq!(
+			mapped,
+			vec![dids[0], "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c3", dids[0], dids[3],]
+		);
+		// The DID indexed twice resolves to its first index.
+		let (index, is_new) = IndexManager::get_index(&db, dids[0].to_string(), 4).unwrap();
+		assert_eq!((u32::from_be_bytes(index), is_new), (0, false));
+		let (index, is_new) = IndexManager::get_index(&db, mapped[1].to_string(), 4).unwrap();
+		assert_eq!((u32::from_be_bytes(index), is_new), (1, false));
+		let (_, is_new) = IndexManager::get_index(&db, dids[2].to_string(), 4).unwrap();
+		assert!(is_new);
+	}
 }
@@ -20,7 +20,8 @@ impl ItemManager {
 		let cf = db.cf_handle("item").ok_or(LcError::NotFoundError)?;
 		let item = Self::get_value(db, &key)?;
 
-		let new_value = item.value + weight;
+		// Revoked weight can't take the value below zero.
+		let new_value = (item.value + weight).max(0.);
 
 		let mut bytes = Vec::new();
 		bytes.extend_from_slice(&new_value.to_be_bytes());
@@ -80,6 +81,24 @@ mod test {
 		assert_eq!(item.value, new_value);
 	}
 
+	#[test]
+	fn should_revoke_item_weight() {
+		let mut opts = Options::default();
+		opts.create_missing_column_families(true);
+		opts.create_if_missing(true);
+		let db = DB::open_cf(&opts, "lc-ri-test-storage", vec!["item"]).unwrap();
+
+		let key = vec![1; 16];
+		ItemManager::update_value(&db, key.clone(), 50., 0).unwrap();
+		ItemManager::update_value(&db, key.clone(), 10., 1).unwrap();
+
+		let value = ItemManager::update_value(&db, key.clone(), -50., 2).unwrap();
+		assert_eq!(value, 10.);
+
+		let value = ItemManager::update_value(&db, key.clone(), -50., 3).unwrap();
+		assert_eq!(value, 0.);
+	}
+
 	#[test]
 	fn should_read_window() {
 		let mut opts = Options::default();
@@ -23,12 +23,9 @@ impl MappingManager {
 		let size = usize::try_from(n).map_err(|_| LcError::ParseError)?;
 		/* mappings */
 		iter.take(size).try_fold(Vec::new(), |mut acc, item| {
-			item.map(|(key, value)| {
-				let mapping = MappingItem::from_raw(key, value);
-				acc.push(mapping);
-				acc
-			})
-			.map_err(LcError::DbError)
+			let (key, value) = item.map_err(LcError::DbError)?;
+			acc.push(MappingItem::from_raw(key, value)?);
+			Ok(acc)
 		})
 	}
 }
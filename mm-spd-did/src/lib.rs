@@ -1,4 +1,19 @@
-use itertools::Itertools;
+pub use peer::{to_checksum_address, PeerDid, CANONICAL_CHAIN_ID};
+pub use snap::{compare_versions, SnapId, SnapSource};
+pub use subject::SubjectDid;
+
+mod peer;
+mod snap;
+mod subject;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CanonicalizeOptions {
+	/// Keep the chain ID of `did:pkh:eip155` DIDs instead of substituting chain ID 1.
+	pub keep_chain_id: bool,
+	/// Keep the version and shasum of npm snap ids, so that the endorsements of each version
+	/// are separate, instead of grouping them all under the package.
+	pub keep_snap_version: bool,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum CanonicalizePeerDidError {
@@ -8,40 +23,64 @@ pub enum CanonicalizePeerDidError {
 	BadPkhMsid(String),
 	#[error("unrecognized DID method {0:?}")]
 	BadDidMethod(String),
+	#[error("bad chain ID {0:?}")]
+	BadChainId(String),
+	#[error("bad address {0:?}")]
+	BadAddress(String),
+	#[error("bad EIP-55 checksum of address {0:?}")]
+	BadChecksum(String),
 	#[error("not a DID")]
 	NotDid,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum CanonicalizeSnapIdError {
+	#[error("unrecognized snap ID scheme of {0:?}")]
+	UnknownScheme(String),
+	#[error("bad npm package name {0:?}")]
+	BadPackageName(String),
+	#[error("bad snap version {0:?}")]
+	BadVersion(String),
+	#[error("bad snap shasum {0:?}")]
+	BadShasum(String),
+	#[error("bad local snap URL {0:?}")]
+	BadUrl(String),
+	#[error("bad legacy snap key {0:?}")]
+	BadKey(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CanonicalizeDidError {
+	#[error("bad peer DID: {0}")]
+	Peer(#[from] CanonicalizePeerDidError),
+	#[error("bad snap ID: {0}")]
+	Snap(#[from] CanonicalizeSnapIdError),
+}
+
+/// Canonicalize a peer DID or a snap subject ID, the form every component identifies them by.
+pub fn canonicalize_did(did: &str) -> Result<String, CanonicalizeDidError> {
+	canonicalize_did_with(did, &CanonicalizeOptions::default())
+}
+
+/// Canonicalize a peer DID or a snap subject ID, keeping what the options ask to keep.
+pub fn canonicalize_did_with(
+	did: &str, options: &CanonicalizeOptions,
+) -> Result<String, CanonicalizeDidError> {
+	Ok(SubjectDid::parse(did, options)?.to_string())
+}
+
+/// Canonicalize a snap id, by lowercasing its package name, URL scheme, or hex key,
+/// and dropping its version unless the options ask to keep it. Also accept legacy `snap://0x…` ids.
+pub fn canonicalize_snap_id(
+	id: &str, options: &CanonicalizeOptions,
+) -> Result<String, CanonicalizeSnapIdError> {
+	Ok(SnapId::parse(id, options)?.to_string())
+}
+
 /// Canonicalize a peer DID, by lowercasing its 0x address portion and substituting chain ID 1.
 /// Also convert legacy ether DIDs into pkh:eip155 DIDs.
 pub fn canonicalize_peer_did(did: &str) -> Result<String, CanonicalizePeerDidError> {
-	if let Some((scheme, method, msid)) = did.splitn(3, ':').collect_tuple() {
-		if scheme == "did" {
-			match method {
-				"pkh" => {
-					let fields = msid.split(':');
-					if let Some(("eth", address)) = fields.to_owned().collect_tuple() {
-						Ok(format!("did:pkh:eip155:1:{}", address.to_lowercase()))
-					} else if let Some((namespace, _chain_id, address)) =
-						fields.to_owned().collect_tuple()
-					{
-						match namespace {
-							"eip155" => Ok(format!("did:pkh:eip155:1:{}", address.to_lowercase())),
-							_ => Err(CanonicalizePeerDidError::BadPkhNamespace(namespace.into())),
-						}
-					} else {
-						Err(CanonicalizePeerDidError::BadPkhMsid(msid.into()))
-					}
-				},
-				"eth" => Ok(format!("did:pkh:eip155:1:{}", msid.to_lowercase())),
-				_ => Err(CanonicalizePeerDidError::BadDidMethod(method.into())),
-			}
-		} else {
-			Err(CanonicalizePeerDidError::NotDid)
-		}
-	} else {
-		Err(CanonicalizePeerDidError::NotDid)
-	}
+	Ok(did.parse::<PeerDid>()?.to_string())
 }
 
 #[cfg(test)]
@@ -131,4 +170,59 @@ mod test {
 			Ok(did) if did == "did:pkh:eip155:1:0x0123456789abcdef0123456789abcdef01234567"
 		);
 	}
+
+	#[test]
+	fn test_canonicalize_did_key() {
+		let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+		assert_matches!(canonicalize_peer_did(did), Ok(canonical) if canonical == did);
+		assert_matches!(
+			canonicalize_peer_did("did:key:6Mkha"),
+			Err(CanonicalizePeerDidError::BadDidMethod(method)) if method == "key"
+		);
+	}
+
+	#[test]
+	fn test_canonicalize_snap_id() {
+		let id = "npm:Foo@1.2.0";
+		assert_matches!(
+			canonicalize_snap_id(id, &CanonicalizeOptions::default()),
+			Ok(id) if id == "npm:foo"
+		);
+		let options = CanonicalizeOptions { keep_snap_version: true, ..Default::default() };
+		assert_matches!(canonicalize_snap_id(id, &options), Ok(id) if id == "npm:foo@1.2.0");
+	}
+
+	#[test]
+	fn test_canonicalize_did_subjects() {
+		assert_matches!(
+			canonicalize_did("did:pkh:eth:0x0123456789ABCDEF0123456789ABCDEF01234567"),
+			Ok(did) if did == "did:pkh:eip155:1:0x0123456789abcdef0123456789abcdef01234567"
+		);
+		assert_matches!(
+			canonicalize_did("snap://0x0123456789ABCDEF0123456789ABCDEF01234567"),
+			Ok(id) if id == "snap://0x0123456789abcdef0123456789abcdef01234567"
+		);
+		assert_matches!(
+			canonicalize_did("npm:@Consensys/Foo"),
+			Ok(id) if id == "npm:@consensys/foo"
+		);
+		assert_matches!(
+			canonicalize_did("local:http://localhost:8080/"),
+			Ok(id) if id == "local:http://localhost:8080"
+		);
+		assert_matches!(
+			canonicalize_did("local:HTTP://LocalHost:8080/"),
+			Ok(id) if id == "local:http://LocalHost:8080"
+		);
+		assert_matches!(
+			canonicalize_did("snap://"),
+			Err(CanonicalizeDidError::Snap(CanonicalizeSnapIdError::BadKey(key))) if key.is_empty()
+		);
+		assert_matches!(
+			canonicalize_did("did:METHOD:MSID"),
+			Err(CanonicalizeDidError::Peer(
+				CanonicalizePeerDidError::BadDidMethod(_)
+			))
+		);
+	}
 }
@@ -0,0 +1,240 @@
+use std::fmt;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use sha3::{Digest, Keccak256};
+
+use crate::{CanonicalizeOptions, CanonicalizePeerDidError};
+
+/// Chain ID peers are identified on, unless their own is kept.
+pub const CANONICAL_CHAIN_ID: u64 = 1;
+
+/// DID of a peer, i.e. of an account that issues credentials.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PeerDid {
+	/// `did:pkh:eip155:<chain ID>:<address>`, also parsed from legacy `did:pkh:eth` and `did:eth`.
+	Pkh { chain_id: u64, address: [u8; 20] },
+	/// `did:key:z<base58btc key>`, canonical as it is, as multibase keys are case-sensitive.
+	Key(String),
+}
+
+impl PeerDid {
+	pub fn parse(
+		did: &str, options: &CanonicalizeOptions,
+	) -> Result<Self, CanonicalizePeerDidError> {
+		let (scheme, method, msid) =
+			did.splitn(3, ':').collect_tuple().ok_or(CanonicalizePeerDidError::NotDid)?;
+		if scheme != "did" {
+			return Err(CanonicalizePeerDidError::NotDid);
+		}
+		match method {
+			"pkh" => {
+				let fields = msid.split(':');
+				if let Some(("eth", address)) = fields.to_owned().collect_tuple() {
+					Self::pkh(CANONICAL_CHAIN_ID, address)
+				} else if let Some((namespace, chain_id, address)) = fields.collect_tuple() {
+					if namespace != "eip155" {
+						return Err(CanonicalizePeerDidError::BadPkhNamespace(namespace.into()));
+					}
+					let chain_id = parse_chain_id(chain_id)?;
+					let chain_id =
+						if options.keep_chain_id { chain_id } else { CANONICAL_CHAIN_ID };
+					Self::pkh(chain_id, address)
+				} else {
+					Err(CanonicalizePeerDidError::BadPkhMsid(msid.into()))
+				}
+			},
+			"eth" => Self::pkh(CANONICAL_CHAIN_ID, msid),
+			"key" if msid.starts_with('z') => Ok(Self::Key(did.to_string())),
+			_ => Err(CanonicalizePeerDidError::BadDidMethod(method.into())),
+		}
+	}
+
+	fn pkh(chain_id: u64, address: &str) -> Result<Self, CanonicalizePeerDidError> {
+		Ok(Self::Pkh { chain_id, address: parse_address(address)? })
+	}
+
+	/// DID method, e.g. `pkh`.
+	pub fn method(&self) -> &'static str {
+		match self {
+			Self::Pkh { .. } => "pkh",
+			Self::Key(_) => "key",
+		}
+	}
+
+	/// CAIP-2 namespace of the chain of the account, e.g. `eip155`.
+	pub fn namespace(&self) -> Option<&'static str> {
+		match self {
+			Self::Pkh { .. } => Some("eip155"),
+			Self::Key(_) => None,
+		}
+	}
+
+	pub fn chain_id(&self) -> Option<u64> {
+		match self {
+			Self::Pkh { chain_id, .. } => Some(*chain_id),
+			Self::Key(_) => None,
+		}
+	}
+
+	pub fn address(&self) -> Option<&[u8; 20]> {
+		match self {
+			Self::Pkh { address, .. } => Some(address),
+			Self::Key(_) => None,
+		}
+	}
+
+	/// EIP-55 mixed-case checksummed address, e.g. `0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed`.
+	pub fn checksum_address(&self) -> Option<String> {
+		self.address().map(to_checksum_address)
+	}
+}
+
+impl FromStr for PeerDid {
+	type Err = CanonicalizePeerDidError;
+
+	/// Parse with the default options, i.e. onto chain ID 1.
+	fn from_str(did: &str) -> Result<Self, Self::Err> {
+		Self::parse(did, &CanonicalizeOptions::default())
+	}
+}
+
+/// The canonical form, with the address in lowercase.
+impl fmt::Display for PeerDid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Pkh { chain_id, address } => {
+				write!(f, "did:pkh:eip155:{}:0x", chain_id)?;
+				address.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+			},
+			Self::Key(did) => f.write_str(did),
+		}
+	}
+}
+
+/// EIP-55 checksummed form of the address.
+pub fn to_checksum_address(address: &[u8; 20]) -> String {
+	let lower: String = address.iter().map(|byte| format!("{:02x}", byte)).collect();
+	let hash = Keccak256::digest(lower.as_bytes());
+	let checksummed: String = lower
+		.chars()
+		.enumerate()
+		.map(|(i, c)| {
+			let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0xf;
+			if nibble >= 8 {
+				c.to_ascii_uppercase()
+			} else {
+				c
+			}
+		})
+		.collect();
+	format!("0x{}", checksummed)
+}
+
+/// Parse a 0x-prefixed address, checking its EIP-55 checksum if it's in mixed case.
+fn parse_address(value: &str) -> Result<[u8; 20], CanonicalizePeerDidError> {
+	let bad_address = || CanonicalizePeerDidError::BadAddress(value.into());
+	let hex = value.strip_prefix("0x").ok_or_else(bad_address)?;
+	if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+		return Err(bad_address());
+	}
+	let mut address = [0; 20];
+	for (i, byte) in address.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| bad_address())?;
+	}
+
+	let is_mixed_case =
+		hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+	if is_mixed_case && to_checksum_address(&address) != value {
+		return Err(CanonicalizePeerDidError::BadChecksum(value.into()));
+	}
+	Ok(address)
+}
+
+/// Decimal CAIP-2 reference of an eip155 chain.
+fn parse_chain_id(value: &str) -> Result<u64, CanonicalizePeerDidError> {
+	let bad_chain_id = || CanonicalizePeerDidError::BadChainId(value.into());
+	if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+		return Err(bad_chain_id());
+	}
+	value.parse().map_err(|_| bad_chain_id())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_checksum_eip55_examples() {
+		for checksummed in [
+			"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+			"0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+			"0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+			"0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+		] {
+			let did: PeerDid = format!("did:pkh:eth:{}", checksummed).parse().unwrap();
+			assert_eq!(did.checksum_address().unwrap(), checksummed);
+			let lower = format!("did:pkh:eip155:1:{}", checksummed.to_lowercase());
+			assert_eq!(did.to_string(), lower);
+		}
+	}
+
+	#[test]
+	fn test_reject_bad_checksum() {
+		assert_matches!(
+			"did:pkh:eth:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD".parse::<PeerDid>(),
+			Err(CanonicalizePeerDidError::BadChecksum(_))
+		);
+		// Single-case addresses carry no checksum.
+		assert!("did:pkh:eth:0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"
+			.parse::<PeerDid>()
+			.is_ok());
+	}
+
+	#[test]
+	fn test_reject_bad_address() {
+		for address in [
+			"5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+			"0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea",
+			"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaedaa",
+			"0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaeg",
+		] {
+			assert_matches!(
+				format!("did:pkh:eip155:1:{}", address).parse::<PeerDid>(),
+				Err(CanonicalizePeerDidError::BadAddress(a)) if a == address
+			);
+		}
+	}
+
+	#[test]
+	fn test_keep_chain_id() {
+		let did = "did:pkh:eip155:137:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+		let keep = CanonicalizeOptions { keep_chain_id: true, ..Default::default() };
+		let peer = PeerDid::parse(did, &keep).unwrap();
+		assert_eq!(peer.method(), "pkh");
+		assert_eq!(peer.namespace(), Some("eip155"));
+		assert_eq!(peer.chain_id(), Some(137));
+		assert_eq!(
+			peer.to_string(),
+			"did:pkh:eip155:137:0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+		);
+		assert_eq!(did.parse::<PeerDid>().unwrap().chain_id(), Some(1));
+
+		assert_matches!(
+			PeerDid::parse("did:pkh:eip155:-1:0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed", &keep),
+			Err(CanonicalizePeerDidError::BadChainId(id)) if id == "-1"
+		);
+	}
+
+	#[test]
+	fn test_did_key_accessors() {
+		let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+		let peer: PeerDid = did.parse().unwrap();
+		assert_eq!(peer, PeerDid::Key(did.to_string()));
+		assert_eq!(
+			(peer.method(), peer.namespace(), peer.chain_id()),
+			("key", None, None)
+		);
+		assert_eq!(peer.checksum_address(), None);
+	}
+}
@@ -0,0 +1,424 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{CanonicalizeOptions, CanonicalizeSnapIdError};
+
+/// Where a snap is installed from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SnapSource {
+	/// npm package name, e.g. `@consensys/foo`.
+	Npm(String),
+	/// URL of a snap served locally, e.g. `http://localhost:8080`.
+	Local(String),
+	/// Hex key of a legacy `snap://0x…` id, with its `0x` prefix.
+	Legacy(String),
+}
+
+/// MetaMask snap id, e.g. `npm:@consensys/foo@1.2.0#<shasum>`.
+///
+/// Only npm snaps are versioned: by an optional semantic version and an optional shasum,
+/// i.e. the base64 SHA-256 of the snap's bundle, as in its manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SnapId {
+	source: SnapSource,
+	version: Option<String>,
+	shasum: Option<String>,
+}
+
+impl SnapId {
+	/// Parse the id; its version and shasum are dropped unless the options keep them,
+	/// so that all the versions of a snap are one subject.
+	pub fn parse(id: &str, options: &CanonicalizeOptions) -> Result<Self, CanonicalizeSnapIdError> {
+		let snap_id = if let Some(spec) = id.strip_prefix("npm:") {
+			parse_npm(spec)?
+		} else if let Some(url) = id.strip_prefix("local:") {
+			Self::unversioned(SnapSource::Local(parse_local(url)?))
+		} else if let Some(key) = id.strip_prefix("snap://") {
+			Self::unversioned(SnapSource::Legacy(parse_legacy(key)?))
+		} else {
+			return Err(CanonicalizeSnapIdError::UnknownScheme(id.into()));
+		};
+
+		if options.keep_snap_version {
+			Ok(snap_id)
+		} else {
+			Ok(snap_id.without_version())
+		}
+	}
+
+	fn unversioned(source: SnapSource) -> Self {
+		Self { source, version: None, shasum: None }
+	}
+
+	pub fn source(&self) -> &SnapSource {
+		&self.source
+	}
+
+	pub fn version(&self) -> Option<&str> {
+		self.version.as_deref()
+	}
+
+	pub fn shasum(&self) -> Option<&str> {
+		self.shasum.as_deref()
+	}
+
+	/// The id of the snap regardless of its version, which its versions are grouped by.
+	pub fn without_version(&self) -> Self {
+		Self::unversioned(self.source.clone())
+	}
+
+	pub fn is_versioned(&self) -> bool {
+		self.version.is_some() || self.shasum.is_some()
+	}
+
+	/// Whether the version is a pre-release, e.g. `2.0.0-beta.1`.
+	pub fn is_pre_release(&self) -> bool {
+		self.version.as_deref().map_or(false, |version| split_version(version).1.is_some())
+	}
+
+	/// Order of the versions of the snap by semantic version precedence;
+	/// ids without a version come first, and the shasum is disregarded.
+	pub fn cmp_version(&self, other: &Self) -> Ordering {
+		match (&self.version, &other.version) {
+			(Some(a), Some(b)) => compare_versions(a, b),
+			(a, b) => a.is_some().cmp(&b.is_some()),
+		}
+	}
+}
+
+/// Semantic version precedence: the numeric core, then any pre-release identifiers,
+/// which precede the release. Build metadata is disregarded.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+	let (a_core, a_pre_release) = split_version(a);
+	let (b_core, b_pre_release) = split_version(b);
+
+	a_core.cmp(&b_core).then_with(|| match (a_pre_release, b_pre_release) {
+		(None, None) => Ordering::Equal,
+		(None, Some(_)) => Ordering::Greater,
+		(Some(_), None) => Ordering::Less,
+		(Some(a), Some(b)) => {
+			let mut a_ids = a.split('.');
+			let mut b_ids = b.split('.');
+			loop {
+				let ordering = match (a_ids.next(), b_ids.next()) {
+					(None, None) => return Ordering::Equal,
+					(None, Some(_)) => return Ordering::Less,
+					(Some(_), None) => return Ordering::Greater,
+					// Numeric identifiers precede alphanumeric ones.
+					(Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+						(Ok(a), Ok(b)) => a.cmp(&b),
+						(Ok(_), Err(_)) => Ordering::Less,
+						(Err(_), Ok(_)) => Ordering::Greater,
+						(Err(_), Err(_)) => a.cmp(b),
+					},
+				};
+				if ordering != Ordering::Equal {
+					return ordering;
+				}
+			}
+		},
+	})
+}
+
+impl FromStr for SnapId {
+	type Err = CanonicalizeSnapIdError;
+
+	/// Parse with the default options, i.e. without the version.
+	fn from_str(id: &str) -> Result<Self, Self::Err> {
+		Self::parse(id, &CanonicalizeOptions::default())
+	}
+}
+
+/// The canonical form: lowercase package name, URL scheme, or hex key.
+impl fmt::Display for SnapId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.source {
+			SnapSource::Npm(package) => write!(f, "npm:{}", package)?,
+			SnapSource::Local(url) => write!(f, "local:{}", url)?,
+			SnapSource::Legacy(key) => write!(f, "snap://{}", key)?,
+		}
+		if let Some(version) = &self.version {
+			write!(f, "@{}", version)?;
+		}
+		if let Some(shasum) = &self.shasum {
+			write!(f, "#{}", shasum)?;
+		}
+		Ok(())
+	}
+}
+
+/// Numeric core and pre-release identifiers of a version.
+fn split_version(version: &str) -> (Vec<u64>, Option<&str>) {
+	let version = version.split('+').next().unwrap_or_default();
+	let (core, pre_release) = match version.split_once('-') {
+		Some((core, pre_release)) => (core, Some(pre_release)),
+		None => (version, None),
+	};
+	let core = core.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+	(core, pre_release)
+}
+
+/// `[@<scope>/]<name>[@<version>][#<shasum>]`
+fn parse_npm(spec: &str) -> Result<SnapId, CanonicalizeSnapIdError> {
+	let (spec, shasum) = match spec.split_once('#') {
+		Some((spec, shasum)) => (spec, Some(parse_shasum(shasum)?)),
+		None => (spec, None),
+	};
+	// The version follows the last `@` but the one of the scope.
+	let (package, version) = match spec.rfind('@') {
+		Some(i) if i > 0 => (&spec[..i], Some(parse_version(&spec[i + 1..])?)),
+		_ => (spec, None),
+	};
+
+	let package = package.to_lowercase();
+	let bad_package = || CanonicalizeSnapIdError::BadPackageName(package.clone());
+	let name = match package.strip_prefix('@') {
+		Some(scoped) => {
+			let (scope, name) = scoped.split_once('/').ok_or_else(bad_package)?;
+			if !is_npm_name(scope) {
+				return Err(bad_package());
+			}
+			name
+		},
+		None => &package,
+	};
+	if !is_npm_name(name) {
+		return Err(bad_package());
+	}
+
+	Ok(SnapId { source: SnapSource::Npm(package.clone()), version, shasum })
+}
+
+/// Name of an npm package or scope, as allowed for new packages.
+fn is_npm_name(name: &str) -> bool {
+	!name.is_empty()
+		&& name.len() <= 214
+		&& !name.starts_with('.')
+		&& !name.starts_with('_')
+		&& name.chars().all(|c| {
+			c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '.' | '_' | '~')
+		})
+}
+
+/// Semantic version, e.g. `1.2.0` or `1.2.0-beta.1+build.5`.
+fn parse_version(version: &str) -> Result<String, CanonicalizeSnapIdError> {
+	let bad_version = || CanonicalizeSnapIdError::BadVersion(version.into());
+	let (version_core, build) = match version.split_once('+') {
+		Some((core, build)) => (core, Some(build)),
+		None => (version, None),
+	};
+	let (core, pre_release) = match version_core.split_once('-') {
+		Some((core, pre_release)) => (core, Some(pre_release)),
+		None => (version_core, None),
+	};
+
+	let is_number = |part: &str| {
+		!part.is_empty()
+			&& part.chars().all(|c| c.is_ascii_digit())
+			&& (part == "0" || !part.starts_with('0'))
+	};
+	let is_identifier = |part: &str| {
+		!part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+	};
+	let core_parts: Vec<&str> = core.split('.').collect();
+	if core_parts.len() != 3 || !core_parts.iter().all(|part| is_number(part)) {
+		return Err(bad_version());
+	}
+	for identifiers in [pre_release, build].into_iter().flatten() {
+		if !identifiers.split('.').all(is_identifier) {
+			return Err(bad_version());
+		}
+	}
+
+	Ok(version.to_string())
+}
+
+/// Base64 SHA-256 digest.
+fn parse_shasum(shasum: &str) -> Result<String, CanonicalizeSnapIdError> {
+	let is_base64 = shasum.len() == 44
+		&& shasum.ends_with('=')
+		&& shasum[..43].chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/');
+	if !is_base64 {
+		return Err(CanonicalizeSnapIdError::BadShasum(shasum.into()));
+	}
+	Ok(shasum.to_string())
+}
+
+/// `http(s)://<host>[:<port>][/<path>]`, with the scheme lowercased.
+/// The host keeps its case, as signed messages encode the URL as it is.
+fn parse_local(url: &str) -> Result<String, CanonicalizeSnapIdError> {
+	let bad_url = || CanonicalizeSnapIdError::BadUrl(url.into());
+	let (scheme, rest) = url.split_once("://").ok_or_else(bad_url)?;
+	let scheme = scheme.to_lowercase();
+	if scheme != "http" && scheme != "https" {
+		return Err(bad_url());
+	}
+	let rest = rest.trim_end_matches('/');
+	let (host, path) = match rest.find('/') {
+		Some(i) => rest.split_at(i),
+		None => (rest, ""),
+	};
+	if host.is_empty() || url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+		return Err(bad_url());
+	}
+
+	Ok(format!("{}://{}{}", scheme, host, path))
+}
+
+/// `0x<hex>`, lowercased.
+fn parse_legacy(key: &str) -> Result<String, CanonicalizeSnapIdError> {
+	let is_hex = key.strip_prefix("0x").map_or(false, |hex| {
+		!hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+	});
+	if !is_hex {
+		return Err(CanonicalizeSnapIdError::BadKey(key.into()));
+	}
+	Ok(key.to_lowercase())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const SHASUM: &str = "rNyfINgNh161cBmUop+F7xlE+xEJQuIlkbnL1PZ0WcI=";
+
+	#[test]
+	fn test_parse_npm_snap_ids() {
+		let keep = CanonicalizeOptions { keep_snap_version: true, ..Default::default() };
+		let id = format!("npm:@Consensys/Foo@1.2.0-beta.1#{}", SHASUM);
+		let snap = SnapId::parse(&id, &keep).unwrap();
+		assert_eq!(
+			snap.source(),
+			&SnapSource::Npm("@consensys/foo".to_string())
+		);
+		assert_eq!(snap.version(), Some("1.2.0-beta.1"));
+		assert_eq!(snap.shasum(), Some(SHASUM));
+		assert_eq!(
+			snap.to_string(),
+			format!("npm:@consensys/foo@1.2.0-beta.1#{}", SHASUM)
+		);
+
+		let snap = SnapId::parse("npm:foo-snap@0.1.0", &keep).unwrap();
+		assert_eq!(snap.to_string(), "npm:foo-snap@0.1.0");
+		assert_eq!(snap.without_version().to_string(), "npm:foo-snap");
+		assert!(snap.is_versioned() && !snap.without_version().is_versioned());
+	}
+
+	#[test]
+	fn test_group_versions_by_default() {
+		let versions = ["npm:foo@1.0.0", "npm:foo@1.1.0", "npm:Foo"];
+		let ids: Vec<String> =
+			versions.iter().map(|id| id.parse::<SnapId>().unwrap().to_string()).collect();
+		assert_eq!(ids, vec!["npm:foo"; 3]);
+	}
+
+	#[test]
+	fn test_compare_versions() {
+		let ascending = [
+			"1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2",
+			"1.0.0-beta.11", "1.0.0-rc.1", "1.0.0", "1.2.0", "1.10.0", "2.0.0",
+		];
+		for pair in ascending.windows(2) {
+			assert_eq!(
+				compare_versions(pair[0], pair[1]),
+				Ordering::Less,
+				"{:?}",
+				pair
+			);
+			assert_eq!(
+				compare_versions(pair[1], pair[0]),
+				Ordering::Greater,
+				"{:?}",
+				pair
+			);
+		}
+		assert_eq!(
+			compare_versions("1.0.0+build.1", "1.0.0+build.2"),
+			Ordering::Equal
+		);
+
+		let keep = CanonicalizeOptions { keep_snap_version: true, ..Default::default() };
+		let parse = |id| SnapId::parse(id, &keep).unwrap();
+		let shasum = format!("npm:foo#{}", SHASUM);
+		assert_eq!(
+			parse("npm:foo").cmp_version(&parse(&shasum)),
+			Ordering::Equal
+		);
+		assert_eq!(
+			parse("npm:foo").cmp_version(&parse("npm:foo@0.0.1")),
+			Ordering::Less
+		);
+		assert!(parse("npm:foo@1.0.0-rc.1+build.1").is_pre_release());
+		assert!(
+			!parse("npm:foo@1.0.0+build-1").is_pre_release() && !parse("npm:foo").is_pre_release()
+		);
+	}
+
+	#[test]
+	fn test_parse_local_and_legacy_snap_ids() {
+		let snap: SnapId = "local:HTTP://LocalHost:8080/Path/".parse().unwrap();
+		assert_eq!(snap.to_string(), "local:http://LocalHost:8080/Path");
+
+		let snap: SnapId = "snap://0x90F8BF6A479F320EAD074411A4B0E7944EA8C9C2".parse().unwrap();
+		assert_eq!(
+			snap.source(),
+			&SnapSource::Legacy("0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2".to_string())
+		);
+	}
+
+	#[test]
+	fn test_reject_bad_snap_ids() {
+		let cases = [
+			(
+				"npm:",
+				CanonicalizeSnapIdError::BadPackageName(String::new()),
+			),
+			(
+				"npm:@scope",
+				CanonicalizeSnapIdError::BadPackageName("@scope".into()),
+			),
+			(
+				"npm:foo bar",
+				CanonicalizeSnapIdError::BadPackageName("foo bar".into()),
+			),
+			(
+				"npm:foo@1.0",
+				CanonicalizeSnapIdError::BadVersion("1.0".into()),
+			),
+			(
+				"npm:foo@01.0.0",
+				CanonicalizeSnapIdError::BadVersion("01.0.0".into()),
+			),
+			(
+				"npm:foo#abc",
+				CanonicalizeSnapIdError::BadShasum("abc".into()),
+			),
+			(
+				"local:ftp://host",
+				CanonicalizeSnapIdError::BadUrl("ftp://host".into()),
+			),
+			(
+				"local:http://",
+				CanonicalizeSnapIdError::BadUrl("http://".into()),
+			),
+			("snap://", CanonicalizeSnapIdError::BadKey(String::new())),
+			(
+				"snap://0xzz",
+				CanonicalizeSnapIdError::BadKey("0xzz".into()),
+			),
+			(
+				"ipfs:Qm",
+				CanonicalizeSnapIdError::UnknownScheme("ipfs:Qm".into()),
+			),
+		];
+		for (id, error) in cases {
+			assert_eq!(
+				id.parse::<SnapId>().unwrap_err().to_string(),
+				error.to_string(),
+				"{}",
+				id
+			);
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::peer::PeerDid;
+use crate::snap::SnapId;
+use crate::{CanonicalizeDidError, CanonicalizeOptions};
+
+/// Subject of an opinion: either a peer or a snap.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SubjectDid {
+	Peer(PeerDid),
+	Snap(SnapId),
+}
+
+impl SubjectDid {
+	pub fn parse(did: &str, options: &CanonicalizeOptions) -> Result<Self, CanonicalizeDidError> {
+		if did.starts_with("did:") {
+			Ok(Self::Peer(PeerDid::parse(did, options)?))
+		} else {
+			Ok(Self::Snap(SnapId::parse(did, options)?))
+		}
+	}
+
+	pub fn as_peer(&self) -> Option<&PeerDid> {
+		match self {
+			Self::Peer(peer) => Some(peer),
+			Self::Snap(_) => None,
+		}
+	}
+
+	pub fn as_snap(&self) -> Option<&SnapId> {
+		match self {
+			Self::Peer(_) => None,
+			Self::Snap(snap) => Some(snap),
+		}
+	}
+
+	pub fn is_snap(&self) -> bool {
+		matches!(self, Self::Snap(_))
+	}
+}
+
+impl FromStr for SubjectDid {
+	type Err = CanonicalizeDidError;
+
+	fn from_str(did: &str) -> Result<Self, Self::Err> {
+		Self::parse(did, &CanonicalizeOptions::default())
+	}
+}
+
+impl From<PeerDid> for SubjectDid {
+	fn from(value: PeerDid) -> Self {
+		Self::Peer(value)
+	}
+}
+
+impl fmt::Display for SubjectDid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Peer(peer) => peer.fmt(f),
+			Self::Snap(snap) => snap.fmt(f),
+		}
+	}
+}
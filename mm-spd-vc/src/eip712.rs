@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha3::{Digest, Keccak256};
+
+use crate::error::VcError;
+
+/// Name of the type of the EIP-712 domain.
+pub const DOMAIN_TYPE: &str = "EIP712Domain";
+/// Name of the EIP-712 domain the attestations are signed in.
+pub const DOMAIN_NAME: &str = "EigenTrust Attestation";
+/// Version of the EIP-712 domain.
+pub const DOMAIN_VERSION: &str = "1";
+
+/// The attestation domain, without chain or contract, as the attestations are signed off-chain.
+pub fn attestation_domain() -> Value {
+	json!({ "name": DOMAIN_NAME, "version": DOMAIN_VERSION })
+}
+
+/// Member of an EIP-712 struct type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Member {
+	pub name: String,
+	#[serde(rename = "type")]
+	pub type_: String,
+}
+
+/// EIP-712 struct types by name.
+pub type Types = BTreeMap<String, Vec<Member>>;
+
+/// EIP-712 typed data, as in `eth_signTypedData_v4`, with the message as a JSON value.
+#[derive(Debug, Clone)]
+pub struct TypedData {
+	pub types: Types,
+	pub primary_type: String,
+	pub domain: Value,
+	pub message: Value,
+}
+
+impl TypedData {
+	/// Digest of the typed data, i.e. what is signed.
+	///
+	/// The type of the domain is derived from its members if it isn't among the types.
+	pub fn digest(&self) -> Result<[u8; 32], VcError> {
+		let mut types = self.types.clone();
+		if !types.contains_key(DOMAIN_TYPE) {
+			types.insert(DOMAIN_TYPE.to_string(), domain_type(&self.domain)?);
+		}
+
+		let mut bytes = vec![0x19, 0x01];
+		bytes.extend_from_slice(&hash_struct(&types, DOMAIN_TYPE, &self.domain)?);
+		bytes.extend_from_slice(&hash_struct(&types, &self.primary_type, &self.message)?);
+		Ok(keccak(&bytes))
+	}
+}
+
+/// Type of the domain with the standard members present in it, in the standard order.
+fn domain_type(domain: &Value) -> Result<Vec<Member>, VcError> {
+	let fields = domain.as_object().ok_or_else(|| invalid("domain is not an object"))?;
+	let standard = [
+		("name", "string"),
+		("version", "string"),
+		("chainId", "uint256"),
+		("verifyingContract", "address"),
+		("salt", "bytes32"),
+	];
+	let members = standard
+		.iter()
+		.filter(|(name, _)| fields.contains_key(*name))
+		.map(|(name, type_)| Member { name: name.to_string(), type_: type_.to_string() })
+		.collect();
+	Ok(members)
+}
+
+/// E.g. `Mail(Person from,Person to,string contents)Person(string name,address wallet)`.
+pub fn encode_type(types: &Types, primary_type: &str) -> Result<String, VcError> {
+	let mut dependencies = BTreeSet::new();
+	collect_dependencies(types, primary_type, &mut dependencies)?;
+	dependencies.remove(primary_type);
+
+	let mut encoded = String::new();
+	for name in std::iter::once(primary_type).chain(dependencies.iter().map(String::as_str)) {
+		let members: Vec<String> =
+			types[name].iter().map(|m| format!("{} {}", m.type_, m.name)).collect();
+		encoded.push_str(&format!("{}({})", name, members.join(",")));
+	}
+	Ok(encoded)
+}
+
+fn collect_dependencies(
+	types: &Types, type_: &str, dependencies: &mut BTreeSet<String>,
+) -> Result<(), VcError> {
+	let members = types.get(type_).ok_or_else(|| invalid(&format!("unknown type {}", type_)))?;
+	if !dependencies.insert(type_.to_string()) {
+		return Ok(());
+	}
+	for member in members {
+		let base = member.type_.split('[').next().unwrap_or_default();
+		if types.contains_key(base) {
+			collect_dependencies(types, base, dependencies)?;
+		}
+	}
+	Ok(())
+}
+
+pub fn hash_struct(types: &Types, type_: &str, value: &Value) -> Result<[u8; 32], VcError> {
+	let fields =
+		value.as_object().ok_or_else(|| invalid(&format!("{} is not an object", type_)))?;
+	let members = types.get(type_).ok_or_else(|| invalid(&format!("unknown type {}", type_)))?;
+	// Members missing from the type would not be signed.
+	if let Some(name) = fields.keys().find(|name| !members.iter().any(|m| &m.name == *name)) {
+		return Err(invalid(&format!("{} is not a member of {}", name, type_)));
+	}
+
+	let mut bytes = keccak(encode_type(types, type_)?.as_bytes()).to_vec();
+	for member in members {
+		let value = field(fields, &member.name)?;
+		bytes.extend_from_slice(&encode_value(types, &member.type_, value)?);
+	}
+	Ok(keccak(&bytes))
+}
+
+fn field<'a>(fields: &'a Map<String, Value>, name: &str) -> Result<&'a Value, VcError> {
+	fields.get(name).ok_or_else(|| invalid(&format!("missing member {}", name)))
+}
+
+fn encode_value(types: &Types, type_: &str, value: &Value) -> Result<[u8; 32], VcError> {
+	if let Some(element) = type_.strip_suffix(']') {
+		let element = element.rsplit_once('[').map(|(element, _)| element).unwrap_or(element);
+		let items =
+			value.as_array().ok_or_else(|| invalid(&format!("{} is not an array", type_)))?;
+		let mut bytes = Vec::new();
+		for item in items {
+			bytes.extend_from_slice(&encode_value(types, element, item)?);
+		}
+		return Ok(keccak(&bytes));
+	}
+	if types.contains_key(type_) {
+		return hash_struct(types, type_, value);
+	}
+
+	let mut word = [0; 32];
+	match type_ {
+		"string" => word = keccak(as_str(value, type_)?.as_bytes()),
+		"bytes" => word = keccak(&decode_hex(as_str(value, type_)?)?),
+		"bool" => word[31] = value.as_bool().ok_or_else(|| invalid("bool"))? as u8,
+		"address" => {
+			let bytes = decode_hex(as_str(value, type_)?)?;
+			if bytes.len() != 20 {
+				return Err(invalid("address"));
+			}
+			word[12..].copy_from_slice(&bytes);
+		},
+		_ if type_.starts_with("bytes") => {
+			let bytes = decode_hex(as_str(value, type_)?)?;
+			if bytes.len() > 32 {
+				return Err(invalid(type_));
+			}
+			word[..bytes.len()].copy_from_slice(&bytes);
+		},
+		_ if type_.starts_with("uint") || type_.starts_with("int") => {
+			let n = integer(value).ok_or_else(|| invalid(type_))?;
+			if n < 0 && type_.starts_with('u') {
+				return Err(invalid(type_));
+			}
+			// Two's complement, sign-extended to 256 bits.
+			word = if n < 0 { [0xff; 32] } else { [0; 32] };
+			word[16..].copy_from_slice(&n.to_be_bytes());
+		},
+		_ => return Err(invalid(&format!("unsupported type {}", type_))),
+	}
+	Ok(word)
+}
+
+/// Integer from a JSON number or a decimal or 0x-prefixed hex string.
+fn integer(value: &Value) -> Option<i128> {
+	match value {
+		Value::Number(n) => n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from)),
+		Value::String(s) => match s.strip_prefix("0x") {
+			Some(hex) => i128::from_str_radix(hex, 16).ok(),
+			None => s.parse().ok(),
+		},
+		_ => None,
+	}
+}
+
+fn as_str<'a>(value: &'a Value, type_: &str) -> Result<&'a str, VcError> {
+	value.as_str().ok_or_else(|| invalid(&format!("{} is not a string", type_)))
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, VcError> {
+	hex::decode(value.trim_start_matches("0x")).map_err(VcError::HexError)
+}
+
+fn invalid(reason: &str) -> VcError {
+	VcError::InvalidProof(format!("EIP-712: {}", reason))
+}
+
+fn keccak(bytes: &[u8]) -> [u8; 32] {
+	Keccak256::digest(bytes).into()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// The `Mail` example of the EIP.
+	fn mail() -> TypedData {
+		let types = json!({
+			"EIP712Domain": [
+				{ "name": "name", "type": "string" },
+				{ "name": "version", "type": "string" },
+				{ "name": "chainId", "type": "uint256" },
+				{ "name": "verifyingContract", "type": "address" }
+			],
+			"Person": [
+				{ "name": "name", "type": "string" },
+				{ "name": "wallet", "type": "address" }
+			],
+			"Mail": [
+				{ "name": "from", "type": "Person" },
+				{ "name": "to", "type": "Person" },
+				{ "name": "contents", "type": "string" }
+			]
+		});
+		TypedData {
+			types: serde_json::from_value(types).unwrap(),
+			primary_type: "Mail".to_string(),
+			domain: json!({
+				"name": "Ether Mail",
+				"version": "1",
+				"chainId": 1,
+				"verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+			}),
+			message: json!({
+				"from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+				"to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+				"contents": "Hello, Bob!"
+			}),
+		}
+	}
+
+	#[test]
+	fn should_hash_eip712_example() {
+		let mail = mail();
+		assert_eq!(
+			encode_type(&mail.types, "Mail").unwrap(),
+			"Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+		);
+		assert_eq!(
+			hex::encode(hash_struct(&mail.types, "Mail", &mail.message).unwrap()),
+			"c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+		);
+		assert_eq!(
+			hex::encode(mail.digest().unwrap()),
+			"be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+		);
+
+		// The domain type is derived if not given.
+		let mut derived = mail.clone();
+		derived.types.remove(DOMAIN_TYPE);
+		assert_eq!(derived.digest().unwrap(), mail.digest().unwrap());
+	}
+
+	#[test]
+	fn should_reject_untyped_members() {
+		let mut mail = mail();
+		mail.message["bcc"] = json!("Eve");
+		assert!(matches!(mail.digest(), Err(VcError::InvalidProof(_))));
+
+		let mut mail = self::mail();
+		mail.message.as_object_mut().unwrap().remove("contents");
+		assert!(matches!(mail.digest(), Err(VcError::InvalidProof(_))));
+	}
+
+	#[test]
+	fn should_encode_signed_integers() {
+		let types = Types::new();
+		let minus_one = encode_value(&types, "int8", &json!(-1)).unwrap();
+		assert_eq!(minus_one, [0xff; 32]);
+		let n = encode_value(&types, "uint256", &json!("0x0100")).unwrap();
+		assert_eq!(&n[30..], &[1, 0]);
+		assert!(encode_value(&types, "uint8", &json!(-1)).is_err());
+	}
+}
@@ -0,0 +1,35 @@
+use hex::FromHexError;
+use secp256k1::Error as SecpError;
+use serde_json::Error as SerdeError;
+use thiserror::Error;
+use time::error::Format as FormatError;
+
+#[derive(Debug, Error)]
+pub enum VcError {
+	#[error("SerdeError: {0}")]
+	SerdeError(SerdeError),
+
+	#[error("HexError: {0}")]
+	HexError(FromHexError),
+
+	#[error("SigError: {0}")]
+	SigError(SecpError),
+
+	#[error("DateError: {0}")]
+	DateError(FormatError),
+
+	#[error("IoError: {0}")]
+	IoError(std::io::Error),
+
+	#[error("InvalidSignature")]
+	InvalidSignature,
+
+	#[error("InvalidProof: {0}")]
+	InvalidProof(String),
+
+	#[error("UnsupportedProof: {0}")]
+	UnsupportedProof(String),
+
+	#[error("InvalidEpoch: {0:?}")]
+	InvalidEpoch(String),
+}
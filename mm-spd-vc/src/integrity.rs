@@ -0,0 +1,389 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use mm_spd_did::PeerDid;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, PublicKey, SECP256K1};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+
+use crate::eip712::{self, TypedData, Types};
+use crate::error::VcError;
+use crate::proof::{self, PROOF_TYPE};
+
+/// ES256K JWS over the JCS canonical credential.
+pub const ECDSA_SECP256K1_SIGNATURE_2019: &str = "EcdsaSecp256k1Signature2019";
+/// EIP-712 typed-data signature of the credential.
+pub const ETHEREUM_EIP712_SIGNATURE_2021: &str = "EthereumEip712Signature2021";
+
+/// Proof suites of credentials signed over the whole document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofSuite {
+	/// `EcdsaSecp256k1Signature2019`: detached ES256K JWS (RFC 7797, unencoded payload) in `jws`.
+	EcdsaSecp256k1Signature2019,
+	/// `EthereumEip712Signature2021`: hex signature in `proofValue`,
+	/// of the typed data given by `eip712`.
+	EthereumEip712Signature2021,
+	/// `EcdsaSecp256k1RecoverySignature2020`, as signed by [`proof::Signer`].
+	EcdsaSecp256k1RecoverySignature2020,
+}
+
+impl ProofSuite {
+	pub fn from_type(type_: &str) -> Option<Self> {
+		match type_ {
+			ECDSA_SECP256K1_SIGNATURE_2019 => Some(Self::EcdsaSecp256k1Signature2019),
+			ETHEREUM_EIP712_SIGNATURE_2021 => Some(Self::EthereumEip712Signature2021),
+			PROOF_TYPE => Some(Self::EcdsaSecp256k1RecoverySignature2020),
+			_ => None,
+		}
+	}
+}
+
+/// JWS header of ES256K signatures over unencoded payloads.
+#[derive(Serialize, Deserialize)]
+struct JwsHeader {
+	alg: String,
+	b64: bool,
+	crit: Vec<String>,
+}
+
+/// Types, domain and primary type of an `EthereumEip712Signature2021` proof.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip712Options {
+	pub types: Types,
+	pub primary_type: String,
+	pub domain: Value,
+}
+
+/// Verify the proof of the credential, and that it was signed by its issuer.
+/// Returns the issuer DID.
+///
+/// The signed document is the credential with its proof but without the signature
+/// (`jws` or `proofValue`) and the EIP-712 options, so that the proof options
+/// (e.g. `created`, `verificationMethod`) are covered as well.
+/// Recovery signatures (`EcdsaSecp256k1RecoverySignature2020`) cover the proof options
+/// and the credential without proof, see [`proof::Signer::sign_proof`].
+///
+/// EIP-712 proofs must be signed in the [`eip712::attestation_domain`].
+///
+/// Issuers are `did:pkh` DIDs, i.e. identified by the address of their key,
+/// and match the signer in any case and on any chain.
+pub fn verify_credential(credential: &Value) -> Result<String, VcError> {
+	let proof = credential.get("proof").ok_or_else(|| invalid("missing proof"))?;
+	let type_ = proof.get("type").and_then(Value::as_str).unwrap_or_default();
+	let suite =
+		ProofSuite::from_type(type_).ok_or_else(|| VcError::UnsupportedProof(type_.to_string()))?;
+	let issuer = issuer(credential)?;
+
+	let signers = match suite {
+		ProofSuite::EcdsaSecp256k1Signature2019 => {
+			let jws = string(proof, "jws")?;
+			jws_signers(jws, &signed_document(credential, &["jws"])?)?
+		},
+		ProofSuite::EthereumEip712Signature2021 => {
+			let options: Eip712Options =
+				serde_json::from_value(proof.get("eip712").cloned().unwrap_or_default())
+					.map_err(VcError::SerdeError)?;
+			// A signature in another domain may have been made for another verifier.
+			if options.domain != eip712::attestation_domain() {
+				return Err(invalid("EIP-712 domain is not the attestation domain"));
+			}
+			let typed_data = TypedData {
+				types: options.types,
+				primary_type: options.primary_type,
+				domain: options.domain,
+				message: signed_document(credential, &["proofValue", "eip712"])?,
+			};
+			let digest = typed_data.digest()?;
+			vec![recover_did(&digest, string(proof, "proofValue")?)?]
+		},
+		ProofSuite::EcdsaSecp256k1RecoverySignature2020 => {
+			vec![proof::recover_proof(credential)?]
+		},
+	};
+
+	// Compared canonically, as addresses may be checksummed or on another chain.
+	let canonical: PeerDid = issuer.parse().map_err(|_| invalid("issuer is not a peer DID"))?;
+	if !signers.iter().any(|signer| signer.parse::<PeerDid>().map_or(false, |s| s == canonical)) {
+		return Err(VcError::InvalidSignature);
+	}
+	Ok(issuer.to_string())
+}
+
+/// ES256K JWS of the document, with a detached, unencoded payload.
+pub fn sign_jws(secret_key: &secp256k1::SecretKey, document: &Value) -> Result<String, VcError> {
+	let header = JwsHeader { alg: "ES256K".to_string(), b64: false, crit: vec!["b64".to_string()] };
+	let header = serde_json::to_vec(&header).map_err(VcError::SerdeError)?;
+	let header = URL_SAFE_NO_PAD.encode(header);
+	let message = jws_message(&header, document)?;
+	let signature = SECP256K1.sign_ecdsa(&message, secret_key).serialize_compact();
+	Ok(format!("{}..{}", header, URL_SAFE_NO_PAD.encode(signature)))
+}
+
+/// `did:pkh:eth` DIDs of the keys the JWS signature may be from.
+///
+/// ES256K signatures aren't recoverable, so both candidate keys are recovered.
+fn jws_signers(jws: &str, document: &Value) -> Result<Vec<String>, VcError> {
+	let (header, signature) = jws.split_once("..").ok_or_else(|| invalid("JWS is not detached"))?;
+	let decoded = URL_SAFE_NO_PAD.decode(header).map_err(|_| invalid("JWS header"))?;
+	let parsed: JwsHeader = serde_json::from_slice(&decoded).map_err(VcError::SerdeError)?;
+	if parsed.alg != "ES256K" || parsed.b64 || parsed.crit != ["b64"] {
+		return Err(invalid(
+			"JWS header must be ES256K with an unencoded payload",
+		));
+	}
+
+	let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid("JWS signature"))?;
+	if signature.len() != 64 {
+		return Err(VcError::InvalidSignature);
+	}
+	let message = jws_message(header, document)?;
+
+	let mut signers = Vec::new();
+	for rec_id in 0..2 {
+		let rec_id = RecoveryId::from_i32(rec_id).map_err(VcError::SigError)?;
+		let signature =
+			RecoverableSignature::from_compact(&signature, rec_id).map_err(VcError::SigError)?;
+		if let Ok(pk) = SECP256K1.recover_ecdsa(&message, &signature) {
+			SECP256K1
+				.verify_ecdsa(&message, &signature.to_standard(), &pk)
+				.map_err(VcError::SigError)?;
+			signers.push(pkh_did(&pk));
+		}
+	}
+	Ok(signers)
+}
+
+/// SHA-256 of the JWS signing input, `<header>.<JCS document>`.
+fn jws_message(header: &str, document: &Value) -> Result<Message, VcError> {
+	let mut input = format!("{}.", header).into_bytes();
+	input.extend(serde_jcs::to_vec(document).map_err(VcError::SerdeError)?);
+	let digest = Sha256::digest(input);
+	Message::from_digest_slice(digest.as_ref()).map_err(VcError::SigError)
+}
+
+fn recover_did(digest: &[u8; 32], signature: &str) -> Result<String, VcError> {
+	let bytes = hex::decode(signature.trim_start_matches("0x")).map_err(VcError::HexError)?;
+	if bytes.len() != 65 {
+		return Err(VcError::InvalidSignature);
+	}
+	let rec_id = match bytes[64] {
+		0 | 27 => 0,
+		1 | 28 => 1,
+		_ => return Err(VcError::InvalidSignature),
+	};
+	let rec_id = RecoveryId::from_i32(rec_id).map_err(VcError::SigError)?;
+	let signature =
+		RecoverableSignature::from_compact(&bytes[..64], rec_id).map_err(VcError::SigError)?;
+	let message = Message::from_digest_slice(digest).map_err(VcError::SigError)?;
+	let pk = SECP256K1.recover_ecdsa(&message, &signature).map_err(VcError::SigError)?;
+	Ok(pkh_did(&pk))
+}
+
+/// The credential with the given proof members removed.
+pub fn signed_document(credential: &Value, signature_members: &[&str]) -> Result<Value, VcError> {
+	let mut document = credential.clone();
+	let proof = document
+		.get_mut("proof")
+		.and_then(Value::as_object_mut)
+		.ok_or_else(|| invalid("proof is not an object"))?;
+	for member in signature_members {
+		proof.remove(*member);
+	}
+	Ok(document)
+}
+
+/// Issuer DID, given either as a string or as an object with an `id`.
+fn issuer(credential: &Value) -> Result<&str, VcError> {
+	let issuer = credential.get("issuer").ok_or_else(|| invalid("missing issuer"))?;
+	issuer
+		.as_str()
+		.or_else(|| issuer.get("id").and_then(Value::as_str))
+		.ok_or_else(|| invalid("issuer is not a DID"))
+}
+
+fn string<'a>(proof: &'a Value, member: &str) -> Result<&'a str, VcError> {
+	proof
+		.get(member)
+		.and_then(Value::as_str)
+		.ok_or_else(|| invalid(&format!("missing {}", member)))
+}
+
+fn pkh_did(pk: &PublicKey) -> String {
+	let hash = Keccak256::digest(&pk.serialize_uncompressed()[1..]);
+	format!("did:pkh:eth:0x{}", hex::encode(&hash[12..]))
+}
+
+fn invalid(reason: &str) -> VcError {
+	VcError::InvalidProof(reason.to_string())
+}
+
+#[cfg(test)]
+mod test {
+	use secp256k1::SecretKey;
+	use serde_json::json;
+
+	use super::*;
+	use crate::proof::Signer;
+
+	fn credential(issuer: &str, proof: Value) -> Value {
+		json!({
+			"@context": ["https://www.w3.org/2018/credentials/v1"],
+			"type": ["VerifiableCredential", "StatusCredential"],
+			"issuer": issuer,
+			"issuanceDate": "2024-01-01T00:00:00Z",
+			"credentialSubject": {
+				"id": "snap://0x90f8bf6a479f320ead074411a4b0e7944ea8c9c2",
+				"currentStatus": "Endorsed"
+			},
+			"proof": proof
+		})
+	}
+
+	fn tamper(mut credential: Value) -> Value {
+		credential["credentialSubject"]["currentStatus"] = json!("Disputed");
+		credential
+	}
+
+	#[test]
+	fn should_verify_jws_proofs() {
+		let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+		let signer = Signer::new(secret_key);
+		let proof = json!({
+			"type": ECDSA_SECP256K1_SIGNATURE_2019,
+			"created": "2024-01-01T00:00:00Z",
+			"proofPurpose": "assertionMethod",
+			"verificationMethod": signer.verification_method(),
+		});
+		let mut credential = credential(signer.did(), proof);
+		let jws = sign_jws(&secret_key, &credential).unwrap();
+		credential["proof"]["jws"] = json!(jws);
+
+		assert_eq!(verify_credential(&credential).unwrap(), signer.did());
+		assert!(matches!(
+			verify_credential(&tamper(credential.clone())),
+			Err(VcError::InvalidSignature)
+		));
+
+		// Proof options are signed too.
+		let mut moved = credential.clone();
+		moved["proof"]["created"] = json!("2025-01-01T00:00:00Z");
+		assert!(verify_credential(&moved).is_err());
+
+		let other = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		credential["issuer"] = json!({ "id": other.did() });
+		assert!(matches!(
+			verify_credential(&credential),
+			Err(VcError::InvalidSignature)
+		));
+	}
+
+	#[test]
+	fn should_verify_eip712_proofs() {
+		let secret_key = SecretKey::new(&mut secp256k1::rand::thread_rng());
+		let signer = Signer::new(secret_key);
+		let eip712 = json!({
+			"domain": eip712::attestation_domain(),
+			"primaryType": "VerifiableCredential",
+			"types": {
+				"VerifiableCredential": [
+					{ "name": "@context", "type": "string[]" },
+					{ "name": "type", "type": "string[]" },
+					{ "name": "issuer", "type": "string" },
+					{ "name": "issuanceDate", "type": "string" },
+					{ "name": "credentialSubject", "type": "CredentialSubject" },
+					{ "name": "proof", "type": "Proof" }
+				],
+				"CredentialSubject": [
+					{ "name": "id", "type": "string" },
+					{ "name": "currentStatus", "type": "string" }
+				],
+				"Proof": [
+					{ "name": "type", "type": "string" },
+					{ "name": "created", "type": "string" },
+					{ "name": "proofPurpose", "type": "string" },
+					{ "name": "verificationMethod", "type": "string" }
+				]
+			}
+		});
+		let proof = json!({
+			"type": ETHEREUM_EIP712_SIGNATURE_2021,
+			"created": "2024-01-01T00:00:00Z",
+			"proofPurpose": "assertionMethod",
+			"verificationMethod": signer.verification_method(),
+			"eip712": eip712.clone(),
+		});
+		let mut credential = credential(signer.did(), proof);
+
+		let typed_data = TypedData {
+			types: serde_json::from_value(eip712["types"].clone()).unwrap(),
+			primary_type: "VerifiableCredential".to_string(),
+			domain: eip712["domain"].clone(),
+			message: signed_document(&credential, &["eip712"]).unwrap(),
+		};
+		let message = Message::from_digest_slice(&typed_data.digest().unwrap()).unwrap();
+		let (rec_id, rs) =
+			SECP256K1.sign_ecdsa_recoverable(&message, &secret_key).serialize_compact();
+		let mut signature = rs.to_vec();
+		signature.push(27 + rec_id.to_i32() as u8);
+		credential["proof"]["proofValue"] = json!(format!("0x{}", hex::encode(signature)));
+
+		assert_eq!(verify_credential(&credential).unwrap(), signer.did());
+		assert!(matches!(
+			verify_credential(&tamper(credential.clone())),
+			Err(VcError::InvalidSignature)
+		));
+		// Signatures in other domains are rejected, even if valid.
+		let mut other_domain = credential.clone();
+		other_domain["proof"]["eip712"]["domain"] = json!({ "name": "Snap Reputation" });
+		assert!(matches!(
+			verify_credential(&other_domain),
+			Err(VcError::InvalidProof(_))
+		));
+		// Members outside the signed types are rejected rather than ignored.
+		credential["credentialSubject"]["note"] = json!("unsigned");
+		assert!(matches!(
+			verify_credential(&credential),
+			Err(VcError::InvalidProof(_))
+		));
+	}
+
+	#[test]
+	fn should_verify_recovery_proofs() {
+		let signer = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		let mut credential = credential(signer.did(), json!({ "type": PROOF_TYPE }));
+		let signature = signer.sign_proof(&credential).unwrap();
+		credential["proof"]["proofValue"] = json!(signature);
+
+		assert_eq!(verify_credential(&credential).unwrap(), signer.did());
+		assert!(verify_credential(&tamper(credential.clone())).is_err());
+		credential["proof"]["created"] = json!("2025-01-01T00:00:00Z");
+		assert!(verify_credential(&credential).is_err());
+	}
+
+	#[test]
+	fn should_match_issuers_canonically() {
+		let signer = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		let address = signer.did().trim_start_matches("did:pkh:eth:0x");
+		let address: [u8; 20] = hex::decode(address).unwrap().try_into().unwrap();
+		let issuer = format!(
+			"did:pkh:eip155:1:{}",
+			mm_spd_did::to_checksum_address(&address)
+		);
+		let mut credential = credential(&issuer, json!({ "type": PROOF_TYPE }));
+		credential["proof"]["proofValue"] = json!(signer.sign_proof(&credential).unwrap());
+
+		assert_eq!(verify_credential(&credential).unwrap(), issuer);
+	}
+
+	#[test]
+	fn should_reject_unknown_proof_suites() {
+		let credential = credential("did:pkh:eth:0x1", json!({ "type": "Ed25519Signature2020" }));
+		assert!(matches!(
+			verify_credential(&credential),
+			Err(VcError::UnsupportedProof(_))
+		));
+	}
+}
@@ -1,4 +1,17 @@
 use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::error::VcError;
+
+pub mod eip712;
+pub mod error;
+pub mod integrity;
+pub mod manifest;
+pub mod proof;
+pub mod publish;
+pub mod schema;
+pub mod trust_score;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -44,7 +57,7 @@ pub struct StatusCredentialProof {
 	// pub signature: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TrustScoreCredential {
 	#[serde(rename = "@context")]
@@ -58,7 +71,7 @@ pub struct TrustScoreCredential {
 	pub proof: TrustScoreCredentialProof,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TrustScoreCredentialSubject {
 	pub id: String,
@@ -66,7 +79,7 @@ pub struct TrustScoreCredentialSubject {
 	pub trust_score: TrustScore,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TrustScore {
 	pub value: f64,
@@ -83,11 +96,18 @@ pub struct TrustScore {
 	pub scope: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct TrustScoreCredentialProof {}
+pub struct TrustScoreCredentialProof {
+	#[serde(rename = "type")]
+	pub type_: String,
+	pub created: String,
+	pub proof_purpose: String,
+	pub verification_method: String,
+	pub proof_value: String,
+}
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Manifest {
 	pub issuer: String,
@@ -100,9 +120,16 @@ pub struct Manifest {
 	pub proof: ManifestProof,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct ManifestProof {}
+pub struct ManifestProof {
+	#[serde(rename = "type")]
+	pub type_: String,
+	pub created: String,
+	pub proof_purpose: String,
+	pub verification_method: String,
+	pub proof_value: String,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
@@ -119,3 +146,9 @@ impl OneOrMore<String> {
 		}
 	}
 }
+
+/// Format the date as an RFC 3339 timestamp, to the second.
+pub fn format_date(date: OffsetDateTime) -> Result<String, VcError> {
+	let date = date.replace_nanosecond(0).unwrap_or(date);
+	date.format(&Rfc3339).map_err(VcError::DateError)
+}
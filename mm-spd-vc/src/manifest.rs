@@ -0,0 +1,72 @@
+use time::OffsetDateTime;
+
+use crate::error::VcError;
+use crate::proof::{self, Signer, PROOF_PURPOSE, PROOF_TYPE};
+use crate::{format_date, Manifest, ManifestProof};
+
+/// Builds signed manifests of published epochs.
+pub struct ManifestBuilder<'a> {
+	signer: &'a Signer,
+	scope: String,
+	trust_threshold: f64,
+	issuance_date: OffsetDateTime,
+	effective_date: OffsetDateTime,
+}
+
+impl<'a> ManifestBuilder<'a> {
+	/// Builder issuing at the current time, effective immediately.
+	pub fn new(signer: &'a Signer, scope: &str) -> Self {
+		let now = OffsetDateTime::now_utc();
+		Self {
+			signer,
+			scope: scope.to_string(),
+			trust_threshold: 0.,
+			issuance_date: now,
+			effective_date: now,
+		}
+	}
+
+	/// Trust threshold the badges of the epoch were given with.
+	pub fn trust_threshold(mut self, trust_threshold: f64) -> Self {
+		self.trust_threshold = trust_threshold;
+		self
+	}
+
+	pub fn issuance_date(mut self, issuance_date: OffsetDateTime) -> Self {
+		self.issuance_date = issuance_date;
+		self
+	}
+
+	pub fn effective_date(mut self, effective_date: OffsetDateTime) -> Self {
+		self.effective_date = effective_date;
+		self
+	}
+
+	/// Build the manifest of the epoch, listing the locations of its credentials.
+	pub fn build(&self, epoch: &str, locations: Vec<String>) -> Result<Manifest, VcError> {
+		let issuance_date = format_date(self.issuance_date)?;
+		let mut manifest = Manifest {
+			issuer: self.signer.did().to_string(),
+			issuance_date: issuance_date.clone(),
+			effective_date: format_date(self.effective_date)?,
+			epoch: epoch.to_string(),
+			scope: self.scope.clone(),
+			locations,
+			trust_threshold: self.trust_threshold,
+			proof: ManifestProof {
+				type_: PROOF_TYPE.to_string(),
+				created: issuance_date,
+				proof_purpose: PROOF_PURPOSE.to_string(),
+				verification_method: self.signer.verification_method(),
+				proof_value: String::new(),
+			},
+		};
+		manifest.proof.proof_value = self.signer.sign_proof(&manifest)?;
+		Ok(manifest)
+	}
+}
+
+/// Recover the signer DID of the manifest from its proof.
+pub fn recover_issuer(manifest: &Manifest) -> Result<String, VcError> {
+	proof::recover_proof(manifest)
+}
@@ -0,0 +1,194 @@
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, PublicKey, SecretKey, SECP256K1};
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+
+use crate::error::VcError;
+
+/// Proof type of the credentials signed by [`Signer`].
+pub const PROOF_TYPE: &str = "EcdsaSecp256k1RecoverySignature2020";
+
+/// Proof purpose of the credentials signed by [`Signer`].
+pub const PROOF_PURPOSE: &str = "assertionMethod";
+
+/// Issuer of signed documents, identified by the `did:pkh:eth` DID of its secp256k1 key.
+pub struct Signer {
+	secret_key: SecretKey,
+	did: String,
+}
+
+impl Signer {
+	pub fn new(secret_key: SecretKey) -> Self {
+		let pk = secret_key.public_key(SECP256K1);
+		let did = format!("did:pkh:eth:0x{}", hex::encode(address_from_ecdsa_key(&pk)));
+		Self { secret_key, did }
+	}
+
+	/// Signer from a hex-encoded secret key, with or without the 0x prefix.
+	pub fn from_hex(secret_key: &str) -> Result<Self, VcError> {
+		let bytes = hex::decode(secret_key.trim_start_matches("0x")).map_err(VcError::HexError)?;
+		let secret_key = SecretKey::from_slice(&bytes).map_err(VcError::SigError)?;
+		Ok(Self::new(secret_key))
+	}
+
+	pub fn did(&self) -> &str {
+		&self.did
+	}
+
+	/// Verification method, i.e. the blockchain account of the issuer DID.
+	pub fn verification_method(&self) -> String {
+		format!("{}#blockchainAccountId", self.did)
+	}
+
+	/// Sign the JCS (RFC 8785) canonical form of the document.
+	///
+	/// The signature is over the Keccak-256 digest of the canonical bytes,
+	/// hex-encoded with the 0x prefix, as r || s || v (v = 27 + recovery id).
+	pub fn sign<T: Serialize>(&self, document: &T) -> Result<String, VcError> {
+		self.sign_digest(&digest(document)?)
+	}
+
+	/// Sign the document along with the options of its proof, as Data Integrity proofs do,
+	/// so that the proof metadata (e.g. `created`, `verificationMethod`) is signed too.
+	///
+	/// The document must hold its `proof`, which `proofValue` is left out of.
+	/// See [`recover_proof`].
+	pub fn sign_proof<T: Serialize>(&self, document: &T) -> Result<String, VcError> {
+		self.sign_digest(&proof_digest(document)?)
+	}
+
+	fn sign_digest(&self, message: &Message) -> Result<String, VcError> {
+		let (rec_id, rs) =
+			SECP256K1.sign_ecdsa_recoverable(message, &self.secret_key).serialize_compact();
+		let mut bytes = rs.to_vec();
+		bytes.push(27 + rec_id.to_i32() as u8);
+		Ok(format!("0x{}", hex::encode(bytes)))
+	}
+}
+
+/// Recover the `did:pkh:eth` DID of the signer of the document from the signature.
+pub fn recover<T: Serialize>(document: &T, signature: &str) -> Result<String, VcError> {
+	recover_digest(&digest(document)?, signature)
+}
+
+/// Recover the `did:pkh:eth` DID of the signer of the document
+/// from the `proofValue` of its proof, as signed by [`Signer::sign_proof`].
+pub fn recover_proof<T: Serialize>(document: &T) -> Result<String, VcError> {
+	let value = serde_json::to_value(document).map_err(VcError::SerdeError)?;
+	let signature = value
+		.get("proof")
+		.and_then(|proof| proof.get("proofValue"))
+		.and_then(serde_json::Value::as_str)
+		.ok_or(VcError::InvalidSignature)?;
+	recover_digest(&proof_digest(&value)?, signature)
+}
+
+fn recover_digest(message: &Message, signature: &str) -> Result<String, VcError> {
+	let bytes = hex::decode(signature.trim_start_matches("0x")).map_err(VcError::HexError)?;
+	if bytes.len() != 65 {
+		return Err(VcError::InvalidSignature);
+	}
+	let (rs, v) = bytes.split_at(64);
+	let rec_id = match v[0] {
+		0 | 27 => 0,
+		1 | 28 => 1,
+		_ => return Err(VcError::InvalidSignature),
+	};
+	let rec_id = RecoveryId::from_i32(rec_id).map_err(VcError::SigError)?;
+	let signature = RecoverableSignature::from_compact(rs, rec_id).map_err(VcError::SigError)?;
+	let pk = SECP256K1.recover_ecdsa(message, &signature).map_err(VcError::SigError)?;
+	Ok(format!(
+		"did:pkh:eth:0x{}",
+		hex::encode(address_from_ecdsa_key(&pk))
+	))
+}
+
+/// The document without its proof, i.e. the signed part.
+pub fn unsigned<T: Serialize>(document: &T) -> Result<serde_json::Value, VcError> {
+	let mut value = serde_json::to_value(document).map_err(VcError::SerdeError)?;
+	if let Some(fields) = value.as_object_mut() {
+		fields.remove("proof");
+	}
+	Ok(value)
+}
+
+fn digest<T: Serialize>(document: &T) -> Result<Message, VcError> {
+	let bytes = serde_jcs::to_vec(document).map_err(VcError::SerdeError)?;
+	let digest = Keccak256::digest(bytes);
+	Message::from_digest_slice(digest.as_ref()).map_err(VcError::SigError)
+}
+
+/// Digest of the hashes of the canonical proof options (the proof without `proofValue`)
+/// and of the canonical document without proof, in that order.
+fn proof_digest<T: Serialize>(document: &T) -> Result<Message, VcError> {
+	let mut options = serde_json::to_value(document)
+		.map_err(VcError::SerdeError)?
+		.get("proof")
+		.cloned()
+		.ok_or(VcError::InvalidSignature)?;
+	if let Some(fields) = options.as_object_mut() {
+		fields.remove("proofValue");
+	}
+	let options = serde_jcs::to_vec(&options).map_err(VcError::SerdeError)?;
+	let document = serde_jcs::to_vec(&unsigned(document)?).map_err(VcError::SerdeError)?;
+
+	let mut hasher = Keccak256::new();
+	hasher.update(Keccak256::digest(options));
+	hasher.update(Keccak256::digest(document));
+	Message::from_digest_slice(hasher.finalize().as_ref()).map_err(VcError::SigError)
+}
+
+fn address_from_ecdsa_key(pub_key: &PublicKey) -> Vec<u8> {
+	let raw_pub_key = pub_key.serialize_uncompressed();
+	// Hash and get the last 20 bytes.
+	let pub_key_hash = Keccak256::digest(&raw_pub_key[1..]);
+	pub_key_hash[12..].to_vec()
+}
+
+#[cfg(test)]
+mod test {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn should_derive_did_from_secret_key() {
+		// Well-known development key (Ganache account 0).
+		let signer =
+			Signer::from_hex("0x4f3edf983ac636a65a842ce7c78d9aa706d3b113bce9c46f30d7d21715b23b1d")
+				.unwrap();
+		assert_eq!(
+			signer.did(),
+			"did:pkh:eth:0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1"
+		);
+	}
+
+	#[test]
+	fn should_recover_signer_regardless_of_key_order() {
+		let signer = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		let signature = signer.sign(&json!({ "a": 1, "b": [true, "x"] })).unwrap();
+
+		let reordered = json!({ "b": [true, "x"], "a": 1 });
+		assert_eq!(recover(&reordered, &signature).unwrap(), signer.did());
+		let tampered = json!({ "a": 2, "b": [true, "x"] });
+		assert_ne!(recover(&tampered, &signature).unwrap(), signer.did());
+	}
+
+	#[test]
+	fn should_sign_proof_options() {
+		let signer = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		let mut document = json!({
+			"a": 1,
+			"proof": { "created": "2024-01-01T00:00:00Z", "proofValue": "" },
+		});
+		document["proof"]["proofValue"] = json!(signer.sign_proof(&document).unwrap());
+		assert_eq!(recover_proof(&document).unwrap(), signer.did());
+
+		let mut tampered = document.clone();
+		tampered["proof"]["created"] = json!("2025-01-01T00:00:00Z");
+		assert_ne!(recover_proof(&tampered).unwrap(), signer.did());
+		let mut tampered = document.clone();
+		tampered["a"] = json!(2);
+		assert_ne!(recover_proof(&tampered).unwrap(), signer.did());
+	}
+}
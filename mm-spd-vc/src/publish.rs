@@ -0,0 +1,136 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::VcError;
+use crate::manifest::ManifestBuilder;
+use crate::{Manifest, TrustScoreCredential};
+
+/// File name of the manifest within an epoch directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Publishes the credentials of each epoch into a local directory:
+///
+/// ```text
+/// <dir>/<epoch>/<credential digest>.json
+/// <dir>/<epoch>/manifest.json
+/// ```
+///
+/// The manifest is written last, so an epoch without one is incomplete.
+/// Epochs are timestamps, i.e. decimal numbers, so they never escape the directory.
+pub struct Publisher {
+	dir: PathBuf,
+	base_url: Option<String>,
+}
+
+impl Publisher {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self { dir: dir.into(), base_url: None }
+	}
+
+	/// Base URL the directory is served at, used for the manifest locations
+	/// instead of the local file paths.
+	pub fn base_url(mut self, base_url: &str) -> Self {
+		self.base_url = Some(base_url.trim_end_matches('/').to_string());
+		self
+	}
+
+	/// Write the credentials of the epoch, then its signed manifest listing them.
+	/// The epoch must be a decimal number.
+	pub fn publish(
+		&self, manifest: &ManifestBuilder, epoch: &str, credentials: &[TrustScoreCredential],
+	) -> Result<Manifest, VcError> {
+		if epoch.is_empty() || !epoch.bytes().all(|b| b.is_ascii_digit()) {
+			return Err(VcError::InvalidEpoch(epoch.to_string()));
+		}
+		let dir = self.dir.join(epoch);
+		fs::create_dir_all(&dir).map_err(VcError::IoError)?;
+
+		let mut locations = Vec::new();
+		for credential in credentials {
+			let file = format!("{}.json", credential.id.trim_start_matches("0x"));
+			write_json(&dir.join(&file), credential)?;
+			locations.push(self.location(epoch, &file));
+		}
+
+		let manifest = manifest.build(epoch, locations)?;
+		write_json(&dir.join(MANIFEST_FILE), &manifest)?;
+		Ok(manifest)
+	}
+
+	fn location(&self, epoch: &str, file: &str) -> String {
+		match &self.base_url {
+			Some(base_url) => format!("{}/{}/{}", base_url, epoch, file),
+			None => self.dir.join(epoch).join(file).display().to_string(),
+		}
+	}
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), VcError> {
+	let bytes = serde_json::to_vec_pretty(value).map_err(VcError::SerdeError)?;
+	fs::write(path, bytes).map_err(VcError::IoError)
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::BTreeMap;
+
+	use secp256k1::SecretKey;
+
+	use super::*;
+	use crate::manifest::recover_issuer;
+	use crate::proof::Signer;
+	use crate::trust_score::{self, Score, TrustScoreCredentialBuilder, EIGENTRUST};
+
+	#[test]
+	fn should_publish_epoch_with_signed_manifest() {
+		let dir = tempfile::tempdir().unwrap();
+		let signer = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		let scores: BTreeMap<_, _> = [("did:pkh:eth:0x1", 0.25), ("did:pkh:eth:0x2", 0.75)]
+			.map(|(id, v)| (id.to_string(), Score::new(v)))
+			.into();
+		let credentials = TrustScoreCredentialBuilder::new(&signer, EIGENTRUST, "SoftwareSecurity")
+			.build_all(&scores)
+			.unwrap();
+		let builder = ManifestBuilder::new(&signer, "SoftwareSecurity").trust_threshold(0.125);
+
+		let publisher = Publisher::new(dir.path()).base_url("https://example.com/scores/");
+		let manifest = publisher.publish(&builder, "42", &credentials).unwrap();
+
+		assert_eq!(manifest.epoch, "42");
+		assert_eq!(manifest.trust_threshold, 0.125);
+		assert_eq!(manifest.locations.len(), 2);
+		assert!(manifest.locations.iter().all(|l| l.starts_with("https://example.com/scores/42/")));
+		assert_eq!(recover_issuer(&manifest).unwrap(), signer.did());
+
+		// Every listed credential is on disk and verifies.
+		for location in &manifest.locations {
+			let file = location.rsplit('/').next().unwrap();
+			let json = fs::read(dir.path().join("42").join(file)).unwrap();
+			let credential: TrustScoreCredential = serde_json::from_slice(&json).unwrap();
+			assert_eq!(
+				trust_score::recover_issuer(&credential).unwrap(),
+				signer.did()
+			);
+		}
+		let json = fs::read(dir.path().join("42").join(MANIFEST_FILE)).unwrap();
+		let manifest: Manifest = serde_json::from_slice(&json).unwrap();
+		assert_eq!(recover_issuer(&manifest).unwrap(), signer.did());
+	}
+
+	#[test]
+	fn should_reject_non_numeric_epochs() {
+		let dir = tempfile::tempdir().unwrap();
+		let signer = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		let builder = ManifestBuilder::new(&signer, "SoftwareSecurity");
+		let publisher = Publisher::new(dir.path().join("scores"));
+		for epoch in ["", "../42", "/tmp", "4.2", "-1"] {
+			assert!(matches!(
+				publisher.publish(&builder, epoch, &[]),
+				Err(VcError::InvalidEpoch(_))
+			));
+		}
+		assert!(!dir.path().join("scores").exists());
+	}
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::VcError;
+use crate::OneOrMore;
+
+/// Credential schemas known to the pipeline,
+/// shared by the indexer (type to schema ID) and the attestation transformer (schema ID to parser).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchemaConfig {
+	pub schemas: Vec<SchemaEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SchemaEntry {
+	/// Schema ID of the indexed events.
+	pub id: u32,
+	/// Name of the parser implementation in the attestation transformer.
+	pub kind: String,
+	/// Credential `type` names of the schema.
+	pub types: Vec<String>,
+}
+
+impl SchemaConfig {
+	/// Load the config from a JSON file.
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, VcError> {
+		let bytes = fs::read(path).map_err(VcError::IoError)?;
+		serde_json::from_slice(&bytes).map_err(VcError::SerdeError)
+	}
+
+	/// Schema ID of a credential with the given `type`,
+	/// i.e. of the first schema that lists any of its type names.
+	pub fn schema_id(&self, type_: &OneOrMore<String>) -> Option<u32> {
+		self.schemas.iter().find(|s| s.types.iter().any(|t| type_.matches(t))).map(|s| s.id)
+	}
+}
+
+impl Default for SchemaConfig {
+	fn default() -> Self {
+		let entry = |id, kind: &str, types: &[&str]| SchemaEntry {
+			id,
+			kind: kind.to_string(),
+			types: types.iter().map(|t| t.to_string()).collect(),
+		};
+		Self {
+			schemas: vec![
+				entry(0, "SecurityCredential", &["SecurityReportCredential"]),
+				entry(1, "StatusCredential", &["ReviewCredential"]),
+				entry(2, "TrustCredential", &["TrustCredential"]),
+				entry(3, "RevocationCredential", &["RevocationCredential"]),
+			],
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_match_any_listed_type() {
+		let json = r#"{"schemas": [
+			{"id": 1, "kind": "StatusCredential", "types": ["ReviewCredential", "StatusCredential"]},
+			{"id": 7, "kind": "TrustCredential", "types": ["PeerTrustCredential"]}
+		]}"#;
+		let config: SchemaConfig = serde_json::from_str(json).unwrap();
+		let types = |t: &[&str]| OneOrMore::More(t.iter().map(|t| t.to_string()).collect());
+
+		assert_eq!(
+			config.schema_id(&types(&["VerifiableCredential", "StatusCredential"])),
+			Some(1)
+		);
+		assert_eq!(
+			config.schema_id(&OneOrMore::One("PeerTrustCredential".to_string())),
+			Some(7)
+		);
+		assert_eq!(config.schema_id(&types(&["VerifiableCredential"])), None);
+		assert_eq!(
+			SchemaConfig::default().schema_id(&types(&["TrustCredential"])),
+			Some(2)
+		);
+	}
+
+	#[test]
+	fn should_ship_built_in_config() {
+		let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../schemas.json");
+		assert_eq!(
+			SchemaConfig::from_file(path).unwrap(),
+			SchemaConfig::default()
+		);
+	}
+}
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use sha3::{Digest, Keccak256};
+use time::OffsetDateTime;
+
+use crate::error::VcError;
+use crate::proof::{self, unsigned, Signer, PROOF_PURPOSE, PROOF_TYPE};
+use crate::{
+	format_date, OneOrMore, TrustScore, TrustScoreCredential, TrustScoreCredentialProof,
+	TrustScoreCredentialSubject,
+};
+
+pub const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+
+/// Trust score type of peer scores (global trust).
+pub const EIGENTRUST: &str = "EigenTrust";
+
+/// Trust score type of snap scores (trust-weighted average rating).
+pub const TRUST_WEIGHTED_AVERAGE: &str = "TrustWeightedAverage";
+
+/// A subject's computed score, before ranking.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+	pub value: f64,
+	pub value_before_discount: Option<f64>,
+	pub confidence: Option<f64>,
+	pub result: Option<i32>,
+	pub accuracy: Option<f64>,
+}
+
+impl Score {
+	pub fn new(value: f64) -> Self {
+		Self { value, ..Default::default() }
+	}
+}
+
+/// Rank of each subject by score value, 1 being the highest.
+/// Tied subjects share the same rank, and the next rank is skipped for each tie ("1224").
+pub fn ranks<K: Ord + Clone>(scores: &BTreeMap<K, Score>) -> BTreeMap<K, u64> {
+	let mut sorted: Vec<_> = scores.iter().collect();
+	sorted.sort_by(|(_, a), (_, b)| b.value.total_cmp(&a.value));
+	let mut ranks = BTreeMap::new();
+	let mut prev: Option<(f64, u64)> = None;
+	for (i, (id, score)) in sorted.into_iter().enumerate() {
+		let rank = match prev {
+			Some((value, rank)) if value == score.value => rank,
+			_ => i as u64 + 1,
+		};
+		prev = Some((score.value, rank));
+		ranks.insert(id.clone(), rank);
+	}
+	ranks
+}
+
+/// Builds signed trust score credentials of one score vector.
+pub struct TrustScoreCredentialBuilder<'a> {
+	signer: &'a Signer,
+	trust_score_type: String,
+	scope: String,
+	issuance_date: OffsetDateTime,
+}
+
+impl<'a> TrustScoreCredentialBuilder<'a> {
+	/// Builder issuing at the current time.
+	pub fn new(signer: &'a Signer, trust_score_type: &str, scope: &str) -> Self {
+		Self {
+			signer,
+			trust_score_type: trust_score_type.to_string(),
+			scope: scope.to_string(),
+			issuance_date: OffsetDateTime::now_utc(),
+		}
+	}
+
+	pub fn issuance_date(mut self, issuance_date: OffsetDateTime) -> Self {
+		self.issuance_date = issuance_date;
+		self
+	}
+
+	/// Build credentials for all the subjects of the vector, ranked across the vector.
+	pub fn build_all(
+		&self, scores: &BTreeMap<String, Score>,
+	) -> Result<Vec<TrustScoreCredential>, VcError> {
+		let ranks = ranks(scores);
+		scores.iter().map(|(id, score)| self.build(id, score, ranks.get(id).copied())).collect()
+	}
+
+	/// Build the credential of one subject.
+	///
+	/// The credential ID is the Keccak-256 digest of the canonical credential without ID and proof,
+	/// and the proof signs the canonical credential without proof.
+	pub fn build(
+		&self, id: &str, score: &Score, rank: Option<u64>,
+	) -> Result<TrustScoreCredential, VcError> {
+		let issuance_date = format_date(self.issuance_date)?;
+		let mut credential = TrustScoreCredential {
+			context: vec![CREDENTIALS_CONTEXT.to_string()],
+			id: String::new(),
+			type_: OneOrMore::More(vec![
+				"VerifiableCredential".to_string(),
+				"TrustScoreCredential".to_string(),
+			]),
+			issuer: self.signer.did().to_string(),
+			issuance_date: issuance_date.clone(),
+			credential_subject: TrustScoreCredentialSubject {
+				id: id.to_string(),
+				trust_score_type: self.trust_score_type.clone(),
+				trust_score: TrustScore {
+					value: score.value,
+					value_before_discount: score.value_before_discount,
+					confidence: score.confidence,
+					result: score.result,
+					accuracy: score.accuracy,
+					rank,
+					scope: self.scope.clone(),
+				},
+			},
+			proof: TrustScoreCredentialProof {
+				type_: PROOF_TYPE.to_string(),
+				created: issuance_date,
+				proof_purpose: PROOF_PURPOSE.to_string(),
+				verification_method: self.signer.verification_method(),
+				proof_value: String::new(),
+			},
+		};
+
+		let mut content = unsigned(&credential)?;
+		if let Some(fields) = content.as_object_mut() {
+			fields.remove("id");
+		}
+		let bytes = serde_jcs::to_vec(&content).map_err(VcError::SerdeError)?;
+		credential.id = format!("0x{}", hex::encode(Keccak256::digest(bytes)));
+		credential.proof.proof_value = self.signer.sign_proof(&credential)?;
+		Ok(credential)
+	}
+}
+
+/// Recover the signer DID of the credential from its proof.
+pub fn recover_issuer(credential: &TrustScoreCredential) -> Result<String, VcError> {
+	proof::recover_proof(credential)
+}
+
+#[cfg(test)]
+mod test {
+	use secp256k1::SecretKey;
+
+	use super::*;
+
+	fn scores(values: &[(&str, f64)]) -> BTreeMap<String, Score> {
+		values.iter().map(|(id, v)| (id.to_string(), Score::new(*v))).collect()
+	}
+
+	#[test]
+	fn should_rank_ties_equally() {
+		let scores = scores(&[("a", 0.1), ("b", 0.4), ("c", 0.4), ("d", -0.2), ("e", 0.3)]);
+		let ranks = ranks(&scores);
+		let expected: BTreeMap<_, _> = [("a", 4), ("b", 1), ("c", 1), ("d", 5), ("e", 3)]
+			.map(|(k, v)| (k.to_string(), v))
+			.into();
+		assert_eq!(ranks, expected);
+	}
+
+	#[test]
+	fn should_build_signed_credentials() {
+		let signer = Signer::new(SecretKey::new(&mut secp256k1::rand::thread_rng()));
+		let date = OffsetDateTime::from_unix_timestamp(1700000000).unwrap();
+		let builder = TrustScoreCredentialBuilder::new(&signer, EIGENTRUST, "SoftwareSecurity")
+			.issuance_date(date);
+		let mut scores = scores(&[("did:pkh:eth:0x1", 0.25), ("did:pkh:eth:0x2", 0.75)]);
+		scores.get_mut("did:pkh:eth:0x1").unwrap().value_before_discount = Some(0.5);
+
+		let credentials = builder.build_all(&scores).unwrap();
+
+		assert_eq!(credentials.len(), 2);
+		let c = &credentials[0];
+		assert_eq!(c.issuer, signer.did());
+		assert_eq!(c.issuance_date, "2023-11-14T22:13:20Z");
+		assert!(c.type_.matches("TrustScoreCredential"));
+		assert_eq!(c.credential_subject.id, "did:pkh:eth:0x1");
+		assert_eq!(c.credential_subject.trust_score.rank, Some(2));
+		assert_eq!(
+			c.credential_subject.trust_score.value_before_discount,
+			Some(0.5)
+		);
+		assert_eq!(credentials[1].credential_subject.trust_score.rank, Some(1));
+		assert_ne!(c.id, credentials[1].id);
+		assert_eq!(c.proof.type_, PROOF_TYPE);
+		assert_eq!(c.proof.verification_method, signer.verification_method());
+		assert_eq!(recover_issuer(c).unwrap(), signer.did());
+
+		// Round trip through JSON keeps the proof valid.
+		let json = serde_json::to_string(c).unwrap();
+		let mut c: TrustScoreCredential = serde_json::from_str(&json).unwrap();
+		assert_eq!(recover_issuer(&c).unwrap(), signer.did());
+		c.credential_subject.trust_score.value = 1.;
+		assert_ne!(recover_issuer(&c).unwrap(), signer.did());
+	}
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::score::SnapScore;
+
+/// Community sentiment badge of a snap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+	InsufficientReviews,
+	Endorsed,
+	Reported,
+	InReview,
+}
+
+impl fmt::Display for Badge {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			Badge::InsufficientReviews => "Insufficient Reviews",
+			Badge::Endorsed => "Endorsed",
+			Badge::Reported => "Reported",
+			Badge::InReview => "In Review",
+		};
+		f.write_str(s)
+	}
+}
+
+impl Badge {
+	/// Result code of the badge in a trust score credential:
+	/// 1 if endorsed, -1 if reported, 0 if in review, and none without sufficient reviews.
+	pub fn result(&self) -> Option<i32> {
+		match self {
+			Badge::InsufficientReviews => None,
+			Badge::Endorsed => Some(1),
+			Badge::Reported => Some(-1),
+			Badge::InReview => Some(0),
+		}
+	}
+}
+
+/// Highly trusted auditors (P_h): peers directly endorsed by the pre-trusted peers.
+///
+/// `trust_arcs` holds the positive local trust, keyed by (truster, trustee).
+pub fn highly_trusted<'a, P: Ord + Clone + 'a>(
+	pre_trusted: impl IntoIterator<Item = &'a P>, trust_arcs: &BTreeMap<(P, P), f64>,
+) -> BTreeSet<P> {
+	let pre_trusted: BTreeSet<_> = pre_trusted.into_iter().collect();
+	trust_arcs
+		.iter()
+		.filter(|((truster, _), value)| **value > 0. && pre_trusted.contains(truster))
+		.map(|((_, trustee), _)| trustee.clone())
+		.collect()
+}
+
+/// Badge thresholds, derived from the weakest dissident d,
+/// i.e. the highly trusted auditor with the lowest positive-only trust T+(d).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+	/// T+(d), the minimum confidence for a snap to get a sentiment.
+	pub min_confidence: f64,
+}
+
+impl Thresholds {
+	/// None if there are no highly trusted auditors.
+	pub fn new<P: Ord>(
+		highly_trusted: &BTreeSet<P>, positive_trust: &BTreeMap<P, f64>,
+	) -> Option<Self> {
+		let min_confidence = highly_trusted
+			.iter()
+			.map(|p| positive_trust.get(p).copied().unwrap_or(0.))
+			.min_by(|a, b| a.total_cmp(b))?;
+		Some(Self { min_confidence })
+	}
+
+	/// R_E = 1 - T+(d) / C(s)
+	pub fn endorsed(&self, confidence: f64) -> f64 {
+		1. - self.min_confidence / confidence
+	}
+
+	/// R_R = T+(d) / C(s)
+	pub fn reported(&self, confidence: f64) -> f64 {
+		self.min_confidence / confidence
+	}
+
+	pub fn badge(&self, score: &SnapScore) -> Badge {
+		if score.confidence <= 0. || score.confidence < self.min_confidence {
+			Badge::InsufficientReviews
+		} else if score.value > self.endorsed(score.confidence) {
+			Badge::Endorsed
+		} else if score.value < self.reported(score.confidence) {
+			Badge::Reported
+		} else {
+			Badge::InReview
+		}
+	}
+}
+
+/// Whether snaps are badged, and against which thresholds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Badging {
+	/// Badges were not asked for.
+	Disabled,
+	/// Badges were asked for, but there are no highly trusted auditors to set the thresholds;
+	/// every snap gets Insufficient Reviews.
+	NoThresholds,
+	Enabled(Thresholds),
+}
+
+impl Badging {
+	pub fn new(thresholds: Option<Thresholds>) -> Self {
+		thresholds.map_or(Self::NoThresholds, Self::Enabled)
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		!matches!(self, Self::Disabled)
+	}
+
+	pub fn thresholds(&self) -> Option<&Thresholds> {
+		match self {
+			Self::Enabled(thresholds) => Some(thresholds),
+			_ => None,
+		}
+	}
+}
+
+/// Badge of a snap; Insufficient Reviews if there are no thresholds.
+pub fn badge(thresholds: Option<&Thresholds>, score: &SnapScore) -> Badge {
+	thresholds.map_or(Badge::InsufficientReviews, |t| t.badge(score))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::score::{compute, Opinion, Opinions};
+
+	fn arcs(arcs: &[(&'static str, &'static str)]) -> BTreeMap<(&'static str, &'static str), f64> {
+		arcs.iter().map(|arc| (*arc, 1.)).collect()
+	}
+
+	#[test]
+	fn should_find_highly_trusted_auditors() {
+		let trust_arcs = arcs(&[("a", "x"), ("a", "y"), ("x", "z"), ("b", "y")]);
+		let ph = highly_trusted(&["a", "b"], &trust_arcs);
+		assert_eq!(ph, ["x", "y"].into_iter().collect());
+	}
+
+	#[test]
+	fn should_badge_by_thresholds() {
+		let t = Thresholds { min_confidence: 0.1 };
+		let badge = |value, confidence| t.badge(&SnapScore { value, confidence });
+		assert_eq!(badge(1., 0.05), Badge::InsufficientReviews);
+		assert_eq!(badge(0.95, 0.5), Badge::Endorsed);
+		assert_eq!(badge(0.8, 0.5), Badge::InReview);
+		assert_eq!(badge(0.2, 0.5), Badge::InReview);
+		assert_eq!(badge(0.1, 0.5), Badge::Reported);
+		assert_eq!(
+			super::badge(None, &SnapScore { value: 1., confidence: 1. }),
+			Badge::InsufficientReviews
+		);
+	}
+
+	#[test]
+	fn should_force_in_review_on_single_dissenting_auditor() {
+		let pre_trusted = ["a"];
+		let trust_arcs = arcs(&[("a", "x"), ("a", "y"), ("a", "z")]);
+		// Exact binary fractions; z, the weakest auditor, sits right on the boundary.
+		let trust: BTreeMap<_, _> =
+			[("a", 0.375), ("x", 0.25), ("y", 0.1875), ("z", 0.125), ("p", 0.0625)]
+				.into_iter()
+				.collect();
+		let ph = highly_trusted(&pre_trusted, &trust_arcs);
+		let thresholds = Thresholds::new(&ph, &trust).unwrap();
+		assert_eq!(thresholds.min_confidence, 0.125);
+
+		let endorse = Opinion { endorse: 50., dispute: 0. };
+		let dispute = Opinion { endorse: 0., dispute: 50. };
+		for dissident in &ph {
+			for (majority, minority, unanimous) in
+				[(endorse, dispute, Badge::Endorsed), (dispute, endorse, Badge::Reported)]
+			{
+				// Everyone with positive standing opines, in unison...
+				let mut opinions: Opinions<_, _> =
+					trust.keys().map(|p| ((*p, "s"), majority)).collect();
+				let scores = compute(&trust, &opinions);
+				assert_eq!(thresholds.badge(&scores["s"]), unanimous);
+
+				// ...except for one highly trusted auditor.
+				opinions.insert((*dissident, "s"), minority);
+				let scores = compute(&trust, &opinions);
+				assert_eq!(
+					thresholds.badge(&scores["s"]),
+					Badge::InReview,
+					"{}",
+					dissident
+				);
+			}
+		}
+	}
+}
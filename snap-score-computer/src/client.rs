@@ -0,0 +1,121 @@
+use std::collections::{BTreeMap, HashMap};
+
+use proto_buf::combiner::linear_combiner_client::LinearCombinerClient;
+use proto_buf::combiner::{LtHistoryBatch, LtObject, MappingQuery};
+use proto_buf::transformer::Form;
+use tonic::transport::Channel;
+use trustvector::service_client::ServiceClient as TrustVectorClient;
+
+use crate::error::SnapScoreError;
+use crate::score::{Opinion, Opinions};
+
+/// Software security domain, where the status opinions about snaps live.
+pub const SECURITY_DOMAIN: u32 = 2;
+
+/// DID prefix of peers, as opposed to snaps (e.g. `snap://` or `npm:` ids).
+pub const PEER_DID_PREFIX: &str = "did:";
+
+/// Fetch the numeric ID to canonical DID mapping of all subjects known to the linear combiner.
+pub async fn get_did_mapping(
+	client: &mut LinearCombinerClient<Channel>,
+) -> Result<HashMap<u32, String>, SnapScoreError> {
+	let mut m = HashMap::new();
+	let mut start = 0;
+	let mut more = true;
+	while more {
+		more = false;
+		let mut stream =
+			client.get_did_mapping(MappingQuery { start, size: 1000000 }).await?.into_inner();
+		while let Some(mapping) = stream.message().await? {
+			m.insert(mapping.id, mapping.did);
+			more = true;
+			start += 1;
+		}
+	}
+	Ok(m)
+}
+
+/// Fetch the given peer trust vector (e.g. global trust or pre-trust), keyed by the linear combiner's numeric IDs.
+pub async fn get_peer_trust(
+	client: &mut TrustVectorClient<Channel>, id: &str,
+) -> Result<BTreeMap<u32, f64>, SnapScoreError> {
+	Ok(get_timestamped_peer_trust(client, id).await?.1)
+}
+
+/// Same as [`get_peer_trust`], along with the timestamp (in decimal) of the vector.
+pub async fn get_timestamped_peer_trust(
+	client: &mut TrustVectorClient<Channel>, id: &str,
+) -> Result<(String, BTreeMap<u32, f64>), SnapScoreError> {
+	use trustvector::get_response::Part;
+	let mut timestamp = String::new();
+	let mut vector = BTreeMap::new();
+	let mut stream = client.get(trustvector::GetRequest { id: id.to_string() }).await?.into_inner();
+	while let Some(res) = stream.message().await? {
+		match res.part {
+			Some(Part::Header(h)) => {
+				timestamp = trustvector::qwords_to_big(&h.timestamp_qwords).to_string();
+			},
+			Some(Part::Entry(e)) => {
+				let peer =
+					e.trustee.parse().map_err(|_| SnapScoreError::InvalidEntry(e.trustee))?;
+				vector.insert(peer, e.value);
+			},
+			None => {},
+		}
+	}
+	Ok((timestamp, vector))
+}
+
+/// Fetch the local trust of the given domain and form
+/// among all the subjects in the mapping.
+async fn get_lt(
+	client: &mut LinearCombinerClient<Channel>, mapping: &HashMap<u32, String>, domain: u32,
+	form: Form,
+) -> Result<Vec<LtObject>, SnapScoreError> {
+	let mut lt = Vec::new();
+	let Some(last) = mapping.keys().max().copied() else {
+		return Ok(lt);
+	};
+	let batch = LtHistoryBatch { domain, form: form.into(), x0: 0, y0: 0, x1: last, y1: last };
+	let mut stream = client.get_historic_data(batch).await?.into_inner();
+	while let Some(obj) = stream.message().await? {
+		lt.push(obj);
+	}
+	Ok(lt)
+}
+
+fn is_snap(mapping: &HashMap<u32, String>, id: u32) -> bool {
+	mapping.get(&id).map_or(false, |did| !did.starts_with(PEER_DID_PREFIX))
+}
+
+/// Fetch the status opinions (both forms) about snaps from the linear combiner.
+pub async fn get_opinions(
+	client: &mut LinearCombinerClient<Channel>, mapping: &HashMap<u32, String>,
+) -> Result<Opinions<u32, u32>, SnapScoreError> {
+	let mut opinions = Opinions::new();
+	for form in [Form::Trust, Form::Distrust] {
+		for lt in get_lt(client, mapping, SECURITY_DOMAIN, form).await? {
+			if !is_snap(mapping, lt.y) {
+				continue;
+			}
+			let opinion: &mut Opinion = opinions.entry((lt.x, lt.y)).or_default();
+			match form {
+				Form::Trust => opinion.endorse += f64::from(lt.value),
+				Form::Distrust => opinion.dispute += f64::from(lt.value),
+			}
+		}
+	}
+	Ok(opinions)
+}
+
+/// Fetch the peer-to-peer security trust arcs, keyed by (truster, trustee).
+pub async fn get_trust_arcs(
+	client: &mut LinearCombinerClient<Channel>, mapping: &HashMap<u32, String>,
+) -> Result<BTreeMap<(u32, u32), f64>, SnapScoreError> {
+	let lt = get_lt(client, mapping, SECURITY_DOMAIN, Form::Trust).await?;
+	Ok(lt
+		.into_iter()
+		.filter(|lt| !is_snap(mapping, lt.y))
+		.map(|lt| ((lt.x, lt.y), f64::from(lt.value)))
+		.collect())
+}
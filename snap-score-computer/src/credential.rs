@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mm_spd_vc::trust_score::Score;
+use tracing::error;
+
+use crate::badge::{self, Thresholds};
+use crate::score::SnapScore;
+
+/// Scope of the published trust scores.
+pub const SCOPE: &str = "SoftwareSecurity";
+
+/// Peer scores to publish, keyed by DID.
+///
+/// The value is the distrust-adjusted global trust,
+/// and the value before discount the positive-only one, if known.
+pub fn peer_scores(
+	mapping: &HashMap<u32, String>, peer_trust: &BTreeMap<u32, f64>,
+	positive_trust: Option<&BTreeMap<u32, f64>>,
+) -> BTreeMap<String, Score> {
+	with_dids(
+		mapping,
+		peer_trust.iter().map(|(peer, value)| {
+			let value_before_discount = positive_trust.map(|t| t.get(peer).copied().unwrap_or(0.));
+			(
+				*peer,
+				Score { value: *value, value_before_discount, ..Default::default() },
+			)
+		}),
+	)
+}
+
+/// Snap scores to publish, keyed by DID, with the badge as the result.
+pub fn snap_scores(
+	scores: &BTreeMap<String, SnapScore>, thresholds: Option<&Thresholds>,
+) -> BTreeMap<String, Score> {
+	scores
+		.iter()
+		.map(|(did, score)| {
+			let score = Score {
+				value: score.value,
+				confidence: Some(score.confidence),
+				result: badge::badge(thresholds, score).result(),
+				..Default::default()
+			};
+			(did.clone(), score)
+		})
+		.collect()
+}
+
+fn with_dids(
+	mapping: &HashMap<u32, String>, scores: impl Iterator<Item = (u32, Score)>,
+) -> BTreeMap<String, Score> {
+	scores
+		.filter_map(|(id, score)| match mapping.get(&id) {
+			Some(did) => Some((did.clone(), score)),
+			None => {
+				error!(id, "no DID found");
+				None
+			},
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn should_key_scores_by_did() {
+		let mapping: HashMap<_, _> =
+			[(0, "did:pkh:eth:0x1".to_string()), (1, "snap://a".to_string())].into();
+		let peer_trust: BTreeMap<_, _> = [(0, 0.5), (2, 0.5)].into();
+		let positive: BTreeMap<_, _> = [(0, 0.75)].into();
+		let scores: BTreeMap<_, _> = [(
+			"snap://a".to_string(),
+			SnapScore { value: 1., confidence: 0.5 },
+		)]
+		.into();
+		let thresholds = Thresholds { min_confidence: 0.25 };
+
+		let peers = peer_scores(&mapping, &peer_trust, Some(&positive));
+		let snaps = snap_scores(&scores, Some(&thresholds));
+
+		// 2 has no DID.
+		assert_eq!(peers.len(), 1);
+		assert_eq!(peers["did:pkh:eth:0x1"].value_before_discount, Some(0.75));
+		assert_eq!(snaps["snap://a"].confidence, Some(0.5));
+		assert_eq!(snaps["snap://a"].result, Some(1));
+		assert_eq!(snap_scores(&scores, None)["snap://a"].result, None);
+	}
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SnapScoreError {
+	#[error("gRPC error: {0}")]
+	Grpc(#[from] tonic::Status),
+
+	#[error("transport error: {0}")]
+	Transport(#[from] tonic::transport::Error),
+
+	#[error("invalid trust vector entry {0:?}")]
+	InvalidEntry(String),
+}
@@ -0,0 +1,6 @@
+pub mod badge;
+pub mod client;
+pub mod credential;
+pub mod error;
+pub mod score;
+pub mod version;
@@ -1,3 +1,198 @@
-fn main() {
-	println!("Hello, world!");
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use clap::{Parser as ClapParser, Subcommand as ClapSubcommand};
+use proto_buf::combiner::linear_combiner_client::LinearCombinerClient;
+use tonic::transport::{Channel, Endpoint};
+use tracing::{info, warn};
+use tracing_subscriber::filter::LevelFilter;
+use trustvector::service_client::ServiceClient as TrustVectorClient;
+
+use mm_spd_vc::manifest::ManifestBuilder;
+use mm_spd_vc::proof::Signer;
+use mm_spd_vc::publish::Publisher;
+use mm_spd_vc::trust_score::{TrustScoreCredentialBuilder, EIGENTRUST, TRUST_WEIGHTED_AVERAGE};
+use snap_score_computer::badge::{self, Badging};
+use snap_score_computer::client::{
+	get_did_mapping, get_opinions, get_peer_trust, get_timestamped_peer_trust, get_trust_arcs,
+	PEER_DID_PREFIX,
+};
+use snap_score_computer::credential;
+use snap_score_computer::version::{self, Packages};
+
+type BoxedError = Box<dyn std::error::Error>;
+
+#[derive(ClapSubcommand)]
+enum Command {
+	Scores(ScoresCmd),
+}
+
+/// Compute snap security scores.
+///
+/// Each output line has the snap DID, its score value and confidence level,
+/// separated by a space.
+/// Each snap version (e.g. `npm:foo@1.2.0`) is scored on the opinions about it alone,
+/// and each package (e.g. `npm:foo`) on those about all of its versions, rolled up.
+/// Versions are only told apart if the transformer's policy keeps them apart
+/// (`"snapVersions": "separate"`, as shipped); otherwise only packages are scored.
+/// If both the pre-trust and the positive-only peer trust are given,
+/// the community sentiment badge follows.
+///
+/// If a publish directory is given, the peer and snap scores are also published there
+/// as signed trust score credentials, along with a signed manifest of the epoch,
+/// i.e. the timestamp of the peer trust vector.
+#[derive(ClapParser)]
+struct ScoresCmd {
+	/// Peer trust vector ID (distrust-adjusted global trust).
+	#[arg(long)]
+	peer_trust_id: String,
+
+	/// Pre-trust vector ID, which determines the highly trusted auditors.
+	#[arg(long, requires = "positive_peer_trust_id")]
+	pre_trust_id: Option<String>,
+
+	/// Positive-only peer trust vector ID, which determines the badge thresholds.
+	#[arg(long, requires = "pre_trust_id")]
+	positive_peer_trust_id: Option<String>,
+
+	/// Directory to publish the trust score credentials and manifests into.
+	#[arg(long, requires = "signing_key")]
+	publish_dir: Option<PathBuf>,
+
+	/// Base URL the publish directory is served at, for the manifest locations.
+	#[arg(long, requires = "publish_dir")]
+	publish_base_url: Option<String>,
+
+	/// Hex-encoded secp256k1 secret key to sign the credentials and manifests with.
+	#[arg(long, env = "SNAP_SCORE_SIGNING_KEY", hide_env_values = true)]
+	signing_key: Option<String>,
+
+	/// Weight of the opinions about a previous snap version in its package score,
+	/// compounded per newer version: 0 to count the latest version only, 1 to count all alike.
+	#[arg(long, default_value_t = 0.5)]
+	carry_over: f64,
+}
+
+impl ScoresCmd {
+	async fn run(&self, cli: &Cli) -> Result<(), BoxedError> {
+		if !(0. ..=1.).contains(&self.carry_over) {
+			return Err(format!("carry-over {} is not between 0 and 1", self.carry_over).into());
+		}
+		let mut lc_client = cli.lc_client().await?;
+		let mapping = get_did_mapping(&mut lc_client).await?;
+		let opinions = get_opinions(&mut lc_client, &mapping).await?;
+		let mut tv_client = cli.tv_client().await?;
+		let (epoch, peer_trust) =
+			get_timestamped_peer_trust(&mut tv_client, &self.peer_trust_id).await?;
+
+		let mut positive_trust = None;
+		let badging = match (&self.pre_trust_id, &self.positive_peer_trust_id) {
+			(Some(pre_trust_id), Some(positive_peer_trust_id)) => {
+				let pre_trust = get_peer_trust(&mut tv_client, pre_trust_id).await?;
+				let positive = get_peer_trust(&mut tv_client, positive_peer_trust_id).await?;
+				let trust_arcs = get_trust_arcs(&mut lc_client, &mapping).await?;
+				let pre_trusted = pre_trust.iter().filter(|(_, v)| **v > 0.).map(|(p, _)| p);
+				let ph = badge::highly_trusted(pre_trusted, &trust_arcs);
+				if ph.is_empty() {
+					warn!("no highly trusted auditors");
+				}
+				let thresholds = badge::Thresholds::new(&ph, &positive);
+				positive_trust = Some(positive);
+				Badging::new(thresholds)
+			},
+			_ => Badging::Disabled,
+		};
+
+		// All the versions known, including those no one has opined on yet.
+		let packages =
+			Packages::new(mapping.values().filter(|did| !did.starts_with(PEER_DID_PREFIX)));
+		let opinions = version::by_did(&opinions, &mapping);
+		let scores = version::compute(&peer_trust, &opinions, &packages, self.carry_over);
+		for (did, score) in &scores {
+			if badging.is_enabled() {
+				println!(
+					"{} {} {} {}",
+					did,
+					score.value,
+					score.confidence,
+					badge::badge(badging.thresholds(), score)
+				);
+			} else {
+				println!("{} {} {}", did, score.value, score.confidence);
+			}
+		}
+
+		if let (Some(dir), Some(signing_key)) = (&self.publish_dir, &self.signing_key) {
+			let thresholds = badging.thresholds();
+			let signer = Signer::from_hex(signing_key)?;
+			let peer_scores =
+				credential::peer_scores(&mapping, &peer_trust, positive_trust.as_ref());
+			let snap_scores = credential::snap_scores(&scores, thresholds);
+			let mut credentials =
+				TrustScoreCredentialBuilder::new(&signer, EIGENTRUST, credential::SCOPE)
+					.build_all(&peer_scores)?;
+			credentials.extend(
+				TrustScoreCredentialBuilder::new(
+					&signer,
+					TRUST_WEIGHTED_AVERAGE,
+					credential::SCOPE,
+				)
+				.build_all(&snap_scores)?,
+			);
+
+			let manifest = ManifestBuilder::new(&signer, credential::SCOPE)
+				.trust_threshold(thresholds.map_or(0., |t| t.min_confidence));
+			let mut publisher = Publisher::new(dir);
+			if let Some(base_url) = &self.publish_base_url {
+				publisher = publisher.base_url(base_url);
+			}
+			let manifest = publisher.publish(&manifest, &epoch, &credentials)?;
+			info!(epoch, credentials = manifest.locations.len(), "published");
+		}
+		Ok(())
+	}
+}
+
+#[derive(ClapParser)]
+struct Cli {
+	/// Linear combiner gRPC endpoint.
+	#[arg(long, default_value = "http://[::1]:50052")]
+	combiner_grpc: Endpoint,
+
+	/// Trust vector server gRPC endpoint.
+	#[arg(long, default_value = "http://[::1]:8080")]
+	trust_vector_grpc: Endpoint,
+
+	/// Maximum logging level.
+	#[arg(long, default_value = "warn")]
+	log_level: LevelFilter,
+
+	#[command(subcommand)]
+	command: Command,
+}
+
+impl Cli {
+	async fn lc_client(&self) -> Result<LinearCombinerClient<Channel>, BoxedError> {
+		Ok(LinearCombinerClient::connect(self.combiner_grpc.clone()).await?)
+	}
+
+	async fn tv_client(&self) -> Result<TrustVectorClient<Channel>, BoxedError> {
+		Ok(TrustVectorClient::connect(self.trust_vector_grpc.clone()).await?)
+	}
+}
+
+#[tokio::main]
+async fn main() -> Result<(), BoxedError> {
+	let cli = Cli::parse();
+	use std::io::stderr;
+	let subscriber = tracing_subscriber::FmtSubscriber::builder()
+		.with_writer(stderr)
+		.with_ansi(stderr().is_terminal())
+		.with_max_level(cli.log_level)
+		.finish();
+	tracing::subscriber::set_global_default(subscriber)?;
+	match &cli.command {
+		Command::Scores(cmd) => cmd.run(&cli).await?,
+	}
+	Ok(())
 }
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+/// A peer's accumulated status opinion about a snap,
+/// i.e. the linear combiner's trust (endorse) and distrust (dispute) weights.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Opinion {
+	pub endorse: f64,
+	pub dispute: f64,
+}
+
+impl Opinion {
+	/// Rating R(s, p) in [0, 1]: the endorsing share of the opinion.
+	/// None if the peer has expressed no opinion.
+	pub fn rating(&self) -> Option<f64> {
+		let total = self.endorse + self.dispute;
+		(total > 0.).then_some(self.endorse / total)
+	}
+}
+
+/// Status opinions, keyed by (peer, snap).
+pub type Opinions<P, S> = BTreeMap<(P, S), Opinion>;
+
+/// Security score of a snap.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SnapScore {
+	/// Score value R_c(s), the trust-weighted average rating.
+	pub value: f64,
+	/// Score confidence level C(s), the sum of opiners' trust standings.
+	pub confidence: f64,
+}
+
+/// Compute the snap scores (Phase 2):
+///
+/// C(s) = sum of T(p), R_c(s) = sum of R(s, p) * T(p) / C(s)
+///
+/// over the peers p who opined on s.
+/// Only peers with positive (distrust-adjusted) trust standing T(p) count;
+/// a snap with no such opiners gets a zero value and confidence.
+pub fn compute<P: Ord, S: Ord + Clone>(
+	peer_trust: &BTreeMap<P, f64>, opinions: &Opinions<P, S>,
+) -> BTreeMap<S, SnapScore> {
+	let mut sums: BTreeMap<S, (f64, f64)> = BTreeMap::new();
+	for ((peer, snap), opinion) in opinions {
+		let sum = sums.entry(snap.clone()).or_default();
+		let (Some(rating), Some(t)) = (opinion.rating(), peer_trust.get(peer)) else {
+			continue;
+		};
+		if *t > 0. {
+			sum.0 += rating * t;
+			sum.1 += t;
+		}
+	}
+	sums.into_iter()
+		.map(|(snap, (weighted, confidence))| {
+			let value = if confidence > 0. { weighted / confidence } else { 0. };
+			(snap, SnapScore { value, confidence })
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn opinion(endorse: f64, dispute: f64) -> Opinion {
+		Opinion { endorse, dispute }
+	}
+
+	#[test]
+	fn should_rate_by_endorsing_share() {
+		assert_eq!(opinion(50., 0.).rating(), Some(1.));
+		assert_eq!(opinion(0., 50.).rating(), Some(0.));
+		assert_eq!(opinion(50., 150.).rating(), Some(0.25));
+		assert_eq!(opinion(0., 0.).rating(), None);
+	}
+
+	#[test]
+	fn should_weigh_ratings_by_peer_trust() {
+		let peer_trust: BTreeMap<_, _> =
+			[("x", 0.5), ("y", 0.25), ("p", -0.25), ("q", 0.)].into_iter().collect();
+		let opinions: Opinions<_, _> = [
+			(("x", "s1"), opinion(50., 0.)),
+			(("y", "s1"), opinion(0., 50.)),
+			(("p", "s1"), opinion(0., 50.)),
+			(("q", "s2"), opinion(50., 0.)),
+			(("z", "s2"), opinion(50., 0.)),
+		]
+		.into_iter()
+		.collect();
+
+		let scores = compute(&peer_trust, &opinions);
+
+		assert_eq!(
+			scores["s1"],
+			SnapScore { value: 0.5 / 0.75, confidence: 0.75 }
+		);
+		// Neither q (zero) nor z (unknown) has any standing.
+		assert_eq!(scores["s2"], SnapScore { value: 0., confidence: 0. });
+	}
+}
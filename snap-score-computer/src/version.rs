@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mm_spd_did::{CanonicalizeOptions, SnapId};
+use tracing::warn;
+
+use crate::score::{self, Opinion, Opinions, SnapScore};
+
+/// The versions of each snap package, by the DIDs of its subjects.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Packages {
+	/// Package DID and age of each subject, i.e. the number of stable package versions newer
+	/// than it. Subjects about the package as a whole are of age 0; those with a shasum but
+	/// no version are taken to be older than all the known stable versions.
+	versions: HashMap<String, (String, u32)>,
+}
+
+impl Packages {
+	/// Relate the given snap DIDs (e.g. `npm:foo@1.2.0`) to their packages (e.g. `npm:foo`).
+	/// DIDs that aren't snap ids make up packages of their own.
+	pub fn new<'a>(dids: impl IntoIterator<Item = &'a String>) -> Self {
+		let options = CanonicalizeOptions { keep_snap_version: true, ..Default::default() };
+		let mut packages: HashMap<String, Vec<(&String, Option<SnapId>)>> = HashMap::new();
+		for did in dids {
+			match SnapId::parse(did, &options) {
+				Ok(snap_id) => {
+					let package = snap_id.without_version().to_string();
+					packages.entry(package).or_default().push((did, Some(snap_id)));
+				},
+				Err(e) => {
+					warn!(did, err = %e, "not a snap id");
+					packages.entry(did.clone()).or_default().push((did, None));
+				},
+			}
+		}
+
+		let mut versions = HashMap::new();
+		for (package, dids) in packages {
+			// Distinct stable versions, newest first: only they age other versions,
+			// so that a pre-release doesn't discount the release it precedes.
+			let mut stable: Vec<&SnapId> = dids
+				.iter()
+				.filter_map(|(_, snap_id)| snap_id.as_ref())
+				.filter(|snap_id| snap_id.version().is_some() && !snap_id.is_pre_release())
+				.collect();
+			stable.sort_by(|a, b| b.cmp_version(a));
+			stable.dedup_by(|a, b| a.cmp_version(b).is_eq());
+
+			for (did, snap_id) in &dids {
+				let age = match snap_id {
+					Some(snap_id) if snap_id.version().is_some() => {
+						stable.iter().take_while(|newer| newer.cmp_version(snap_id).is_gt()).count()
+					},
+					// Only the shasum is known, so the version may be any but the newest ones.
+					Some(snap_id) if snap_id.shasum().is_some() => stable.len(),
+					// About the package as a whole, or not a snap id.
+					_ => 0,
+				};
+				versions.insert(did.to_string(), (package.clone(), age as u32));
+			}
+		}
+		Self { versions }
+	}
+
+	/// Package DID of the subject, if known.
+	pub fn package(&self, did: &str) -> Option<&str> {
+		self.versions.get(did).map(|(package, _)| package.as_str())
+	}
+
+	/// Number of package versions newer than the subject, if known.
+	pub fn age(&self, did: &str) -> Option<u32> {
+		self.versions.get(did).map(|(_, age)| *age)
+	}
+
+	/// Opinions about the packages: the opinions about each of their subjects,
+	/// weighted `carry_over` times less for every newer version.
+	pub fn roll_up<P: Ord + Clone>(
+		&self, opinions: &Opinions<P, String>, carry_over: f64,
+	) -> Opinions<P, String> {
+		let mut rolled_up = Opinions::new();
+		for ((peer, did), opinion) in opinions {
+			let (package, age) = match self.versions.get(did) {
+				Some((package, age)) => (package.clone(), *age),
+				None => (did.clone(), 0),
+			};
+			let weight = carry_over.powi(age as i32);
+			let sum: &mut Opinion = rolled_up.entry((peer.clone(), package)).or_default();
+			sum.endorse += opinion.endorse * weight;
+			sum.dispute += opinion.dispute * weight;
+		}
+		rolled_up
+	}
+}
+
+/// Opinions keyed by snap DID instead of numeric ID, leaving out snaps without a DID.
+pub fn by_did<P: Ord + Clone>(
+	opinions: &Opinions<P, u32>, mapping: &HashMap<u32, String>,
+) -> Opinions<P, String> {
+	opinions
+		.iter()
+		.filter_map(|((peer, snap), opinion)| {
+			mapping.get(snap).map(|did| ((peer.clone(), did.clone()), *opinion))
+		})
+		.collect()
+}
+
+/// Scores of each snap version on its own opinions, and of each package
+/// on the opinions rolled up from its versions (see [`Packages::roll_up`]).
+pub fn compute<P: Ord + Clone>(
+	peer_trust: &BTreeMap<P, f64>, opinions: &Opinions<P, String>, packages: &Packages,
+	carry_over: f64,
+) -> BTreeMap<String, SnapScore> {
+	let versioned: Opinions<P, String> = opinions
+		.iter()
+		.filter(|((_, did), _)| packages.package(did).map_or(false, |package| package != did))
+		.map(|(key, opinion)| (key.clone(), *opinion))
+		.collect();
+	let mut scores = score::compute(peer_trust, &versioned);
+	scores.extend(score::compute(
+		peer_trust,
+		&packages.roll_up(opinions, carry_over),
+	));
+	scores
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn dids(dids: &[&str]) -> Vec<String> {
+		dids.iter().map(|did| did.to_string()).collect()
+	}
+
+	#[test]
+	fn should_age_versions_by_precedence() {
+		let shasum = "rNyfINgNh161cBmUop+F7xlE+xEJQuIlkbnL1PZ0WcI=";
+		let dids = dids(&[
+			"npm:foo@1.0.0",
+			"npm:foo@1.10.0",
+			"npm:foo@1.2.0",
+			&format!("npm:foo@1.2.0#{}", shasum),
+			"npm:foo@2.0.0-beta.1",
+			"npm:foo@1.10.0-rc.1",
+			"npm:foo",
+			&format!("npm:foo#{}", shasum),
+			"npm:bar@0.1.0",
+			"snap://0xab",
+			"unknown:0xab",
+		]);
+		let packages = Packages::new(&dids);
+
+		// Only stable versions age the others: the beta of 2.0.0 doesn't age 1.10.0,
+		// while the release candidate of 1.10.0 is aged by it.
+		// The package as a whole is of age 0, and the shasum of an unknown version is the oldest.
+		let ages: Vec<_> = dids.iter().map(|did| packages.age(did).unwrap()).collect();
+		assert_eq!(ages, vec![2, 0, 1, 1, 0, 1, 0, 3, 0, 0, 0]);
+		assert_eq!(packages.package(&dids[3]), Some("npm:foo"));
+		assert_eq!(packages.package(&dids[7]), Some("npm:foo"));
+		assert_eq!(packages.package("snap://0xab"), Some("snap://0xab"));
+		assert_eq!(packages.package("unknown:0xab"), Some("unknown:0xab"));
+		assert_eq!(packages.package("npm:baz"), None);
+	}
+
+	#[test]
+	fn should_score_versions_and_roll_up_packages() {
+		let dids = dids(&["npm:foo@1.0.0", "npm:foo@2.0.0", "npm:foo"]);
+		let packages = Packages::new(&dids);
+		let peer_trust: BTreeMap<_, _> = [("x", 0.5), ("y", 0.25)].into_iter().collect();
+		let endorse = Opinion { endorse: 50., dispute: 0. };
+		let dispute = Opinion { endorse: 0., dispute: 50. };
+		let opinions: Opinions<_, _> = [
+			(("x", dids[0].clone()), dispute),
+			(("y", dids[1].clone()), endorse),
+			(("x", dids[2].clone()), endorse),
+		]
+		.into_iter()
+		.collect();
+
+		let scores = compute(&peer_trust, &opinions, &packages, 0.5);
+		assert_eq!(
+			scores["npm:foo@1.0.0"],
+			SnapScore { value: 0., confidence: 0.5 }
+		);
+		assert_eq!(
+			scores["npm:foo@2.0.0"],
+			SnapScore { value: 1., confidence: 0.25 }
+		);
+		// x's dispute of 1.0.0 carries over at half the weight of their endorsement of the package.
+		assert_eq!(
+			scores["npm:foo"],
+			SnapScore { value: (50. / 75. * 0.5 + 0.25) / 0.75, confidence: 0.75 }
+		);
+
+		// Without carry-over, only the latest version and the package itself count.
+		let scores = compute(&peer_trust, &opinions, &packages, 0.);
+		assert_eq!(scores["npm:foo"], SnapScore { value: 1., confidence: 0.75 });
+		let rolled_up = packages.roll_up(&opinions, 1.);
+		assert_eq!(
+			rolled_up[&("x", "npm:foo".to_string())],
+			Opinion { endorse: 50., dispute: 50. }
+		);
+	}
+
+	#[test]
+	fn should_key_opinions_by_did() {
+		let mapping: HashMap<_, _> = [(1, "npm:foo@1.0.0".to_string())].into();
+		let opinion = Opinion { endorse: 1., dispute: 0. };
+		let opinions: Opinions<_, _> = [((0, 1), opinion), ((0, 2), opinion)].into();
+		let by_did = by_did(&opinions, &mapping);
+		assert_eq!(by_did.len(), 1);
+		assert_eq!(by_did[&(0, "npm:foo@1.0.0".to_string())], opinion);
+	}
+}
@@ -6,25 +6,20 @@ use num::{BigUint, Zero};
 use tonic::transport::{Channel, Endpoint};
 use tracing_subscriber::filter::LevelFilter;
 
-use mm_spd_did::canonicalize_peer_did;
+use mm_spd_did::{CanonicalizeOptions, PeerDid, SubjectDid};
 use proto_buf::combiner;
 use proto_buf::combiner::linear_combiner_client::LinearCombinerClient;
-use thiserror::Error as ThisError;
 use tracing::error;
 use trustvector::service_client::ServiceClient as TrustVectorClient;
 use trustvector::Entry;
 
 type BoxedError = Box<dyn std::error::Error>;
 
-#[derive(Debug, ThisError)]
-enum Error {
-	#[error("cannot decode hex string: {0:?}")]
-	Hex2Bin(binascii::ConvertError),
-}
-
 async fn get_did_mapping(
 	client: &mut LinearCombinerClient<Channel>,
-) -> Result<HashMap<u32, String>, BoxedError> {
+) -> Result<HashMap<u32, SubjectDid>, BoxedError> {
+	// The combiner keeps the snap versions the transformer's policy separates.
+	let options = CanonicalizeOptions { keep_snap_version: true, ..Default::default() };
 	let mut m = HashMap::new();
 	let mut start = 0;
 	let mut more = true;
@@ -35,10 +30,7 @@ async fn get_did_mapping(
 			.await?
 			.into_inner();
 		while let Some(mapping) = stream.message().await? {
-			m.insert(
-				mapping.id,
-				canonicalize_peer_did(&unhexlify(&mapping.did)?)?,
-			);
+			m.insert(mapping.id, SubjectDid::parse(&mapping.did, &options)?);
 			more = true;
 			start += 1;
 		}
@@ -121,12 +113,6 @@ fn big_to_u64s(v: BigUint) -> Vec<u64> {
 	v.iter_u64_digits().rev().collect()
 }
 
-fn unhexlify(s: &str) -> Result<String, BoxedError> {
-	let mut buf = vec![0u8; s.len() / 2];
-	let converted = binascii::hex2bin(s.as_bytes(), buf.as_mut_slice()).map_err(Error::Hex2Bin)?;
-	Ok(String::from_utf8(Vec::from(converted))?)
-}
-
 fn now_ms() -> Result<BigUint, BoxedError> {
 	use std::time::{SystemTime, UNIX_EPOCH};
 	Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis().into())
@@ -194,7 +180,7 @@ struct ShowDidMappingCmd {}
 
 impl ShowDidMappingCmd {
 	async fn run(&self, cli: &Cli) -> Result<(), BoxedError> {
-		let m: BTreeMap<u32, String> =
+		let m: BTreeMap<u32, SubjectDid> =
 			get_did_mapping(&mut cli.lc_client().await?).await?.into_iter().collect();
 		for (id, did) in m {
 			println!("{} {}", id, did);
@@ -220,10 +206,10 @@ struct UpdateCmd {
 
 impl UpdateCmd {
 	async fn run(&self, cli: &Cli) -> Result<(), BoxedError> {
-		let m: HashMap<String, u32> = get_did_mapping(&mut cli.lc_client().await?)
+		let m: HashMap<PeerDid, u32> = get_did_mapping(&mut cli.lc_client().await?)
 			.await?
 			.into_iter()
-			.map(|(id, did)| (did, id))
+			.filter_map(|(id, did)| did.as_peer().map(|peer| (peer.clone(), id)))
 			.collect();
 		let mut updates = BTreeMap::new();
 		for (line_no, line) in std::io::stdin().lines().enumerate() {
@@ -236,11 +222,11 @@ impl UpdateCmd {
 					continue;
 				},
 			};
-			let did = canonicalize_peer_did(did)?;
+			let did: PeerDid = did.parse()?;
 			let id = match m.get(&did) {
 				Some(v) => v,
 				None => {
-					error!(line = line_no, did = did, "DID unknown to LC");
+					error!(line = line_no, did = %did, "DID unknown to LC");
 					continue;
 				},
 			};
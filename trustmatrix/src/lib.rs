@@ -121,10 +121,12 @@ where
 	}
 }
 
-fn qwords_to_big(u64s: &[u64]) -> BigUint {
+/// Convert big endian timestamp qwords into a big integer.
+pub fn qwords_to_big(u64s: &[u64]) -> BigUint {
 	u64s.iter().fold(BigUint::default(), |v, qw| (v << 64) | BigUint::from(*qw))
 }
 
-fn big_to_qwords(v: &BigUint) -> Vec<u64> {
+/// Convert a big integer into big endian timestamp qwords.
+pub fn big_to_qwords(v: &BigUint) -> Vec<u64> {
 	v.iter_u64_digits().rev().collect()
 }